@@ -45,6 +45,7 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     let identities: Vec<String> = cfg.repos.keys().cloned().collect();
 
     let mut repo_refs: BTreeMap<String, String> = BTreeMap::new();
+    let mut repo_bases: BTreeMap<String, String> = BTreeMap::new();
 
     if let Some(gn) = group_name {
         let group_repos = group::get(&cfg, gn)?;
@@ -54,8 +55,12 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     }
 
     for rn in &repo_args {
-        let (name, r) = giturl::parse_repo_ref(rn);
+        let (spec, b) = giturl::parse_repo_base(rn);
+        let (name, r) = giturl::parse_repo_ref(spec);
         let id = giturl::resolve(name, &identities)?;
+        if !b.is_empty() {
+            repo_bases.insert(id.clone(), b.to_string());
+        }
         repo_refs.insert(id, r.to_string());
     }
 
@@ -72,7 +77,13 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     }
 
     eprintln!("Adding {} repos to workspace...", repo_refs.len());
-    workspace::add_repos(&paths.mirrors_dir, &ws_dir, &repo_refs, &upstream_urls)?;
+    workspace::add_repos(
+        &paths.mirrors_dir,
+        &ws_dir,
+        &repo_refs,
+        &repo_bases,
+        &upstream_urls,
+    )?;
 
     match workspace::load_metadata(&ws_dir) {
         Ok(meta) => crate::lang::run_integrations(&ws_dir, &meta, &cfg),
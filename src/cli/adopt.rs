@@ -0,0 +1,82 @@
+use anyhow::Result;
+use clap::{Arg, ArgMatches, Command};
+
+use crate::config::{self, Paths};
+use crate::giturl;
+use crate::mirror;
+use crate::output::{MutationOutput, Output};
+use crate::workspace;
+
+use super::repo;
+
+pub fn cmd() -> Command {
+    Command::new("adopt")
+        .about("Turn an existing directory of clones into a workspace, moving it into the workspaces dir")
+        .arg(
+            Arg::new("dir")
+                .required(true)
+                .value_hint(clap::ValueHint::DirPath)
+                .help("Directory of clones; it is moved to <workspaces-dir>/<name>"),
+        )
+        .arg(
+            Arg::new("name")
+                .long("name")
+                .help("Workspace name [default: the directory's name]"),
+        )
+}
+
+pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    let dir_arg = matches.get_one::<String>("dir").unwrap();
+    let src = std::fs::canonicalize(dir_arg).map_err(|e| anyhow::anyhow!("{}: {}", dir_arg, e))?;
+    let name = match matches.get_one::<String>("name") {
+        Some(n) => n.clone(),
+        None => src
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    workspace::validate_name(&name)?;
+
+    let clones = workspace::scan_clones(&src)?;
+
+    // Register repos that aren't yet, so every clone has a mirror
+    let mut cfg = config::Config::load_from(&paths.config_path)
+        .map_err(|e| anyhow::anyhow!("loading config: {}", e))?;
+    let mut registered = false;
+    for clone in &clones {
+        if !cfg.repos.contains_key(&clone.identity) {
+            repo::register(paths, &mut cfg, &clone.url)?;
+            registered = true;
+        }
+    }
+    if registered {
+        cfg.save_to(&paths.config_path)
+            .map_err(|e| anyhow::anyhow!("saving config: {}", e))?;
+    }
+
+    // Fill the mirrors' refs/remotes/origin/*, which wsp-mirror fetches from
+    for clone in &clones {
+        let parsed = giturl::Parsed::from_identity(&clone.identity)?;
+        if let Err(e) = mirror::fetch_locked(paths, &parsed, false) {
+            eprintln!("  warning: fetching mirror for {}: {}", clone.identity, e);
+        }
+    }
+
+    eprintln!(
+        "Adopting {} repo(s) from {} as workspace {:?}...",
+        clones.len(),
+        src.display(),
+        name
+    );
+    let ws_dir = workspace::adopt(paths, &src, &name, cfg.branch_prefix.as_deref(), &clones)?;
+
+    match workspace::load_metadata(&ws_dir) {
+        Ok(meta) => crate::lang::run_integrations(&ws_dir, &meta, &cfg),
+        Err(e) => eprintln!("warning: skipping language integrations: {}", e),
+    }
+
+    Ok(Output::Mutation(MutationOutput {
+        ok: true,
+        message: format!("Workspace adopted: {}", ws_dir.display()),
+    }))
+}
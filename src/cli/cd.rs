@@ -1,3 +1,5 @@
+use std::path::{Path, PathBuf};
+
 use anyhow::{Result, bail};
 use clap::{Arg, ArgMatches, Command};
 use clap_complete::engine::ArgValueCandidates;
@@ -40,3 +42,49 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
         path: ws_dir.display().to_string(),
     }))
 }
+
+/// Asks the shell integration to change into `dir` once the command exits.
+/// The wrapper passes a file in `WSP_CD_FILE` and reads the directory back,
+/// so it never has to work out the workspace from the command's arguments.
+pub fn request(dir: &Path) {
+    let Some(file) = std::env::var_os("WSP_CD_FILE") else {
+        return;
+    };
+    if let Err(e) = std::fs::write(&file, dir.as_os_str().as_encoded_bytes()) {
+        eprintln!("warning: telling the shell where to cd: {}", e);
+    }
+}
+
+/// The shell's working directory. `$PWD` keeps the path the user typed
+/// (symlinks included), which is what the workspace root is compared with.
+pub fn shell_cwd() -> Option<PathBuf> {
+    std::env::var_os("PWD")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::current_dir().ok())
+}
+
+/// Where a shell inside `old` should end up once `old` has moved to `new`,
+/// or `None` if `cwd` is outside it.
+pub fn moved(cwd: &Path, old: &Path, new: &Path) -> Option<PathBuf> {
+    let rest = cwd.strip_prefix(old).ok()?;
+    Some(new.join(rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moved() {
+        let old = Path::new("/dev/old");
+        let new = Path::new("/dev/new");
+        assert_eq!(moved(old, old, new), Some(PathBuf::from("/dev/new")));
+        assert_eq!(
+            moved(Path::new("/dev/old/api/src"), old, new),
+            Some(PathBuf::from("/dev/new/api/src"))
+        );
+        assert_eq!(moved(Path::new("/dev/older"), old, new), None);
+        assert_eq!(moved(Path::new("/dev"), old, new), None);
+    }
+}
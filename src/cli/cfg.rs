@@ -31,38 +31,54 @@ pub fn unset_cmd() -> Command {
 
 pub fn run_list(_matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     let cfg = config::Config::load_from(&paths.config_path)?;
-    let mut entries = Vec::new();
-
-    // branch-prefix: show value or (not set)
-    entries.push(ConfigListEntry {
-        key: "branch-prefix".into(),
-        value: cfg
-            .branch_prefix
-            .as_deref()
-            .unwrap_or("(not set)")
-            .to_string(),
-    });
-
-    // workspaces-dir: show effective value (explicit or resolved default)
-    entries.push(ConfigListEntry {
-        key: "workspaces-dir".into(),
-        value: paths.workspaces_dir.display().to_string(),
-    });
-
-    // sync-strategy
-    entries.push(ConfigListEntry {
-        key: "sync-strategy".into(),
-        value: cfg.sync_strategy.as_deref().unwrap_or("rebase").to_string(),
-    });
+    let mut entries = vec![
+        // branch-prefix: show value or (not set)
+        ConfigListEntry {
+            key: "branch-prefix".into(),
+            value: cfg
+                .branch_prefix
+                .as_deref()
+                .unwrap_or("(not set)")
+                .to_string(),
+        },
+        // workspaces-dir: show effective value (explicit or resolved default)
+        ConfigListEntry {
+            key: "workspaces-dir".into(),
+            value: paths.workspaces_dir.display().to_string(),
+        },
+        ConfigListEntry {
+            key: "sync-strategy".into(),
+            value: cfg.sync_strategy.as_deref().unwrap_or("rebase").to_string(),
+        },
+        ConfigListEntry {
+            key: "sync-autostash".into(),
+            value: cfg.sync_autostash.unwrap_or(false).to_string(),
+        },
+        ConfigListEntry {
+            key: "trash-retention".into(),
+            value: cfg
+                .trash_retention
+                .as_deref()
+                .unwrap_or(config::DEFAULT_TRASH_RETENTION)
+                .to_string(),
+        },
+        ConfigListEntry {
+            key: "node-package-manager".into(),
+            value: cfg
+                .node_package_manager
+                .as_deref()
+                .unwrap_or(config::DEFAULT_NODE_PACKAGE_MANAGER)
+                .to_string(),
+        },
+        ConfigListEntry {
+            key: "python-venv".into(),
+            value: cfg.python_venv.unwrap_or(false).to_string(),
+        },
+    ];
 
     // language integrations: show effective value for all known integrations
     for name in crate::lang::integration_names() {
-        let enabled = cfg
-            .language_integrations
-            .as_ref()
-            .and_then(|m| m.get(name.as_str()))
-            .copied()
-            .unwrap_or(true);
+        let enabled = crate::lang::is_enabled(&cfg, &name);
         entries.push(ConfigListEntry {
             key: format!("language-integrations.{}", name),
             value: enabled.to_string(),
@@ -89,14 +105,35 @@ pub fn run_get(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
             key: key.clone(),
             value: Some(cfg.sync_strategy.as_deref().unwrap_or("rebase").to_string()),
         })),
+        "sync-autostash" => Ok(Output::ConfigGet(ConfigGetOutput {
+            key: key.clone(),
+            value: Some(cfg.sync_autostash.unwrap_or(false).to_string()),
+        })),
+        "trash-retention" => Ok(Output::ConfigGet(ConfigGetOutput {
+            key: key.clone(),
+            value: Some(
+                cfg.trash_retention
+                    .as_deref()
+                    .unwrap_or(config::DEFAULT_TRASH_RETENTION)
+                    .to_string(),
+            ),
+        })),
+        "node-package-manager" => Ok(Output::ConfigGet(ConfigGetOutput {
+            key: key.clone(),
+            value: Some(
+                cfg.node_package_manager
+                    .as_deref()
+                    .unwrap_or(config::DEFAULT_NODE_PACKAGE_MANAGER)
+                    .to_string(),
+            ),
+        })),
+        "python-venv" => Ok(Output::ConfigGet(ConfigGetOutput {
+            key: key.clone(),
+            value: Some(cfg.python_venv.unwrap_or(false).to_string()),
+        })),
         k if k.starts_with("language-integrations.") => {
             let lang = &k["language-integrations.".len()..];
-            let enabled = cfg
-                .language_integrations
-                .as_ref()
-                .and_then(|m| m.get(lang))
-                .copied()
-                .unwrap_or(true);
+            let enabled = crate::lang::is_enabled(&cfg, lang);
             Ok(Output::ConfigGet(ConfigGetOutput {
                 key: key.clone(),
                 value: Some(enabled.to_string()),
@@ -144,6 +181,49 @@ pub fn run_set(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
                 message: format!("sync-strategy = {}", value),
             }))
         }
+        "sync-autostash" => {
+            let enabled: bool = value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("value must be true or false"))?;
+            cfg.sync_autostash = Some(enabled);
+            cfg.save_to(&paths.config_path)?;
+            Ok(Output::Mutation(MutationOutput {
+                ok: true,
+                message: format!("sync-autostash = {}", enabled),
+            }))
+        }
+        "trash-retention" => {
+            config::parse_retention(value).map_err(|e| anyhow::anyhow!("{} (or `never`)", e))?;
+            cfg.trash_retention = Some(value.clone());
+            cfg.save_to(&paths.config_path)?;
+            Ok(Output::Mutation(MutationOutput {
+                ok: true,
+                message: format!("trash-retention = {}", value),
+            }))
+        }
+        "node-package-manager" => {
+            match value.as_str() {
+                "pnpm" | "npm" | "yarn" => {}
+                _ => bail!("node-package-manager must be 'pnpm', 'npm' or 'yarn'"),
+            }
+            cfg.node_package_manager = Some(value.clone());
+            cfg.save_to(&paths.config_path)?;
+            Ok(Output::Mutation(MutationOutput {
+                ok: true,
+                message: format!("node-package-manager = {}", value),
+            }))
+        }
+        "python-venv" => {
+            let enabled: bool = value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("value must be true or false"))?;
+            cfg.python_venv = Some(enabled);
+            cfg.save_to(&paths.config_path)?;
+            Ok(Output::Mutation(MutationOutput {
+                ok: true,
+                message: format!("python-venv = {}", enabled),
+            }))
+        }
         k if k.starts_with("language-integrations.") => {
             let lang = &k["language-integrations.".len()..];
             let known = crate::lang::integration_names();
@@ -194,6 +274,44 @@ pub fn run_unset(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
                 message: "sync-strategy unset (default: rebase)".into(),
             }))
         }
+        "sync-autostash" => {
+            cfg.sync_autostash = None;
+            cfg.save_to(&paths.config_path)?;
+            Ok(Output::Mutation(MutationOutput {
+                ok: true,
+                message: "sync-autostash unset (default: false)".into(),
+            }))
+        }
+        "trash-retention" => {
+            cfg.trash_retention = None;
+            cfg.save_to(&paths.config_path)?;
+            Ok(Output::Mutation(MutationOutput {
+                ok: true,
+                message: format!(
+                    "trash-retention unset (default: {})",
+                    config::DEFAULT_TRASH_RETENTION
+                ),
+            }))
+        }
+        "node-package-manager" => {
+            cfg.node_package_manager = None;
+            cfg.save_to(&paths.config_path)?;
+            Ok(Output::Mutation(MutationOutput {
+                ok: true,
+                message: format!(
+                    "node-package-manager unset (default: {})",
+                    config::DEFAULT_NODE_PACKAGE_MANAGER
+                ),
+            }))
+        }
+        "python-venv" => {
+            cfg.python_venv = None;
+            cfg.save_to(&paths.config_path)?;
+            Ok(Output::Mutation(MutationOutput {
+                ok: true,
+                message: "python-venv unset (default: false)".into(),
+            }))
+        }
         k if k.starts_with("language-integrations.") => {
             let lang = &k["language-integrations.".len()..];
             let known = crate::lang::integration_names();
@@ -209,7 +327,11 @@ pub fn run_unset(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
             cfg.save_to(&paths.config_path)?;
             Ok(Output::Mutation(MutationOutput {
                 ok: true,
-                message: format!("language-integrations.{} unset (default: true)", lang),
+                message: format!(
+                    "language-integrations.{} unset (default: {})",
+                    lang,
+                    crate::lang::enabled_by_default(lang)
+                ),
             }))
         }
         _ => bail!("unknown config key: {}", key),
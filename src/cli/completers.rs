@@ -73,6 +73,39 @@ pub fn complete_workspace_repos() -> Vec<CompletionCandidate> {
     repos_to_candidates(meta.repos.keys().cloned().collect())
 }
 
+/// Complete task names from the current workspace, or from every workspace
+/// when not inside one (for `wsp run <task> <workspace>`).
+pub fn complete_tasks() -> Vec<CompletionCandidate> {
+    let mut ws_dirs = Vec::new();
+    if let Some(ws_dir) = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| workspace::detect(&cwd).ok())
+    {
+        ws_dirs.push(ws_dir);
+    } else if let Ok(paths) = Paths::resolve()
+        && let Ok(names) = workspace::list_all(&paths.workspaces_dir)
+    {
+        ws_dirs.extend(
+            names
+                .iter()
+                .map(|n| workspace::dir(&paths.workspaces_dir, n)),
+        );
+    }
+
+    let mut tasks = std::collections::BTreeMap::new();
+    for ws_dir in ws_dirs {
+        if let Ok(meta) = workspace::load_metadata(&ws_dir) {
+            for (name, task) in meta.tasks {
+                tasks.entry(name).or_insert(task.command);
+            }
+        }
+    }
+    tasks
+        .into_iter()
+        .map(|(name, command)| CompletionCandidate::new(name).help(Some(command.into())))
+        .collect()
+}
+
 pub fn complete_workspaces() -> Vec<CompletionCandidate> {
     let Ok(paths) = Paths::resolve() else {
         return Vec::new();
@@ -104,7 +104,7 @@ fn build_posix_cases() -> Vec<ShellCase> {
     vec![
         ShellCase {
             pattern: "new".to_string(),
-            body: build_posix_cd_into("new"),
+            body: build_posix_cd_after("new"),
         },
         ShellCase {
             pattern: "cd".to_string(),
@@ -122,15 +122,29 @@ fn build_posix_cases() -> Vec<ShellCase> {
             pattern: "remove".to_string(),
             body: build_posix_cd_out("rm"),
         },
+        ShellCase {
+            pattern: "rename".to_string(),
+            body: build_posix_cd_after("rename"),
+        },
     ]
 }
 
-fn build_posix_cd_into(cmd_name: &str) -> String {
+/// Changes to the directory the command asks for through `WSP_CD_FILE`: the
+/// new workspace after `new`, or its new path after `rename` if the shell was
+/// inside it. Flags and templates make the name unsafe to take from `$1`.
+fn build_posix_cd_after(cmd_name: &str) -> String {
     format!(
         "shift\n\
-         \x20     command \"$wsp_bin\" {cmd_name} \"$@\" || return\n\
-         \x20     local wsp_dir=\"$wsp_root/$1\"\n\
-         \x20     cd \"$wsp_dir\"",
+         \x20     local cd_file rc dir\n\
+         \x20     cd_file=$(mktemp) || return\n\
+         \x20     WSP_CD_FILE=\"$cd_file\" command \"$wsp_bin\" {cmd_name} \"$@\"\n\
+         \x20     rc=$?\n\
+         \x20     dir=$(cat \"$cd_file\")\n\
+         \x20     rm -f \"$cd_file\"\n\
+         \x20     if [[ -n \"$dir\" ]]; then\n\
+         \x20       cd \"$dir\" || return\n\
+         \x20     fi\n\
+         \x20     return $rc",
     )
 }
 
@@ -174,11 +188,16 @@ function wsp\n\
     set -l wsp_root '{root_esc}'\n\
 \n\
     switch $argv[1]\n\
-        case new\n\
-            set -l args $argv[2..]\n\
-            command $wsp_bin new $args; or return\n\
-            set -l wsp_dir \"$wsp_root/$args[1]\"\n\
-            cd $wsp_dir\n\
+        case new rename\n\
+            set -l cd_file (mktemp); or return\n\
+            WSP_CD_FILE=$cd_file command $wsp_bin $argv\n\
+            set -l rc $status\n\
+            set -l dir (cat $cd_file)\n\
+            rm -f $cd_file\n\
+            if test -n \"$dir\"\n\
+                cd $dir; or return\n\
+            end\n\
+            return $rc\n\
 \n\
         case cd\n\
             set -l args $argv[2..]\n\
@@ -276,11 +295,72 @@ mod tests {
     #[test]
     fn test_posix_contains_all_cases() {
         let out = output(|w| write_posix(w, "/usr/bin/ws", "/home/user/dev", "zsh"));
-        for pattern in &["new)", "cd)", "rm)", "remove)", "*)"] {
+        for pattern in &["new)", "cd)", "rm)", "remove)", "rename)", "*)"] {
             assert!(out.contains(pattern), "missing case pattern: {}", pattern);
         }
     }
 
+    #[test]
+    fn test_posix_cd_after_with_flags_first() {
+        // A stand-in for wsp that records its arguments and asks for a cd.
+        let tmp = tempfile::tempdir().unwrap();
+        let target = tmp.path().join("target dir");
+        std::fs::create_dir(&target).unwrap();
+        let stub = tmp.path().join("wsp");
+        std::fs::write(
+            &stub,
+            format!(
+                "#!/bin/sh\n\
+                 [ -n \"$WSP_CD_FILE\" ] || exit 0\n\
+                 printf '%s\\n' \"$@\" > '{}'\n\
+                 printf '%s' '{}' > \"$WSP_CD_FILE\"\n",
+                tmp.path().join("args").display(),
+                target.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&stub, std::os::unix::fs::PermissionsExt::from_mode(0o755))
+            .unwrap();
+        let script = output(|w| {
+            write_posix(
+                w,
+                stub.to_str().unwrap(),
+                tmp.path().to_str().unwrap(),
+                "bash",
+            )
+        });
+
+        for args in [
+            "new --base dev foo api",
+            "new --from team.yaml",
+            "rename --keep-branch old new",
+        ] {
+            let out = std::process::Command::new("bash")
+                .arg("-c")
+                .arg(format!("{}\nwsp {} && pwd", script, args))
+                .current_dir(tmp.path())
+                .output()
+                .unwrap();
+            assert!(
+                out.status.success(),
+                "{}: {}",
+                args,
+                String::from_utf8_lossy(&out.stderr)
+            );
+            assert_eq!(
+                String::from_utf8_lossy(&out.stdout).trim(),
+                target.display().to_string(),
+                "{}",
+                args
+            );
+            let recorded = std::fs::read_to_string(tmp.path().join("args")).unwrap();
+            assert_eq!(
+                recorded.split_whitespace().collect::<Vec<_>>().join(" "),
+                args
+            );
+        }
+    }
+
     #[test]
     fn test_posix_shell_name_in_header() {
         let bash = output(|w| write_posix(w, "/usr/bin/ws", "/home/user/dev", "bash"));
@@ -318,7 +398,7 @@ mod tests {
     #[test]
     fn test_fish_contains_all_cases() {
         let out = output(|w| write_fish(w, "/usr/bin/ws", "/home/user/dev"));
-        for pattern in &["case new", "case cd", "case rm remove", "case '*'"] {
+        for pattern in &["case new rename", "case cd", "case rm remove", "case '*'"] {
             assert!(out.contains(pattern), "missing case pattern: {}", pattern);
         }
     }
@@ -36,10 +36,14 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     };
 
     eprintln!("Removing workspace {:?}...", name);
-    workspace::remove(paths, &name, force)?;
+    let reason = if force { "wsp rm --force" } else { "wsp rm" };
+    workspace::remove(paths, &name, force, reason)?;
 
     Ok(Output::Mutation(MutationOutput {
         ok: true,
-        message: format!("Workspace {:?} removed.", name),
+        message: format!(
+            "Workspace {:?} moved to the trash. Undo with `wsp trash restore {}`.",
+            name, name
+        ),
     }))
 }
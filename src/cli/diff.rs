@@ -9,16 +9,20 @@ use crate::git;
 use crate::output::{DiffOutput, Output, RepoDiffEntry};
 use crate::workspace;
 
+use super::select::{self, RepoSelector};
+
 pub fn cmd() -> Command {
-    Command::new("diff")
-        .about("Show git diff across workspace repos")
-        .arg(Arg::new("workspace"))
-        .arg(
-            Arg::new("args")
-                .num_args(1..)
-                .last(true)
-                .allow_hyphen_values(true),
-        )
+    select::add_args(
+        Command::new("diff")
+            .about("Show git diff across workspace repos")
+            .arg(Arg::new("workspace")),
+    )
+    .arg(
+        Arg::new("args")
+            .num_args(1..)
+            .last(true)
+            .allow_hyphen_values(true),
+    )
 }
 
 pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
@@ -40,28 +44,27 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     let is_json = matches.get_flag("json");
     let use_color = !is_json && std::io::stdout().is_terminal();
 
+    let selected = RepoSelector::from_matches(matches).apply(meta.repo_infos(&ws_dir))?;
     let mut repos = Vec::new();
-    for identity in meta.repos.keys() {
-        let dir_name = match meta.dir_name(identity) {
-            Ok(d) => d,
-            Err(e) => {
-                repos.push(RepoDiffEntry {
-                    name: identity.clone(),
-                    diff: String::new(),
-                    error: Some(e.to_string()),
-                });
-                continue;
-            }
-        };
+    for info in selected {
+        if let Some(e) = info.error {
+            repos.push(RepoDiffEntry {
+                name: info.identity,
+                diff: String::new(),
+                error: Some(e),
+            });
+            continue;
+        }
 
-        let repo_dir = ws_dir.join(&dir_name);
+        let dir_name = info.dir_name;
+        let repo_dir = info.clone_dir;
 
         let mut args = vec!["diff"];
         if use_color {
             args.push("--color=always");
         }
         let diff_base = if extra_args.is_empty() {
-            Some(resolve_diff_base(&repo_dir))
+            Some(resolve_diff_base(&repo_dir, info.base_ref.as_deref()))
         } else {
             None
         };
@@ -94,10 +97,10 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
 
 /// Pick the best ref to diff against: the merge-base between the upstream
 /// ref and HEAD, so only changes introduced by this branch are shown.
-fn resolve_diff_base(repo_dir: &Path) -> String {
-    let upstream = match git::resolve_upstream_ref(repo_dir) {
+fn resolve_diff_base(repo_dir: &Path, base_ref: Option<&str>) -> String {
+    let upstream = match git::resolve_upstream_ref(repo_dir, base_ref) {
         git::UpstreamRef::Tracking => "@{upstream}".to_string(),
-        git::UpstreamRef::DefaultBranch(b) => format!("origin/{}", b),
+        git::UpstreamRef::Base(r) => r,
         git::UpstreamRef::Head => return "HEAD".to_string(),
     };
     // Use merge-base so we only show changes introduced by this branch,
@@ -0,0 +1,608 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use clap::{Arg, ArgMatches, Command};
+
+use crate::config::{Config, Paths};
+use crate::git;
+use crate::giturl;
+use crate::lock;
+use crate::mirror;
+use crate::output::{DoctorFinding, DoctorOutput, Output, Severity};
+use crate::workspace;
+
+/// Files left behind by an interrupted atomic write: `go.work` from the Go
+/// integration, and `tempfile`'s `.tmpXXXXXX` for `.wsp.yaml`.
+const GO_WORK_TMP: &str = ".go.work.tmp";
+const TEMPFILE_PREFIX: &str = ".tmp";
+const TEMPFILE_RAND_LEN: usize = 6;
+
+/// Leftovers younger than this may belong to a write still in progress.
+const STRAY_MIN_AGE: Duration = Duration::from_secs(60);
+
+fn is_leftover(file_name: &str) -> bool {
+    file_name == GO_WORK_TMP
+        || file_name.strip_prefix(TEMPFILE_PREFIX).is_some_and(|rest| {
+            rest.len() == TEMPFILE_RAND_LEN && rest.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+pub fn cmd() -> Command {
+    Command::new("doctor")
+        .about("Check mirrors and workspaces for problems")
+        .arg(
+            Arg::new("fix")
+                .long("fix")
+                .action(clap::ArgAction::SetTrue)
+                .help("Repair what is safe to repair"),
+        )
+}
+
+pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    let fix = matches.get_flag("fix");
+    let findings = diagnose(paths, fix);
+    Ok(Output::Doctor(DoctorOutput { fix, findings }))
+}
+
+/// Collects findings, applying each repair as it goes when `fix` is set.
+struct Doctor {
+    fix: bool,
+    findings: Vec<DoctorFinding>,
+    /// Indexes into `findings` of repairs that edit `.wsp.yaml` in memory,
+    /// which only take effect once it is saved.
+    metadata_fixes: Vec<usize>,
+}
+
+impl Doctor {
+    fn report(&mut self, severity: Severity, scope: &str, message: String) {
+        self.findings.push(DoctorFinding {
+            severity,
+            scope: scope.to_string(),
+            message,
+            fix: None,
+            fixed: false,
+            error: None,
+        });
+    }
+
+    /// Reports a problem `repair` can fix, running it in `--fix` mode.
+    fn report_fixable(
+        &mut self,
+        severity: Severity,
+        scope: &str,
+        message: String,
+        fix: String,
+        repair: impl FnOnce() -> Result<()>,
+    ) {
+        let (fixed, error) = if self.fix {
+            match repair() {
+                Ok(()) => (true, None),
+                Err(e) => (false, Some(e.to_string())),
+            }
+        } else {
+            (false, None)
+        };
+        self.findings.push(DoctorFinding {
+            severity,
+            scope: scope.to_string(),
+            message,
+            fix: Some(fix),
+            fixed,
+            error,
+        });
+    }
+
+    /// Like `report_fixable`, for a repair that edits `.wsp.yaml`.
+    fn report_metadata_fix(
+        &mut self,
+        severity: Severity,
+        scope: &str,
+        message: String,
+        fix: String,
+        repair: impl FnOnce() -> Result<()>,
+    ) {
+        self.metadata_fixes.push(self.findings.len());
+        self.report_fixable(severity, scope, message, fix, repair);
+    }
+}
+
+fn diagnose(paths: &Paths, fix: bool) -> Vec<DoctorFinding> {
+    let mut doc = Doctor {
+        fix,
+        findings: Vec::new(),
+        metadata_fixes: Vec::new(),
+    };
+
+    // Without a readable config every URL comparison below would be noise.
+    let cfg = match Config::load_from(&paths.config_path) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            doc.report(
+                Severity::Error,
+                "config",
+                format!("cannot read {}: {}", paths.config_path.display(), e),
+            );
+            return doc.findings;
+        }
+    };
+
+    for (identity, entry) in &cfg.repos {
+        check_mirror(&mut doc, paths, identity, &entry.url);
+    }
+
+    match workspace::list_all(&paths.workspaces_dir) {
+        Ok(names) => {
+            for name in names {
+                // Repairs rewrite .wsp.yaml; don't race another wsp on it.
+                let _lock = match fix.then(|| lock::workspace(paths, &name)).transpose() {
+                    Ok(lock) => lock,
+                    Err(e) => {
+                        doc.report(Severity::Error, &name, e.to_string());
+                        continue;
+                    }
+                };
+                check_workspace(&mut doc, paths, &cfg, &name);
+            }
+        }
+        Err(e) => doc.report(
+            Severity::Error,
+            "workspaces",
+            format!("cannot list {}: {}", paths.workspaces_dir.display(), e),
+        ),
+    }
+
+    doc.findings
+}
+
+fn check_mirror(doc: &mut Doctor, paths: &Paths, identity: &str, url: &str) {
+    let scope = format!("mirror {}", identity);
+    let parsed = match giturl::Parsed::from_identity(identity) {
+        Ok(p) => p,
+        Err(e) => {
+            doc.report(Severity::Error, &scope, format!("invalid identity: {}", e));
+            return;
+        }
+    };
+    let mirror_dir = mirror::dir(&paths.mirrors_dir, &parsed);
+
+    if !mirror_dir.exists() {
+        doc.report_fixable(
+            Severity::Error,
+            &scope,
+            format!("mirror missing at {}", mirror_dir.display()),
+            format!("re-clone from {}", url),
+            || {
+                mirror::clone(&paths.mirrors_dir, &parsed, url)?;
+                mirror::fetch_locked(paths, &parsed, false)
+            },
+        );
+        return;
+    }
+
+    match git::remote_url(&mirror_dir, "origin") {
+        Ok(got) if got == url => {}
+        Ok(got) => doc.report_fixable(
+            Severity::Warning,
+            &scope,
+            format!("origin is {}, registered URL is {}", got, url),
+            "point origin at the registered URL".to_string(),
+            || git::remote_set_url(&mirror_dir, "origin", url),
+        ),
+        Err(_) => doc.report_fixable(
+            Severity::Error,
+            &scope,
+            "mirror has no origin remote".to_string(),
+            "re-add origin from the registered URL".to_string(),
+            || git::remote_set_url(&mirror_dir, "origin", url),
+        ),
+    }
+
+    if git::fetch_refspecs(&mirror_dir, "origin") != [git::MIRROR_REFSPEC] {
+        doc.report_fixable(
+            Severity::Warning,
+            &scope,
+            "origin fetch refspec is not the mirror default".to_string(),
+            format!("reset it to {}", git::MIRROR_REFSPEC),
+            || git::configure_fetch_refspec(&mirror_dir),
+        );
+    }
+}
+
+fn check_workspace(doc: &mut Doctor, paths: &Paths, cfg: &Config, name: &str) {
+    let ws_dir = workspace::dir(&paths.workspaces_dir, name);
+    let mut meta = match workspace::load_metadata(&ws_dir) {
+        Ok(m) => m,
+        Err(e) => {
+            doc.report(
+                Severity::Error,
+                name,
+                format!("cannot read {}: {}", workspace::METADATA_FILE, e),
+            );
+            return;
+        }
+    };
+    let mut dirty = false;
+
+    if let Some(parent) = meta.parent.clone()
+        && !workspace::dir(&paths.workspaces_dir, &parent)
+            .join(workspace::METADATA_FILE)
+            .exists()
+    {
+        doc.report_metadata_fix(
+            Severity::Warning,
+            name,
+            format!("parent workspace {:?} no longer exists", parent),
+            "drop the parent".to_string(),
+            || {
+                meta.parent = None;
+                dirty = true;
+                Ok(())
+            },
+        );
+    }
+
+    let orphan_dirs: Vec<String> = meta
+        .dirs
+        .keys()
+        .filter(|id| !meta.repos.contains_key(*id))
+        .cloned()
+        .collect();
+    for identity in orphan_dirs {
+        doc.report_metadata_fix(
+            Severity::Warning,
+            name,
+            format!(
+                "dir override for {}, which is not in the workspace",
+                identity
+            ),
+            "drop the override".to_string(),
+            || {
+                meta.dirs.remove(&identity);
+                dirty = true;
+                Ok(())
+            },
+        );
+    }
+
+    let identities: Vec<String> = meta.repos.keys().cloned().collect();
+    for identity in identities {
+        let dir_name = match meta.dir_name(&identity) {
+            Ok(d) => d,
+            Err(e) => {
+                doc.report(Severity::Error, name, format!("{}: {}", identity, e));
+                continue;
+            }
+        };
+        let clone_dir = ws_dir.join(&dir_name);
+        let scope = format!("{}/{}", name, dir_name);
+
+        if !clone_dir.exists() {
+            doc.report_metadata_fix(
+                Severity::Error,
+                &scope,
+                format!(
+                    "{} lists {} but the dir does not exist",
+                    workspace::METADATA_FILE,
+                    identity
+                ),
+                format!("drop {} from the workspace", identity),
+                || {
+                    meta.repos.remove(&identity);
+                    meta.dirs.remove(&identity);
+                    dirty = true;
+                    Ok(())
+                },
+            );
+            continue;
+        }
+        if !clone_dir.join(".git").exists() {
+            doc.report(Severity::Error, &scope, "not a git clone".to_string());
+            continue;
+        }
+
+        check_clone(doc, paths, cfg, &identity, &clone_dir, &scope);
+    }
+
+    let metadata_fixes = std::mem::take(&mut doc.metadata_fixes);
+    if dirty && let Err(e) = workspace::save_metadata(&ws_dir, &meta) {
+        doc.report(
+            Severity::Error,
+            name,
+            format!("saving {}: {}", workspace::METADATA_FILE, e),
+        );
+        for i in metadata_fixes {
+            doc.findings[i].fixed = false;
+        }
+    }
+
+    check_stray_files(doc, &ws_dir, name);
+}
+
+fn check_clone(
+    doc: &mut Doctor,
+    paths: &Paths,
+    cfg: &Config,
+    identity: &str,
+    clone_dir: &Path,
+    scope: &str,
+) {
+    if let Ok(parsed) = giturl::Parsed::from_identity(identity) {
+        let mirror_dir = mirror::dir(&paths.mirrors_dir, &parsed);
+        let mirror_url = mirror_dir.to_string_lossy().to_string();
+        let problem = match git::remote_url(clone_dir, "wsp-mirror") {
+            Ok(got) if got == mirror_url => None,
+            Ok(got) => Some(format!("wsp-mirror points at {}", got)),
+            Err(_) => Some("missing wsp-mirror remote".to_string()),
+        };
+        if let Some(message) = problem {
+            doc.report_fixable(
+                Severity::Warning,
+                scope,
+                message,
+                format!("point wsp-mirror at {}", mirror_dir.display()),
+                || {
+                    git::remote_set_url(clone_dir, "wsp-mirror", &mirror_url)?;
+                    git::configure_wsp_mirror_refspec(clone_dir)
+                },
+            );
+        } else if git::fetch_refspecs(clone_dir, "wsp-mirror") != [git::WSP_MIRROR_REFSPEC] {
+            doc.report_fixable(
+                Severity::Warning,
+                scope,
+                "wsp-mirror fetch refspec is not the default".to_string(),
+                format!("reset it to {}", git::WSP_MIRROR_REFSPEC),
+                || git::configure_wsp_mirror_refspec(clone_dir),
+            );
+        }
+    }
+
+    let Some(url) = cfg.upstream_url(identity) else {
+        doc.report(
+            Severity::Warning,
+            scope,
+            format!(
+                "{} is not registered; run `wsp setup repo add <url>`",
+                identity
+            ),
+        );
+        return;
+    };
+    match git::remote_url(clone_dir, "origin") {
+        Ok(got) if got == url => {}
+        Ok(got) => doc.report_fixable(
+            Severity::Warning,
+            scope,
+            format!("origin is {}, registered URL is {}", got, url),
+            "point origin at the registered URL".to_string(),
+            || git::remote_set_url(clone_dir, "origin", url),
+        ),
+        Err(_) => doc.report_fixable(
+            Severity::Warning,
+            scope,
+            "missing origin remote".to_string(),
+            "re-add origin from the registered URL".to_string(),
+            || git::remote_set_url(clone_dir, "origin", url),
+        ),
+    }
+}
+
+fn check_stray_files(doc: &mut Doctor, ws_dir: &Path, name: &str) {
+    let Ok(entries) = fs::read_dir(ws_dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    let mut stray: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .filter(|e| {
+            e.metadata()
+                .and_then(|m| m.modified())
+                .is_ok_and(|t| now.duration_since(t).unwrap_or_default() >= STRAY_MIN_AGE)
+        })
+        .filter_map(|e| e.file_name().to_str().map(str::to_string))
+        .filter(|f| is_leftover(f))
+        .collect();
+    stray.sort();
+    for file in stray {
+        let path = ws_dir.join(&file);
+        doc.report_fixable(
+            Severity::Warning,
+            name,
+            format!("leftover {} from an interrupted write", file),
+            format!("delete {}", file),
+            || {
+                fs::remove_file(&path)
+                    .map_err(|e| anyhow::anyhow!("removing {}: {}", path.display(), e))
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RepoEntry;
+    use crate::workspace::Bases;
+    use std::collections::BTreeMap;
+
+    /// A registered repo with a fetched mirror and a workspace `ws` using it.
+    fn setup() -> (Paths, String, String, Vec<tempfile::TempDir>) {
+        let (_clone, source, ct, st) = crate::testutil::setup_clone_repo();
+        let tmp = tempfile::tempdir().unwrap();
+        let workspaces_dir = tmp.path().join("workspaces");
+        fs::create_dir_all(&workspaces_dir).unwrap();
+        let paths = Paths::from_dirs(&tmp.path().join("data"), &workspaces_dir);
+
+        let url = source.to_str().unwrap().to_string();
+        let parsed = giturl::Parsed {
+            host: "test.local".into(),
+            owner: "user".into(),
+            repo: "api".into(),
+        };
+        let identity = parsed.identity();
+        let mut cfg = Config::default();
+        cfg.repos.insert(
+            identity.clone(),
+            RepoEntry {
+                url: url.clone(),
+                added: chrono::Utc::now(),
+            },
+        );
+        cfg.save_to(&paths.config_path).unwrap();
+        mirror::clone(&paths.mirrors_dir, &parsed, &url).unwrap();
+        mirror::fetch(&paths.mirrors_dir, &parsed).unwrap();
+
+        workspace::create(
+            &paths,
+            "ws",
+            &BTreeMap::from([(identity.clone(), String::new())]),
+            &Bases::default(),
+            None,
+            &BTreeMap::from([(identity.clone(), url.clone())]),
+        )
+        .unwrap();
+        (paths, identity, url, vec![ct, st, tmp])
+    }
+
+    fn backdate(path: &Path) {
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - 2 * STRAY_MIN_AGE)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_is_leftover() {
+        let cases = vec![
+            (".go.work.tmp", true),
+            (".tmpAb12Cd", true),
+            (".tmpAb12C", false),
+            (".tmpAb12Cd3", false),
+            (".tmp-b12Cd", false),
+            (".tmprc", false),
+            ("go.work", false),
+        ];
+        for (file, want) in cases {
+            assert_eq!(is_leftover(file), want, "{}", file);
+        }
+    }
+
+    #[test]
+    fn test_stray_files_skips_recent() {
+        let tmp = tempfile::tempdir().unwrap();
+        for file in [".tmpAAAAAA", ".tmpBBBBBB", ".tmprc"] {
+            fs::write(tmp.path().join(file), "").unwrap();
+        }
+        backdate(&tmp.path().join(".tmpAAAAAA"));
+        backdate(&tmp.path().join(".tmprc"));
+
+        let mut doc = Doctor {
+            fix: true,
+            findings: Vec::new(),
+            metadata_fixes: Vec::new(),
+        };
+        check_stray_files(&mut doc, tmp.path(), "ws");
+        let got: Vec<&str> = doc.findings.iter().map(|f| f.message.as_str()).collect();
+        assert_eq!(got, vec!["leftover .tmpAAAAAA from an interrupted write"]);
+        assert!(!tmp.path().join(".tmpAAAAAA").exists());
+        assert!(tmp.path().join(".tmpBBBBBB").exists());
+        assert!(tmp.path().join(".tmprc").exists());
+    }
+
+    #[test]
+    fn test_diagnose_healthy() {
+        let (paths, _identity, _url, _tmp) = setup();
+        let findings = diagnose(&paths, false);
+        assert!(
+            findings.is_empty(),
+            "{:?}",
+            findings.iter().map(|f| &f.message).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_diagnose_and_fix() {
+        let (paths, identity, url, _tmp) = setup();
+        let ws_dir = workspace::dir(&paths.workspaces_dir, "ws");
+        let clone_dir = ws_dir.join("api");
+        let mirror_dir = mirror::dir(
+            &paths.mirrors_dir,
+            &giturl::Parsed::from_identity(&identity).unwrap(),
+        );
+
+        // Break things the ways a user or a crash might.
+        git::run(Some(&clone_dir), &["remote", "remove", "wsp-mirror"]).unwrap();
+        git::remote_set_url(&clone_dir, "origin", "https://example.test/moved/api.git").unwrap();
+        let mut meta = workspace::load_metadata(&ws_dir).unwrap();
+        meta.repos.insert("test.local/user/gone".into(), None);
+        meta.dirs
+            .insert("test.local/user/removed".into(), "removed".into());
+        meta.parent = Some("no-such-ws".into());
+        workspace::save_metadata(&ws_dir, &meta).unwrap();
+        fs::write(ws_dir.join(GO_WORK_TMP), "go 1.22\n").unwrap();
+        backdate(&ws_dir.join(GO_WORK_TMP));
+        fs::remove_dir_all(&mirror_dir).unwrap();
+
+        let findings = diagnose(&paths, false);
+        let got: Vec<(&str, Severity, bool)> = findings
+            .iter()
+            .map(|f| (f.scope.as_str(), f.severity, f.fix.is_some()))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                ("mirror test.local/user/api", Severity::Error, true),
+                ("ws", Severity::Warning, true),     // parent
+                ("ws", Severity::Warning, true),     // dir override
+                ("ws/api", Severity::Warning, true), // wsp-mirror
+                ("ws/api", Severity::Warning, true), // origin
+                ("ws/gone", Severity::Error, true),
+                ("ws", Severity::Warning, true), // .go.work.tmp
+            ],
+            "{:#?}",
+            findings.iter().map(|f| &f.message).collect::<Vec<_>>()
+        );
+        assert!(findings.iter().all(|f| !f.fixed));
+        assert!(!mirror_dir.exists(), "diagnosing alone must not repair");
+
+        let fixed = diagnose(&paths, true);
+        assert!(
+            fixed.iter().all(|f| f.fixed),
+            "{:#?}",
+            fixed
+                .iter()
+                .map(|f| (&f.message, &f.error))
+                .collect::<Vec<_>>()
+        );
+        assert!(mirror_dir.exists());
+        assert_eq!(git::remote_url(&clone_dir, "origin").unwrap(), url);
+        assert!(!ws_dir.join(GO_WORK_TMP).exists());
+        let meta = workspace::load_metadata(&ws_dir).unwrap();
+        assert_eq!(meta.repos.keys().collect::<Vec<_>>(), vec![&identity]);
+        assert!(meta.dirs.is_empty());
+        assert!(meta.parent.is_none());
+
+        let again = diagnose(&paths, false);
+        assert!(
+            again.is_empty(),
+            "{:?}",
+            again.iter().map(|f| &f.message).collect::<Vec<_>>()
+        );
+        git::fetch_remote(&clone_dir, "wsp-mirror").unwrap();
+    }
+
+    #[test]
+    fn test_diagnose_unreadable_config() {
+        let tmp = tempfile::tempdir().unwrap();
+        let paths = Paths::from_dirs(tmp.path(), &tmp.path().join("workspaces"));
+        fs::write(&paths.config_path, "repos: [").unwrap();
+        let findings = diagnose(&paths, true);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].scope, "config");
+        assert_eq!(findings[0].severity, Severity::Error);
+    }
+}
@@ -1,88 +1,481 @@
-use std::path::Path;
-use std::process::Command as ProcessCommand;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command as ProcessCommand, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
 
-use anyhow::{Result, bail};
+use anyhow::Result;
 use clap::{Arg, ArgMatches, Command};
 use clap_complete::engine::ArgValueCandidates;
 
 use crate::config::Paths;
-use crate::output::Output;
-use crate::workspace;
+use crate::output::{ExecOutput, ExecRepoResult, Output};
+use crate::workspace::{self, Metadata, RepoInfo};
 
 use super::completers;
+use super::select::{self, RepoSelector};
 
 pub fn cmd() -> Command {
-    Command::new("exec")
-        .about("Run a command in each repo of a workspace")
-        .arg(
-            Arg::new("workspace")
-                .required(true)
-                .add(ArgValueCandidates::new(completers::complete_workspaces)),
-        )
-        .arg(Arg::new("command").required(true).num_args(1..).last(true))
+    select::add_args(
+        Command::new("exec")
+            .about("Run a command in each repo of a workspace")
+            .arg(
+                Arg::new("workspace")
+                    .required(true)
+                    .add(ArgValueCandidates::new(completers::complete_workspaces)),
+            )
+            .arg(parallel_arg()),
+    )
+    .arg(Arg::new("command").required(true).num_args(1..).last(true))
+}
+
+/// The `-p/--parallel[=N]` flag shared by `exec` and `run`.
+pub fn parallel_arg() -> Arg {
+    Arg::new("parallel")
+        .short('p')
+        .long("parallel")
+        .num_args(0..=1)
+        .require_equals(true)
+        .default_missing_value("0")
+        .value_parser(clap::value_parser!(usize))
+        .value_name("N")
+        .help("Run in parallel (at most N at a time, default: all repos)")
 }
 
 pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     let ws_name = matches.get_one::<String>("workspace").unwrap();
-    let command: Vec<&String> = matches.get_many::<String>("command").unwrap().collect();
+    let command: Vec<String> = matches
+        .get_many::<String>("command")
+        .unwrap()
+        .cloned()
+        .collect();
+    let parallel = matches.get_one::<usize>("parallel").copied();
+    let json = matches.get_flag("json");
 
     let ws_dir = workspace::dir(&paths.workspaces_dir, ws_name);
     let meta = workspace::load_metadata(&ws_dir)
         .map_err(|e| anyhow::anyhow!("reading workspace: {}", e))?;
 
-    let mut failed = 0;
-    for identity in meta.repos.keys() {
-        let dir_name = match meta.dir_name(identity) {
-            Ok(d) => d,
-            Err(e) => {
-                eprintln!("[{}] error: {}", identity, e);
-                failed += 1;
-                continue;
-            }
-        };
+    let repo_infos = RepoSelector::from_matches(matches).apply(meta.repo_infos(&ws_dir))?;
+    let env = WorkspaceEnv::new(&meta, &ws_dir);
+    let repos = run_in_repos(&env, &repo_infos, &command, parallel, json);
+
+    Ok(Output::Exec(ExecOutput {
+        workspace: meta.name,
+        task: None,
+        command: command.join(" "),
+        repos,
+    }))
+}
+
+/// Workspace context exported to every command as `WSP_*` environment variables.
+pub struct WorkspaceEnv {
+    pub name: String,
+    pub dir: PathBuf,
+    pub branch: String,
+}
+
+impl WorkspaceEnv {
+    pub fn new(meta: &Metadata, ws_dir: &Path) -> WorkspaceEnv {
+        WorkspaceEnv {
+            name: meta.name.clone(),
+            dir: ws_dir.to_path_buf(),
+            branch: meta.branch.clone(),
+        }
+    }
+
+    /// Variables for a command running in `info`'s clone. `WSP_PINNED_REF` is
+    /// empty for active repos.
+    pub fn vars(&self, info: &RepoInfo) -> Vec<(&'static str, String)> {
+        let role = if info.is_context { "context" } else { "active" };
+        vec![
+            ("WSP_WORKSPACE", self.name.clone()),
+            ("WSP_WORKSPACE_DIR", self.dir.display().to_string()),
+            ("WSP_BRANCH", self.branch.clone()),
+            ("WSP_REPO_IDENTITY", info.identity.clone()),
+            ("WSP_REPO_DIR", info.clone_dir.display().to_string()),
+            ("WSP_REPO_ROLE", role.to_string()),
+            (
+                "WSP_PINNED_REF",
+                info.pinned_ref.clone().unwrap_or_default(),
+            ),
+        ]
+    }
+}
+
+/// A command to run in one repo.
+pub struct ExecJob {
+    pub info: RepoInfo,
+    pub command: Vec<String>,
+    /// How the command is shown in the `==> [repo] ...` header.
+    pub label: String,
+    /// Extra environment variables for the child process.
+    pub env: Vec<(&'static str, String)>,
+}
+
+/// Runs `command` in every repo and collects per-repo results in input order.
+///
+/// With `parallel`, up to N commands run at once (0 = all) and their output is
+/// interleaved line-by-line with a `[dir_name]` prefix. Without it, commands run
+/// one at a time with the terminal attached. In `capture` mode (JSON output)
+/// nothing is streamed; stdout/stderr are recorded in the results instead.
+pub fn run_in_repos(
+    ws: &WorkspaceEnv,
+    repos: &[RepoInfo],
+    command: &[String],
+    parallel: Option<usize>,
+    capture: bool,
+) -> Vec<ExecRepoResult> {
+    let jobs: Vec<ExecJob> = repos
+        .iter()
+        .map(|info| ExecJob {
+            info: info.clone(),
+            command: command.to_vec(),
+            label: command.join(" "),
+            env: ws.vars(info),
+        })
+        .collect();
+    run_jobs(&jobs, parallel, capture)
+}
 
-        let repo_dir = ws_dir.join(&dir_name);
-        let cmd_str = command
+/// Like [`run_in_repos`], but each repo may run a different command.
+pub fn run_jobs(jobs: &[ExecJob], parallel: Option<usize>, capture: bool) -> Vec<ExecRepoResult> {
+    match parallel {
+        Some(n) => run_parallel(jobs, n, !capture),
+        None => jobs
             .iter()
-            .map(|s| s.as_str())
-            .collect::<Vec<_>>()
-            .join(" ");
-        println!("==> [{}] {}", dir_name, cmd_str);
-
-        match run_command(&command, &repo_dir) {
-            Ok(None) => {}
-            Ok(Some(code)) => {
-                eprintln!("[{}] error: exit status {}", dir_name, code);
-                failed += 1;
-            }
-            Err(e) => {
-                eprintln!("[{}] error: {}", dir_name, e);
-                failed += 1;
+            .map(|job| {
+                if capture {
+                    run_captured(job, None)
+                } else {
+                    run_inherited(job)
+                }
+            })
+            .collect(),
+    }
+}
+
+fn run_parallel(jobs: &[ExecJob], max: usize, stream: bool) -> Vec<ExecRepoResult> {
+    let workers = match max {
+        0 => jobs.len(),
+        n => n.min(jobs.len()),
+    };
+    let width = jobs
+        .iter()
+        .map(|j| j.info.dir_name.len())
+        .max()
+        .unwrap_or(0);
+    let printer = Mutex::new(());
+    let next = AtomicUsize::new(0);
+    let slots: Mutex<Vec<Option<ExecRepoResult>>> = Mutex::new(jobs.iter().map(|_| None).collect());
+
+    std::thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(job) = jobs.get(i) else {
+                        break;
+                    };
+                    let prefix = stream.then_some(LinePrefix {
+                        printer: &printer,
+                        width,
+                    });
+                    let result = run_captured(job, prefix);
+                    let mut slots = slots.lock().unwrap_or_else(|e| e.into_inner());
+                    slots[i] = Some(result);
+                }
+            });
+        }
+    });
+
+    slots
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Serial mode: stdin/stdout/stderr are attached to the terminal.
+fn run_inherited(job: &ExecJob) -> ExecRepoResult {
+    let info = &job.info;
+    if let Some(ref e) = info.error {
+        return failed_before_start(info, e.clone());
+    }
+
+    println!("==> [{}] {}", info.dir_name, job.label);
+    let start = Instant::now();
+    let status = build_command(job)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status();
+    println!();
+
+    match status {
+        Ok(status) => ExecRepoResult {
+            name: info.dir_name.clone(),
+            ok: status.success(),
+            exit_code: status.code(),
+            duration_ms: start.elapsed().as_millis() as u64,
+            stdout: String::new(),
+            stderr: String::new(),
+            error: None,
+        },
+        Err(e) => ExecRepoResult {
+            name: info.dir_name.clone(),
+            ok: false,
+            exit_code: None,
+            duration_ms: start.elapsed().as_millis() as u64,
+            stdout: String::new(),
+            stderr: String::new(),
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Shared state for printing `[dir_name] line` without tearing lines.
+#[derive(Clone, Copy)]
+struct LinePrefix<'a> {
+    printer: &'a Mutex<()>,
+    width: usize,
+}
+
+/// Runs the command with piped output, optionally echoing each line with a prefix.
+fn run_captured(job: &ExecJob, prefix: Option<LinePrefix<'_>>) -> ExecRepoResult {
+    let info = &job.info;
+    if let Some(ref e) = info.error {
+        return failed_before_start(info, e.clone());
+    }
+
+    let start = Instant::now();
+    let child = build_command(job)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(c) => c,
+        Err(e) => {
+            return ExecRepoResult {
+                name: info.dir_name.clone(),
+                ok: false,
+                exit_code: None,
+                duration_ms: start.elapsed().as_millis() as u64,
+                stdout: String::new(),
+                stderr: String::new(),
+                error: Some(e.to_string()),
+            };
+        }
+    };
+
+    let child_out = child.stdout.take();
+    let child_err = child.stderr.take();
+    let name = info.dir_name.as_str();
+    let (stdout, stderr) = std::thread::scope(|s| {
+        let out = s.spawn(|| drain_lines(child_out, name, prefix, false));
+        let err = s.spawn(|| drain_lines(child_err, name, prefix, true));
+        (
+            out.join().unwrap_or_default(),
+            err.join().unwrap_or_default(),
+        )
+    });
+
+    match child.wait() {
+        Ok(status) => ExecRepoResult {
+            name: info.dir_name.clone(),
+            ok: status.success(),
+            exit_code: status.code(),
+            duration_ms: start.elapsed().as_millis() as u64,
+            stdout,
+            stderr,
+            error: None,
+        },
+        Err(e) => ExecRepoResult {
+            name: info.dir_name.clone(),
+            ok: false,
+            exit_code: None,
+            duration_ms: start.elapsed().as_millis() as u64,
+            stdout,
+            stderr,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Reads a child pipe to EOF, returning everything read. When a prefix is
+/// given, each line is also echoed to our stdout/stderr as `[name] line`.
+fn drain_lines(
+    pipe: Option<impl Read>,
+    name: &str,
+    prefix: Option<LinePrefix<'_>>,
+    is_stderr: bool,
+) -> String {
+    let Some(pipe) = pipe else {
+        return String::new();
+    };
+    let mut reader = BufReader::new(pipe);
+    let mut captured = String::new();
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let line = String::from_utf8_lossy(&buf);
+        captured.push_str(&line);
+        if let Some(p) = prefix {
+            let text = line.trim_end_matches(['\n', '\r']);
+            let tag = format!("[{}]", name);
+            let _lock = p.printer.lock().unwrap_or_else(|e| e.into_inner());
+            if is_stderr {
+                let _ = writeln!(std::io::stderr(), "{:<w$} {}", tag, text, w = p.width + 2);
+            } else {
+                let _ = writeln!(std::io::stdout(), "{:<w$} {}", tag, text, w = p.width + 2);
             }
         }
-        println!();
     }
+    captured
+}
 
-    if failed > 0 {
-        bail!("{} command(s) failed", failed);
+/// A result for a repo whose command never ran.
+pub fn failed_before_start(info: &RepoInfo, error: String) -> ExecRepoResult {
+    ExecRepoResult {
+        name: info.dir_name.clone(),
+        ok: false,
+        exit_code: None,
+        duration_ms: 0,
+        stdout: String::new(),
+        stderr: String::new(),
+        error: Some(error),
     }
-    Ok(Output::None)
 }
 
-fn run_command(command: &[&String], dir: &Path) -> Result<Option<i32>> {
-    let mut cmd = ProcessCommand::new(command[0].as_str());
-    for arg in &command[1..] {
-        cmd.arg(arg.as_str());
+fn build_command(job: &ExecJob) -> ProcessCommand {
+    let mut cmd = ProcessCommand::new(&job.command[0]);
+    cmd.args(&job.command[1..]);
+    cmd.current_dir(&job.info.clone_dir);
+    cmd.envs(job.env.iter().map(|(k, v)| (k, v)));
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn make_repos(names: &[&str]) -> (Vec<RepoInfo>, tempfile::TempDir) {
+        let tmp = tempfile::tempdir().unwrap();
+        let repos = names
+            .iter()
+            .map(|n| {
+                let dir = tmp.path().join(n);
+                std::fs::create_dir_all(&dir).unwrap();
+                RepoInfo {
+                    identity: format!("github.com/acme/{}", n),
+                    dir_name: n.to_string(),
+                    clone_dir: dir,
+                    is_context: false,
+                    pinned_ref: None,
+                    base_ref: None,
+                    error: None,
+                }
+            })
+            .collect();
+        (repos, tmp)
+    }
+
+    fn ws_env() -> WorkspaceEnv {
+        WorkspaceEnv {
+            name: "my-ws".into(),
+            dir: PathBuf::from("/ws/my-ws"),
+            branch: "me/my-ws".into(),
+        }
+    }
+
+    fn sh(script: &str) -> Vec<String> {
+        vec!["sh".into(), "-c".into(), script.into()]
     }
-    cmd.current_dir(dir);
-    cmd.stdin(std::process::Stdio::inherit());
-    cmd.stdout(std::process::Stdio::inherit());
-    cmd.stderr(std::process::Stdio::inherit());
 
-    let status = cmd.status()?;
-    if status.success() {
-        Ok(None)
-    } else {
-        Ok(status.code())
+    #[test]
+    fn test_captured_records_output_and_exit_code() {
+        let (repos, _tmp) = make_repos(&["api", "web"]);
+        std::fs::write(repos[1].clone_dir.join("fail"), "").unwrap();
+
+        let cmd = sh("echo out; echo err >&2; test ! -f fail");
+        let results = run_in_repos(&ws_env(), &repos, &cmd, None, true);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name, "api");
+        assert!(results[0].ok);
+        assert_eq!(results[0].exit_code, Some(0));
+        assert_eq!(results[0].stdout, "out\n");
+        assert_eq!(results[0].stderr, "err\n");
+        assert_eq!(results[1].name, "web");
+        assert!(!results[1].ok);
+        assert_eq!(results[1].exit_code, Some(1));
+    }
+
+    #[test]
+    fn test_parallel_preserves_repo_order() {
+        let (repos, _tmp) = make_repos(&["a", "b", "c", "d"]);
+        let cmd = sh("basename \"$PWD\"");
+
+        for jobs in [0, 1, 2, 8] {
+            let results = run_in_repos(&ws_env(), &repos, &cmd, Some(jobs), true);
+            let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+            let outputs: Vec<&str> = results.iter().map(|r| r.stdout.trim()).collect();
+            assert_eq!(names, vec!["a", "b", "c", "d"], "jobs={}", jobs);
+            assert_eq!(outputs, vec!["a", "b", "c", "d"], "jobs={}", jobs);
+        }
+    }
+
+    #[test]
+    fn test_spawn_failure_and_repo_error() {
+        let (mut repos, _tmp) = make_repos(&["ok"]);
+        repos.push(RepoInfo {
+            identity: "broken".into(),
+            dir_name: "broken".into(),
+            clone_dir: PathBuf::new(),
+            is_context: false,
+            pinned_ref: None,
+            base_ref: None,
+            error: Some("invalid identity".into()),
+        });
+
+        let cmd = vec!["wsp-no-such-command-xyz".to_string()];
+        let results = run_in_repos(&ws_env(), &repos, &cmd, Some(0), true);
+
+        assert!(!results[0].ok);
+        assert_eq!(results[0].exit_code, None);
+        assert!(results[0].error.is_some());
+        assert!(!results[1].ok);
+        assert_eq!(results[1].error.as_deref(), Some("invalid identity"));
+    }
+
+    #[test]
+    fn test_exports_workspace_env() {
+        let (mut repos, _tmp) = make_repos(&["api", "proto"]);
+        repos[1].is_context = true;
+        repos[1].pinned_ref = Some("v1.0".into());
+
+        let cmd = sh(
+            "echo \"$WSP_WORKSPACE|$WSP_WORKSPACE_DIR|$WSP_BRANCH|$WSP_REPO_IDENTITY|$WSP_REPO_ROLE|$WSP_PINNED_REF\"; \
+             test \"$(cd \"$WSP_REPO_DIR\" && pwd -P)\" = \"$(pwd -P)\"",
+        );
+        let results = run_in_repos(&ws_env(), &repos, &cmd, None, true);
+
+        assert!(results[0].ok && results[1].ok);
+        assert_eq!(
+            results[0].stdout,
+            "my-ws|/ws/my-ws|me/my-ws|github.com/acme/api|active|\n"
+        );
+        assert_eq!(
+            results[1].stdout,
+            "my-ws|/ws/my-ws|me/my-ws|github.com/acme/proto|context|v1.0\n"
+        );
     }
 }
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Arg, ArgMatches, Command};
+use clap_complete::engine::ArgValueCandidates;
+
+use crate::config::{self, Paths};
+use crate::git;
+use crate::giturl;
+use crate::output::{ExportOutput, MutationOutput, Output};
+use crate::template::{self, WorkspaceTemplate};
+use crate::workspace;
+
+use super::completers;
+
+pub fn cmd() -> Command {
+    Command::new("export")
+        .about("Print a `wsp new` command or write a template for a workspace")
+        .arg(Arg::new("workspace").add(ArgValueCandidates::new(completers::complete_workspaces)))
+        .arg(
+            Arg::new("file")
+                .long("file")
+                .action(clap::ArgAction::SetTrue)
+                .help("Write <workspace>.wsp-template.yaml in the current directory"),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FILE")
+                .conflicts_with("file")
+                .value_hint(clap::ValueHint::FilePath)
+                .help("Write the template to FILE"),
+        )
+}
+
+pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    let ws_dir: PathBuf = if let Some(name) = matches.get_one::<String>("workspace") {
+        workspace::dir(&paths.workspaces_dir, name)
+    } else {
+        let cwd = std::env::current_dir()?;
+        workspace::detect(&cwd)?
+    };
+
+    let meta = workspace::load_metadata(&ws_dir)
+        .map_err(|e| anyhow::anyhow!("reading workspace: {}", e))?;
+    let cfg = config::Config::load_from(&paths.config_path)
+        .map_err(|e| anyhow::anyhow!("loading config: {}", e))?;
+
+    let path = match matches.get_one::<String>("output") {
+        Some(p) => Some(PathBuf::from(p)),
+        None if matches.get_flag("file") => Some(PathBuf::from(format!(
+            "{}{}",
+            meta.name,
+            template::TEMPLATE_SUFFIX
+        ))),
+        None => None,
+    };
+
+    let Some(path) = path else {
+        // Shortnames against every registered repo, so the command resolves
+        // the same way `wsp new` will
+        let registered: Vec<String> = cfg.repos.keys().cloned().collect();
+        let shortnames = giturl::shortnames(&registered);
+        return Ok(Output::Export(ExportOutput {
+            command: template::new_command(&meta, &shortnames),
+        }));
+    };
+
+    let mut urls = BTreeMap::new();
+    for identity in meta.repos.keys() {
+        let url = match cfg.upstream_url(identity) {
+            Some(u) => Some(u.to_string()),
+            None => meta
+                .dir_name(identity)
+                .ok()
+                .and_then(|dn| git::remote_url(&ws_dir.join(dn), "origin").ok()),
+        };
+        if let Some(url) = url {
+            urls.insert(identity.clone(), url);
+        }
+    }
+
+    let t = WorkspaceTemplate::from_workspace(&meta, urls);
+    template::save(&path, &t)?;
+
+    Ok(Output::Mutation(MutationOutput {
+        ok: true,
+        message: format!("Wrote {}", path.display()),
+    }))
+}
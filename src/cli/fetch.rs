@@ -4,7 +4,6 @@ use anyhow::{Result, bail};
 use clap::{ArgMatches, Command};
 
 use crate::config::{self, Paths};
-use crate::git;
 use crate::giturl;
 use crate::mirror;
 use crate::output::{FetchOutput, FetchRepoResult, Output};
@@ -61,10 +60,10 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     }
 
     // Phase 1: Fetch mirrors (network, parallel)
-    let repos: Vec<(String, std::path::PathBuf)> = identities
+    let repos: Vec<(String, giturl::Parsed)> = identities
         .into_iter()
         .filter_map(|id| match giturl::Parsed::from_identity(&id) {
-            Ok(parsed) => Some((id, mirror::dir(&paths.mirrors_dir, &parsed))),
+            Ok(parsed) => Some((id, parsed)),
             Err(e) => {
                 eprintln!("  {}: error parsing identity: {}", id, e);
                 None
@@ -89,11 +88,11 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     let results: Vec<(String, Result<()>)> = std::thread::scope(|s| {
         let handles: Vec<_> = repos
             .iter()
-            .map(|(id, mirror_dir)| {
+            .map(|(id, parsed)| {
                 let progress = &progress;
                 let shortnames = &shortnames;
                 s.spawn(move || {
-                    let result = git::fetch(mirror_dir, prune);
+                    let result = mirror::fetch_locked(paths, parsed, prune);
                     let _lock = progress.lock().unwrap();
                     let name = shortnames.get(id).map(|s| s.as_str()).unwrap_or(id);
                     match &result {
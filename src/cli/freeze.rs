@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Arg, ArgMatches, Command};
+use clap_complete::engine::ArgValueCandidates;
+
+use crate::config::Paths;
+use crate::lockfile;
+use crate::output::{MutationOutput, Output};
+use crate::workspace;
+
+use super::completers;
+
+pub fn cmd() -> Command {
+    Command::new("freeze")
+        .about("Record the exact commit of every repo in a lockfile")
+        .arg(Arg::new("workspace").add(ArgValueCandidates::new(completers::complete_workspaces)))
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FILE")
+                .value_hint(clap::ValueHint::FilePath)
+                .help("Where to write the lockfile [default: <workspace>/wsp.lock]"),
+        )
+}
+
+pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    let ws_dir: PathBuf = if let Some(name) = matches.get_one::<String>("workspace") {
+        workspace::dir(&paths.workspaces_dir, name)
+    } else {
+        let cwd = std::env::current_dir()?;
+        workspace::detect(&cwd)?
+    };
+
+    let meta = workspace::load_metadata(&ws_dir)
+        .map_err(|e| anyhow::anyhow!("reading workspace: {}", e))?;
+
+    let path = match matches.get_one::<String>("output") {
+        Some(p) => PathBuf::from(p),
+        None => ws_dir.join(lockfile::LOCK_FILE),
+    };
+
+    let lock = lockfile::capture(&ws_dir, &meta)?;
+    lockfile::save(&path, &lock)?;
+
+    Ok(Output::Mutation(MutationOutput {
+        ok: true,
+        message: format!("Froze {} repo(s) to {}", lock.repos.len(), path.display()),
+    }))
+}
@@ -0,0 +1,259 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use clap_complete::engine::ArgValueCandidates;
+
+use super::completers;
+use crate::config::Paths;
+use crate::git;
+use crate::giturl;
+use crate::journal;
+use crate::output::{HistoryEntry, HistoryOutput, HistoryRepo, Output, SyncOutput, SyncRepoResult};
+use crate::workspace;
+
+pub fn cmd() -> Command {
+    Command::new("history")
+        .about("Show what wsp changed, or restore the HEADs from before a command")
+        .arg(
+            Arg::new("workspace")
+                .help("Only show commands that touched this workspace")
+                .add(ArgValueCandidates::new(completers::complete_workspaces)),
+        )
+        .arg(
+            Arg::new("limit")
+                .short('n')
+                .long("limit")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("20")
+                .help("Show the last N entries"),
+        )
+        .arg(
+            Arg::new("restore")
+                .long("restore")
+                .value_name("ID")
+                .value_parser(clap::value_parser!(usize))
+                .conflicts_with("workspace")
+                .help("Undo sync entry ID: reset repos to their HEADs from before it"),
+        )
+        .arg(
+            Arg::new("repo")
+                .long("repo")
+                .value_name("REPO")
+                .action(ArgAction::Append)
+                .requires("restore")
+                .help("Only restore this repo (repeatable)"),
+        )
+}
+
+pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    if let Some(&id) = matches.get_one::<usize>("restore") {
+        return run_restore(matches, paths, id);
+    }
+
+    let filter = matches.get_one::<String>("workspace");
+    let limit = *matches.get_one::<usize>("limit").unwrap();
+    let entries: Vec<HistoryEntry> = journal::read(&paths.journal_path)?
+        .into_iter()
+        .rev()
+        .filter(|(_, e)| filter.is_none() || e.workspace.as_ref() == filter)
+        .take(limit)
+        .map(|(id, e)| {
+            let identities: Vec<String> = e.repos.iter().map(|r| r.identity.clone()).collect();
+            let shortnames = giturl::shortnames(&identities);
+            HistoryEntry {
+                id,
+                time: e.time,
+                command: e.command,
+                args: e.args,
+                workspace: e.workspace,
+                repos: e
+                    .repos
+                    .into_iter()
+                    .map(|r| HistoryRepo {
+                        name: shortnames
+                            .get(&r.identity)
+                            .cloned()
+                            .unwrap_or_else(|| r.identity.clone()),
+                        identity: r.identity,
+                        before: r.before,
+                        after: r.after,
+                    })
+                    .collect(),
+                ok: e.ok,
+                error: e.error,
+            }
+        })
+        .collect();
+    Ok(Output::History(HistoryOutput { entries }))
+}
+
+/// Resets each repo recorded in a sync's history entry to its HEAD from
+/// before the sync, like `wsp sync --abort` does for an interrupted sync.
+/// Other commands switch branches or create and delete clones, which a reset
+/// can't undo.
+fn run_restore(matches: &ArgMatches, paths: &Paths, id: usize) -> Result<Output> {
+    let entry = journal::find(&paths.journal_path, id)?;
+    if entry.command != "sync" {
+        bail!(
+            "history entry {} is a {:?}, only sync entries can be restored",
+            id,
+            entry.command
+        );
+    }
+    let Some(ref name) = entry.workspace else {
+        bail!("history entry {} did not change a workspace", id);
+    };
+    let ws_dir = workspace::dir(&paths.workspaces_dir, name);
+    let meta = workspace::load_metadata(&ws_dir)
+        .map_err(|e| anyhow::anyhow!("reading workspace {:?}: {}", name, e))?;
+
+    let restorable: Vec<String> = entry
+        .repos
+        .iter()
+        .filter(|r| r.before.is_some())
+        .map(|r| r.identity.clone())
+        .collect();
+    if restorable.is_empty() {
+        bail!("history entry {} has no HEADs to restore", id);
+    }
+    let selected = match matches.get_many::<String>("repo") {
+        Some(names) => names
+            .map(|n| giturl::resolve(n, &restorable))
+            .collect::<Result<Vec<_>>>()?,
+        None => restorable.clone(),
+    };
+    let shortnames = giturl::shortnames(&restorable);
+
+    let mut repos = Vec::new();
+    for r in entry
+        .repos
+        .iter()
+        .filter(|r| selected.contains(&r.identity))
+    {
+        let Some(ref sha) = r.before else { continue };
+        let dir = ws_dir.join(&r.dir);
+        let restored = if !meta.repos.contains_key(&r.identity) {
+            Err(anyhow::anyhow!("no longer in the workspace"))
+        } else {
+            restore_head(&dir, r, sha)
+        };
+        let (ok, detail, error) = match restored {
+            Ok(true) => (true, Some("restored".to_string()), None),
+            Ok(false) => (true, Some("already at that HEAD".to_string()), None),
+            Err(e) => (false, None, Some(e.to_string())),
+        };
+        repos.push(SyncRepoResult {
+            name: shortnames
+                .get(&r.identity)
+                .cloned()
+                .unwrap_or_else(|| r.identity.clone()),
+            action: format!("reset to {}", &sha[..sha.len().min(7)]),
+            ok,
+            detail,
+            error,
+            repo_dir: dir,
+            target: String::new(),
+            strategy: String::new(),
+        });
+    }
+
+    Ok(Output::Sync(SyncOutput {
+        workspace: meta.name,
+        branch: meta.branch,
+        dry_run: false,
+        repos,
+        stack: Vec::new(),
+    }))
+}
+
+/// Moves HEAD back to `sha`, keeping local changes. Returns false if it's
+/// already there. Refuses unless the clone is still on the recorded branch
+/// at the HEAD the command left it at, so later work isn't reset away.
+fn restore_head(dir: &Path, heads: &journal::RepoHeads, sha: &str) -> Result<bool> {
+    if git::rebase_in_progress(dir) || git::merge_in_progress(dir) {
+        bail!("rebase or merge in progress; finish or abort it first");
+    }
+    let Some(ref branch) = heads.branch else {
+        bail!("no branch recorded for this repo");
+    };
+    let current = git::branch_current(dir)?;
+    if &current != branch {
+        bail!(
+            "on {:?}, but the entry was recorded on {:?}",
+            current,
+            branch
+        );
+    }
+    let head = git::head_sha(dir)?;
+    if head == sha {
+        return Ok(false);
+    }
+    if heads.after.as_deref() != Some(head.as_str()) {
+        bail!("HEAD has moved since this entry; restore skipped");
+    }
+    git::reset_keep(dir, sha)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{local_commit, setup_clone_repo};
+
+    fn recorded(dir: &Path, before: &str) -> journal::RepoHeads {
+        journal::RepoHeads {
+            identity: "example.com/acme/api".into(),
+            dir: "api".into(),
+            branch: git::branch_current(dir).ok(),
+            before: Some(before.into()),
+            after: git::head_sha(dir).ok(),
+        }
+    }
+
+    #[test]
+    fn test_restore_head() {
+        let (dir, _source, _ct, _st) = setup_clone_repo();
+        let before = git::head_sha(&dir).unwrap();
+        local_commit(&dir, "a.txt", "a");
+        let heads = recorded(&dir, &before);
+
+        assert!(restore_head(&dir, &heads, &before).unwrap());
+        assert_eq!(git::head_sha(&dir).unwrap(), before);
+        assert!(!restore_head(&dir, &heads, &before).unwrap());
+    }
+
+    #[test]
+    fn test_restore_head_refuses_moved_head() {
+        let (dir, _source, _ct, _st) = setup_clone_repo();
+        let before = git::head_sha(&dir).unwrap();
+        local_commit(&dir, "a.txt", "a");
+        let heads = recorded(&dir, &before);
+        local_commit(&dir, "b.txt", "b");
+        let moved = git::head_sha(&dir).unwrap();
+
+        let err = restore_head(&dir, &heads, &before).unwrap_err();
+        assert!(err.to_string().contains("HEAD has moved"), "{}", err);
+        assert_eq!(git::head_sha(&dir).unwrap(), moved);
+    }
+
+    #[test]
+    fn test_restore_head_refuses_other_branch() {
+        let (dir, _source, _ct, _st) = setup_clone_repo();
+        let before = git::head_sha(&dir).unwrap();
+        local_commit(&dir, "a.txt", "a");
+        let heads = recorded(&dir, &before);
+        git::run(Some(&dir), &["checkout", "-q", "-b", "other"]).unwrap();
+
+        let err = restore_head(&dir, &heads, &before).unwrap_err();
+        assert!(err.to_string().contains("\"other\""), "{}", err);
+        assert_eq!(git::head_sha(&dir).unwrap(), heads.after.unwrap());
+
+        // An entry without a branch (older wsp) is never restored.
+        git::run(Some(&dir), &["checkout", "-q", "feature"]).unwrap();
+        let mut old = recorded(&dir, &before);
+        old.branch = None;
+        assert!(restore_head(&dir, &old, &before).is_err());
+    }
+}
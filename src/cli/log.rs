@@ -6,27 +6,32 @@ use clap::{Arg, ArgAction, ArgMatches, Command};
 use clap_complete::engine::ArgValueCandidates;
 
 use super::completers;
+use super::select::{self, RepoSelector};
 use crate::config::Paths;
 use crate::git;
 use crate::output::{LogCommit, LogOutput, Output, RepoLogEntry};
 use crate::workspace;
 
 pub fn cmd() -> Command {
-    Command::new("log")
-        .about("Show commits ahead of upstream per workspace repo")
-        .arg(Arg::new("workspace").add(ArgValueCandidates::new(completers::complete_workspaces)))
-        .arg(
-            Arg::new("oneline")
-                .long("oneline")
-                .action(ArgAction::SetTrue)
-                .help("Flat chronological view across all repos"),
-        )
-        .arg(
-            Arg::new("args")
-                .num_args(1..)
-                .last(true)
-                .allow_hyphen_values(true),
-        )
+    select::add_args(
+        Command::new("log")
+            .about("Show commits ahead of upstream per workspace repo")
+            .arg(
+                Arg::new("workspace").add(ArgValueCandidates::new(completers::complete_workspaces)),
+            )
+            .arg(
+                Arg::new("oneline")
+                    .long("oneline")
+                    .action(ArgAction::SetTrue)
+                    .help("Flat chronological view across all repos"),
+            ),
+    )
+    .arg(
+        Arg::new("args")
+            .num_args(1..)
+            .last(true)
+            .allow_hyphen_values(true),
+    )
 }
 
 pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
@@ -50,30 +55,25 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     let use_color = !is_json && !is_oneline && std::io::stdout().is_terminal();
 
     let mut repos = Vec::new();
-    for (identity, entry) in &meta.repos {
+    let selected = RepoSelector::from_matches(matches).apply(meta.repo_infos(&ws_dir))?;
+    for info in selected {
         // Skip context repos (pinned to a specific ref)
-        let is_context = match entry {
-            Some(re) => !re.r#ref.is_empty(),
-            None => false,
-        };
-        if is_context {
+        if info.is_context {
             continue;
         }
 
-        let dir_name = match meta.dir_name(identity) {
-            Ok(d) => d,
-            Err(e) => {
-                repos.push(RepoLogEntry {
-                    name: identity.clone(),
-                    commits: vec![],
-                    raw: None,
-                    error: Some(e.to_string()),
-                });
-                continue;
-            }
-        };
+        if let Some(e) = info.error {
+            repos.push(RepoLogEntry {
+                name: info.identity,
+                commits: vec![],
+                raw: None,
+                error: Some(e),
+            });
+            continue;
+        }
 
-        let repo_dir = ws_dir.join(&dir_name);
+        let dir_name = info.dir_name;
+        let repo_dir = info.clone_dir;
 
         if !extra_args.is_empty() {
             // Pass-through mode: run git log with user-supplied args verbatim
@@ -103,7 +103,7 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
             }
         } else {
             // Structured mode: parse commits from upstream..HEAD
-            match resolve_log_range(&repo_dir) {
+            match resolve_log_range(&repo_dir, info.base_ref.as_deref()) {
                 Some(range) => match fetch_commits(&repo_dir, &range) {
                     Ok(commits) => {
                         repos.push(RepoLogEntry {
@@ -143,10 +143,10 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
 
 /// Resolve the log range for the current branch relative to its upstream.
 /// Returns None if there's no upstream to compare against.
-fn resolve_log_range(repo_dir: &Path) -> Option<String> {
-    match git::resolve_upstream_ref(repo_dir) {
+fn resolve_log_range(repo_dir: &Path, base_ref: Option<&str>) -> Option<String> {
+    match git::resolve_upstream_ref(repo_dir, base_ref) {
         git::UpstreamRef::Tracking => Some("@{upstream}..HEAD".to_string()),
-        git::UpstreamRef::DefaultBranch(b) => Some(format!("origin/{}..HEAD", b)),
+        git::UpstreamRef::Base(r) => Some(format!("{}..HEAD", r)),
         git::UpstreamRef::Head => None,
     }
 }
@@ -309,18 +309,22 @@ mod tests {
             .unwrap();
         assert!(out.status.success());
 
-        let range = resolve_log_range(&clone_dir);
+        let range = resolve_log_range(&clone_dir, None);
         assert_eq!(range, Some("origin/main..HEAD".to_string()));
+
+        // A workspace base replaces the default branch
+        let range = resolve_log_range(&clone_dir, Some("origin/release/2.3"));
+        assert_eq!(range, Some("origin/release/2.3..HEAD".to_string()));
     }
 
     #[test]
     fn test_resolve_log_range_no_remote_falls_back_to_default_branch() {
         // Repo with no remote — default_branch() falls back to symbolic-ref HEAD,
-        // so resolve_log_range returns DefaultBranch("main") → "origin/main..HEAD".
+        // so resolve_log_range returns Base("origin/main") → "origin/main..HEAD".
         // In practice this range would fail at git log time since origin doesn't exist,
         // but workspace repos always have an origin.
         let (dir, _tmp) = setup_repo(1);
-        let range = resolve_log_range(&dir);
+        let range = resolve_log_range(&dir, None);
         assert_eq!(range, Some("origin/main..HEAD".to_string()));
     }
 
@@ -357,7 +361,7 @@ mod tests {
         assert!(out.status.success());
 
         // main tracks origin/main by default
-        let range = resolve_log_range(&clone_dir);
+        let range = resolve_log_range(&clone_dir, None);
         assert_eq!(range, Some("@{upstream}..HEAD".to_string()));
     }
 }
@@ -1,28 +1,45 @@
 pub mod add;
+pub mod adopt;
 pub mod cd;
 pub mod cfg;
 pub mod completers;
 pub mod completion;
 pub mod delete;
 pub mod diff;
+pub mod doctor;
 pub mod exec;
+pub mod export;
 pub mod fetch;
+pub mod freeze;
 pub mod group;
+pub mod history;
 pub mod list;
 pub mod log;
 pub mod new;
+pub mod pin;
+pub mod prune;
 pub mod push;
 pub mod remove;
+pub mod rename;
 pub mod repo;
 pub mod repo_list;
+pub mod run;
+pub mod select;
 pub mod skill;
 pub mod status;
 pub mod sync;
+pub mod trash;
 
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
 use clap::{Arg, ArgMatches, Command};
 
 use crate::config::Paths;
-use crate::output::Output;
+use crate::journal;
+use crate::lock;
+use crate::output::{self, Output};
 use crate::workspace;
 
 pub fn build_cli() -> Command {
@@ -31,7 +48,8 @@ pub fn build_cli() -> Command {
         .subcommand_required(true)
         .subcommand(repo::add_cmd())
         .subcommand(repo::list_cmd())
-        .subcommand(repo::remove_cmd());
+        .subcommand(repo::remove_cmd())
+        .subcommand(repo::gc_cmd());
 
     let group = Command::new("group")
         .about("Manage repo groups")
@@ -50,6 +68,13 @@ pub fn build_cli() -> Command {
         .subcommand(cfg::set_cmd())
         .subcommand(cfg::unset_cmd());
 
+    let trash_cmd = Command::new("trash")
+        .about("List, restore or delete removed workspaces")
+        .subcommand_required(true)
+        .subcommand(trash::list_cmd())
+        .subcommand(trash::restore_cmd())
+        .subcommand(trash::empty_cmd());
+
     let skill_cmd = Command::new("skill")
         .about("Manage Claude Code skills")
         .subcommand_required(true)
@@ -77,6 +102,9 @@ pub fn build_cli() -> Command {
         .subcommand_required(true)
         .subcommand(add::cmd())
         .subcommand(remove::cmd())
+        .subcommand(pin::activate_cmd())
+        .subcommand(pin::pin_cmd())
+        .subcommand(pin::update_pin_cmd())
         .subcommand(fetch::cmd())
         .subcommand(repo_list::cmd());
 
@@ -91,7 +119,13 @@ pub fn build_cli() -> Command {
                 .help("Output as JSON"),
         )
         .subcommand(new::cmd())
+        .subcommand(adopt::cmd())
         .subcommand(delete::cmd())
+        .subcommand(prune::cmd())
+        .subcommand(trash_cmd)
+        .subcommand(rename::cmd())
+        .subcommand(freeze::cmd())
+        .subcommand(export::cmd())
         .subcommand(repo_ws)
         .subcommand(list::cmd())
         .subcommand(status::cmd())
@@ -100,10 +134,205 @@ pub fn build_cli() -> Command {
         .subcommand(sync::cmd())
         .subcommand(push::cmd())
         .subcommand(exec::cmd())
+        .subcommand(run::cmd())
         .subcommand(cd::cmd())
+        .subcommand(history::cmd())
+        .subcommand(doctor::cmd())
         .subcommand(setup)
 }
 
+/// Which workspace a journaled command changes.
+enum Target {
+    /// Global state, or several workspaces at once.
+    Global,
+    /// Named by this argument, else the workspace containing the cwd.
+    Arg(&'static str),
+    /// The workspace containing the cwd.
+    Current,
+    /// A workspace the command creates.
+    Created,
+    /// The workspace renamed from `old` to `new`.
+    Renamed,
+    /// The workspace of the history entry being restored.
+    HistoryEntry,
+}
+
+/// Returns the subcommand path (e.g. `setup repo add`), its matches, and what
+/// it changes, for commands recorded in the journal.
+fn journal_target(matches: &ArgMatches) -> Option<(String, &ArgMatches, Target)> {
+    let mut path = Vec::new();
+    let mut m = matches;
+    while let Some((name, sub)) = m.subcommand() {
+        path.push(name);
+        m = sub;
+    }
+    let flag = |id: &str| matches!(m.try_get_one::<bool>(id), Ok(Some(true)));
+    if flag("dry-run") {
+        return None;
+    }
+    let command = path.join(" ");
+    let target = match command.as_str() {
+        "new" | "adopt" | "trash restore" => Target::Created,
+        "rm" | "sync" | "push" => Target::Arg("workspace"),
+        "rename" => Target::Renamed,
+        "repo add" | "repo rm" | "repo activate" | "repo pin" | "repo update-pin" => {
+            Target::Current
+        }
+        "history" if m.contains_id("restore") => Target::HistoryEntry,
+        "doctor" if flag("fix") => Target::Global,
+        "prune" | "trash empty" | "setup repo add" | "setup repo remove" | "setup repo gc"
+        | "setup group new" | "setup group update" | "setup group delete" | "setup config set"
+        | "setup config unset" => Target::Global,
+        _ => return None,
+    };
+    Some((command, m, target))
+}
+
+/// Runs a command. Mutating ones first take the locks they need, then are
+/// appended to the journal along with every affected clone's HEAD before and
+/// after. `args` are the arguments after `wsp`, recorded as typed.
+pub fn run(matches: &ArgMatches, paths: &Paths, args: Vec<String>) -> anyhow::Result<Output> {
+    let Some((command, m, target)) = journal_target(matches) else {
+        return dispatch(matches, paths);
+    };
+
+    let cwd_workspace = || {
+        std::env::current_dir()
+            .ok()
+            .and_then(|cwd| workspace::detect(&cwd).ok())
+    };
+    let named = |id: &str| {
+        m.get_one::<String>(id)
+            .map(|n| workspace::dir(&paths.workspaces_dir, n))
+    };
+    let before_dir: Option<PathBuf> = match target {
+        Target::Global | Target::Created => None,
+        Target::Arg(id) => named(id).or_else(cwd_workspace),
+        Target::Current => cwd_workspace(),
+        Target::Renamed => named("old"),
+        Target::HistoryEntry => m
+            .get_one::<usize>("restore")
+            .and_then(|&id| journal::find(&paths.journal_path, id).ok())
+            .and_then(|e| e.workspace)
+            .map(|n| workspace::dir(&paths.workspaces_dir, &n)),
+    };
+    let _locks = acquire_locks(&command, m, paths, &target, before_dir.as_deref())?;
+    let existing = match target {
+        Target::Created => workspace::list_all(&paths.workspaces_dir).unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let before = before_dir
+        .as_deref()
+        .map(journal::heads)
+        .unwrap_or_default();
+
+    let result = dispatch(matches, paths);
+
+    let after_dir = match target {
+        Target::Created => workspace::list_all(&paths.workspaces_dir)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|n| !existing.contains(n))
+            .map(|n| workspace::dir(&paths.workspaces_dir, &n)),
+        Target::Renamed => named("new"),
+        _ => before_dir.clone(),
+    };
+    let after = after_dir.as_deref().map(journal::heads).unwrap_or_default();
+
+    let (ok, error) = match &result {
+        Ok(out) if output::exit_code(out) == 0 => (true, None),
+        Ok(_) => (false, Some("finished with errors".to_string())),
+        Err(e) => (false, Some(e.to_string())),
+    };
+    let entry = journal::Entry {
+        time: Utc::now(),
+        command,
+        args,
+        workspace: after_dir
+            .or(before_dir)
+            .and_then(|d| d.file_name().map(|n| n.to_string_lossy().into_owned())),
+        repos: journal::diff_heads(&before, &after),
+        ok,
+        error,
+    };
+    if let Err(e) = journal::append(&paths.journal_path, &entry) {
+        eprintln!("warning: recording history: {}", e);
+    }
+    result
+}
+
+/// Takes the config lock for commands that rewrite `config.yaml`, then a lock
+/// for each workspace the command changes, in name order so two commands
+/// can't each hold a lock the other is waiting for.
+fn acquire_locks(
+    command: &str,
+    m: &ArgMatches,
+    paths: &Paths,
+    target: &Target,
+    ws_dir: Option<&Path>,
+) -> anyhow::Result<Vec<lock::Lock>> {
+    let mut locks = Vec::new();
+    if command.starts_with("setup ")
+        || command == "adopt"
+        || (command == "new" && m.contains_id("from"))
+    {
+        locks.push(lock::config(paths)?);
+    }
+
+    let mut names = BTreeSet::new();
+    let mut meta = None;
+    if let Some(dir) = ws_dir {
+        if let Some(name) = dir.file_name() {
+            names.insert(name.to_string_lossy().into_owned());
+        }
+        meta = workspace::load_metadata(dir).ok();
+    }
+    match target {
+        // The restore argument may be a trash ID; lock the name it restores to.
+        Target::Created if command == "trash restore" => {
+            if let Some(arg) = m.get_one::<String>("workspace")
+                && let Ok(entry) = crate::trash::find(&paths.trash_dir, arg)
+            {
+                names.insert(entry.manifest.name);
+            }
+        }
+        Target::Created => {
+            for id in ["workspace", "name"] {
+                if let Ok(Some(name)) = m.try_get_one::<String>(id) {
+                    names.insert(name.clone());
+                }
+            }
+        }
+        // Workspaces stacked on the old name get their `parent` rewritten.
+        Target::Renamed => {
+            names.extend(m.get_one::<String>("new").cloned());
+            if let Some(old) = m.get_one::<String>("old") {
+                for name in workspace::list_all(&paths.workspaces_dir).unwrap_or_default() {
+                    let dir = workspace::dir(&paths.workspaces_dir, &name);
+                    if workspace::load_metadata(&dir).is_ok_and(|c| c.parent.as_ref() == Some(old))
+                    {
+                        names.insert(name);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    // Syncing a stacked workspace syncs the ones below it first.
+    if command == "sync"
+        && let (Some(dir), Some(meta)) = (ws_dir, meta.as_ref())
+    {
+        for parent in workspace::ancestors(dir, meta).unwrap_or_default() {
+            names.insert(parent.meta.name);
+        }
+    }
+
+    for name in names {
+        locks.push(lock::workspace(paths, &name)?);
+    }
+    Ok(locks)
+}
+
 pub fn dispatch(matches: &ArgMatches, paths: &Paths) -> anyhow::Result<Output> {
     match matches.subcommand() {
         Some(("setup", sub)) => match sub.subcommand() {
@@ -111,6 +340,7 @@ pub fn dispatch(matches: &ArgMatches, paths: &Paths) -> anyhow::Result<Output> {
                 Some(("add", m)) => repo::run_add(m, paths),
                 Some(("list", m)) => repo::run_list(m, paths),
                 Some(("remove", m)) => repo::run_remove(m, paths),
+                Some(("gc", m)) => repo::run_gc(m, paths),
                 _ => unreachable!(),
             },
             Some(("group", sub2)) => match sub2.subcommand() {
@@ -138,12 +368,26 @@ pub fn dispatch(matches: &ArgMatches, paths: &Paths) -> anyhow::Result<Output> {
         Some(("repo", sub)) => match sub.subcommand() {
             Some(("add", m)) => add::run(m, paths),
             Some(("rm", m)) => remove::run(m, paths),
+            Some(("activate", m)) => pin::run_activate(m, paths),
+            Some(("pin", m)) => pin::run_pin(m, paths),
+            Some(("update-pin", m)) => pin::run_update_pin(m, paths),
             Some(("fetch", m)) => fetch::run(m, paths),
             Some(("ls", m)) => repo_list::run(m, paths),
             _ => unreachable!(),
         },
         Some(("new", m)) => new::run(m, paths),
+        Some(("adopt", m)) => adopt::run(m, paths),
         Some(("rm", m)) => delete::run(m, paths),
+        Some(("prune", m)) => prune::run(m, paths),
+        Some(("trash", sub)) => match sub.subcommand() {
+            Some(("list", m)) => trash::run_list(m, paths),
+            Some(("restore", m)) => trash::run_restore(m, paths),
+            Some(("empty", m)) => trash::run_empty(m, paths),
+            _ => unreachable!(),
+        },
+        Some(("rename", m)) => rename::run(m, paths),
+        Some(("freeze", m)) => freeze::run(m, paths),
+        Some(("export", m)) => export::run(m, paths),
         Some(("cd", m)) => cd::run(m, paths),
         Some(("ls", m)) => list::run(m, paths),
         Some(("st", m)) => status::run(m, paths),
@@ -152,6 +396,9 @@ pub fn dispatch(matches: &ArgMatches, paths: &Paths) -> anyhow::Result<Output> {
         Some(("sync", m)) => sync::run(m, paths),
         Some(("push", m)) => push::run(m, paths),
         Some(("exec", m)) => exec::run(m, paths),
+        Some(("run", m)) => run::run(m, paths),
+        Some(("history", m)) => history::run(m, paths),
+        Some(("doctor", m)) => doctor::run(m, paths),
         None => {
             let cwd = std::env::current_dir()?;
             if workspace::detect(&cwd).is_ok() {
@@ -168,3 +415,88 @@ pub fn dispatch(matches: &ArgMatches, paths: &Paths) -> anyhow::Result<Output> {
         _ => unreachable!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_target() {
+        let cases: Vec<(&[&str], Option<&str>)> = vec![
+            (&["sync"], Some("sync")),
+            (&["sync", "--dry-run"], None),
+            (&["st"], None),
+            (&["new", "ws", "api"], Some("new")),
+            (&["repo", "add", "api"], Some("repo add")),
+            (&["repo", "fetch"], None),
+            (
+                &["setup", "config", "set", "branch-prefix", "me"],
+                Some("setup config set"),
+            ),
+            (&["setup", "config", "get", "branch-prefix"], None),
+            (&["setup", "repo", "gc", "--dry-run"], None),
+            (&["doctor"], None),
+            (&["doctor", "--fix"], Some("doctor")),
+            (&["history"], None),
+            (&["history", "--restore", "3"], Some("history")),
+            (&["trash", "list"], None),
+            (&["trash", "empty"], Some("trash empty")),
+        ];
+        for (args, want) in cases {
+            let matches = build_cli()
+                .try_get_matches_from(std::iter::once("wsp").chain(args.iter().copied()))
+                .unwrap();
+            let got = journal_target(&matches).map(|(command, _, _)| command);
+            assert_eq!(got.as_deref(), want, "{:?}", args);
+        }
+    }
+
+    fn locks_for(paths: &Paths, args: &[&str]) -> anyhow::Result<Vec<lock::Lock>> {
+        let matches = build_cli()
+            .try_get_matches_from(std::iter::once("wsp").chain(args.iter().copied()))
+            .unwrap();
+        let (command, m, target) = journal_target(&matches).unwrap();
+        acquire_locks(&command, m, paths, &target, None)
+    }
+
+    #[test]
+    fn test_acquire_locks_rejects_bad_names() {
+        let tmp = tempfile::tempdir().unwrap();
+        let paths = Paths::from_dirs(&tmp.path().join("data"), &tmp.path().join("ws"));
+        let err = locks_for(&paths, &["new", "../../escaped", "api"])
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("path separators"), "{}", err);
+        assert!(!tmp.path().join("escaped.lock").exists());
+        assert!(!tmp.path().join("data").join("escaped.lock").exists());
+    }
+
+    #[test]
+    fn test_acquire_locks_trash_restore_by_id() {
+        let tmp = tempfile::tempdir().unwrap();
+        let paths = Paths::from_dirs(&tmp.path().join("data"), &tmp.path().join("ws"));
+        let ws_dir = workspace::dir(&paths.workspaces_dir, "bug-1");
+        std::fs::create_dir_all(&ws_dir).unwrap();
+        let meta = workspace::Metadata {
+            name: "bug-1".into(),
+            branch: "bug-1".into(),
+            repos: Default::default(),
+            created: Utc::now(),
+            base: None,
+            parent: None,
+            dirs: Default::default(),
+            tasks: Default::default(),
+        };
+        workspace::save_metadata(&ws_dir, &meta).unwrap();
+        let id = crate::trash::put(&paths.trash_dir, &ws_dir, &meta, "wsp rm").unwrap();
+
+        let _locks = locks_for(&paths, &["trash", "restore", &id]).unwrap();
+        // `wsp new bug-1` must wait for the restore.
+        let busy = lock::acquire(
+            &paths.locks_dir.join("workspaces").join("bug-1.lock"),
+            "bug-1",
+            std::time::Duration::ZERO,
+        );
+        assert!(busy.is_err());
+    }
+}
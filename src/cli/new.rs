@@ -9,16 +9,18 @@ use crate::config::{self, Paths};
 use crate::git;
 use crate::giturl;
 use crate::group;
+use crate::lockfile::{self, Role};
 use crate::mirror;
 use crate::output::{MutationOutput, Output};
+use crate::template;
 use crate::workspace;
 
-use super::completers;
+use super::{cd, completers, repo};
 
 pub fn cmd() -> Command {
     Command::new("new")
         .about("Create a new workspace")
-        .arg(Arg::new("workspace").required(true))
+        .arg(Arg::new("workspace").required_unless_present("from"))
         .arg(
             Arg::new("repos")
                 .num_args(0..)
@@ -31,6 +33,36 @@ pub fn cmd() -> Command {
                 .help("Add repos from a group")
                 .add(ArgValueCandidates::new(completers::complete_groups)),
         )
+        .arg(
+            Arg::new("base")
+                .long("base")
+                .value_name("BRANCH")
+                .help("Branch to start from and sync against (default: remote default branch)"),
+        )
+        .arg(
+            Arg::new("on")
+                .long("on")
+                .value_name("WORKSPACE")
+                .conflicts_with("base")
+                .help("Stack on another workspace, branching from its workspace branch")
+                .add(ArgValueCandidates::new(completers::complete_workspaces)),
+        )
+        .arg(
+            Arg::new("from-lock")
+                .long("from-lock")
+                .value_name("FILE")
+                .conflicts_with_all(["repos", "group", "base", "on"])
+                .value_hint(clap::ValueHint::FilePath)
+                .help("Recreate the repos and commits recorded by `wsp freeze`"),
+        )
+        .arg(
+            Arg::new("from")
+                .long("from")
+                .value_name("FILE")
+                .conflicts_with_all(["repos", "group", "on", "from-lock"])
+                .value_hint(clap::ValueHint::FilePath)
+                .help("Create from a template written by `wsp export` (`-` reads stdin)"),
+        )
         .arg(
             Arg::new("no-fetch")
                 .long("no-fetch")
@@ -40,20 +72,84 @@ pub fn cmd() -> Command {
 }
 
 pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
-    let ws_name = matches.get_one::<String>("workspace").unwrap();
-    let repo_args: Vec<&String> = matches
+    let template = match matches.get_one::<String>("from") {
+        Some(source) => Some(template::read(source)?),
+        None => None,
+    };
+    let ws_arg = matches.get_one::<String>("workspace").map(|s| s.as_str());
+    let ws_name = match &template {
+        Some(t) => t.workspace_name(ws_arg)?,
+        None => ws_arg.unwrap().to_string(),
+    };
+    let mut repo_args: Vec<String> = matches
         .get_many::<String>("repos")
-        .map(|v| v.collect())
+        .map(|v| v.cloned().collect())
         .unwrap_or_default();
     let group_name = matches.get_one::<String>("group");
+    // A template stacked on a workspace recreates the stack, unless --base
+    // picks a branch instead.
+    let cli_base = matches.get_one::<String>("base");
+    let parent = matches.get_one::<String>("on").cloned().or_else(|| {
+        template
+            .as_ref()
+            .filter(|_| cli_base.is_none())
+            .and_then(|t| t.parent.clone())
+    });
+    let base = cli_base.cloned().or_else(|| {
+        template
+            .as_ref()
+            .filter(|_| parent.is_none())
+            .and_then(|t| t.base.clone())
+    });
+    let from_lock = matches.get_one::<String>("from-lock");
     let no_fetch = matches.get_flag("no-fetch");
 
-    let cfg = config::Config::load_from(&paths.config_path)
+    let mut cfg = config::Config::load_from(&paths.config_path)
         .map_err(|e| anyhow::anyhow!("loading config: {}", e))?;
 
-    let identities: Vec<String> = cfg.repos.keys().cloned().collect();
+    // Check the template before registering the repos it brings URLs for,
+    // so a bad template or name doesn't leave them behind; then take its repo
+    // specs like repo args
+    let mut pending: Vec<(String, String)> = Vec::new();
+    if let Some(t) = &template {
+        workspace::validate_name(&ws_name)?;
+        if workspace::dir(&paths.workspaces_dir, &ws_name).exists() {
+            bail!("workspace {:?} already exists", ws_name);
+        }
+        for (id, url) in &t.urls {
+            if cfg.repos.contains_key(id) {
+                continue;
+            }
+            let url_identity = giturl::parse(url)?.identity();
+            if &url_identity != id {
+                bail!("template url {} is for {}, not {}", url, url_identity, id);
+            }
+            pending.push((id.clone(), url.clone()));
+        }
+        repo_args.extend(t.repos.iter().cloned());
+    }
+
+    let identities: Vec<String> = cfg
+        .repos
+        .keys()
+        .cloned()
+        .chain(pending.iter().map(|(id, _)| id.clone()))
+        .collect();
 
     let mut repo_refs: BTreeMap<String, String> = BTreeMap::new();
+    let mut bases = workspace::Bases {
+        default: base.clone(),
+        repos: BTreeMap::new(),
+        parent: parent.clone(),
+        commits: BTreeMap::new(),
+    };
+    if let Some(p) = &parent
+        && !workspace::dir(&paths.workspaces_dir, p)
+            .join(workspace::METADATA_FILE)
+            .exists()
+    {
+        bail!("workspace {:?} not found", p);
+    }
 
     // Add repos from group (active, no ref)
     if let Some(gn) = group_name {
@@ -63,17 +159,65 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
         }
     }
 
-    // Add individual repos (may have @ref)
+    // Add individual repos (may have @ref or ^base)
     for rn in &repo_args {
-        let (name, r) = giturl::parse_repo_ref(rn);
+        let (spec, b) = giturl::parse_repo_base(rn);
+        let (name, r) = giturl::parse_repo_ref(spec);
         let id = giturl::resolve(name, &identities)?;
+        if !b.is_empty() {
+            bases.repos.insert(id.clone(), b.to_string());
+        }
         repo_refs.insert(id, r.to_string());
     }
 
+    // Active repos branch at their locked commit, context repos are pinned to it
+    let lock = match from_lock {
+        Some(path) => Some(lockfile::load(std::path::Path::new(path))?),
+        None => None,
+    };
+    if let Some(lock) = &lock {
+        for (id, locked) in &lock.repos {
+            if !cfg.repos.contains_key(id) {
+                let url = if locked.url.is_empty() {
+                    "<url>"
+                } else {
+                    locked.url.as_str()
+                };
+                bail!(
+                    "repo {} is not registered; run `wsp setup repo add {}`",
+                    id,
+                    url
+                );
+            }
+            match locked.role {
+                Role::Active => {
+                    repo_refs.insert(id.clone(), String::new());
+                    bases.commits.insert(id.clone(), locked.sha.clone());
+                }
+                Role::Context => {
+                    repo_refs.insert(id.clone(), locked.sha.clone());
+                }
+            }
+        }
+    }
+
     if repo_refs.is_empty() {
         bail!("no repos specified (use repo args or --group)");
     }
 
+    // Everything checks out; clone the template's new repos. The config is
+    // saved only once the workspace exists.
+    let mut registered: Vec<String> = Vec::new();
+    for (_, url) in &pending {
+        match repo::register(paths, &mut cfg, url) {
+            Ok(id) => registered.push(id),
+            Err(e) => {
+                unregister(paths, &registered);
+                return Err(e);
+            }
+        }
+    }
+
     // Build upstream URL map from config
     let mut upstream_urls: BTreeMap<String, String> = BTreeMap::new();
     for identity in repo_refs.keys() {
@@ -84,12 +228,12 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
 
     // Pre-fetch mirrors (parallel) unless --no-fetch
     if !no_fetch {
-        let mirrors: Vec<(String, std::path::PathBuf)> = repo_refs
+        let mirrors: Vec<(String, giturl::Parsed)> = repo_refs
             .keys()
             .filter_map(|id| {
                 giturl::Parsed::from_identity(id)
                     .ok()
-                    .map(|p| (id.clone(), mirror::dir(&paths.mirrors_dir, &p)))
+                    .map(|p| (id.clone(), p))
             })
             .collect();
 
@@ -99,10 +243,10 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
             std::thread::scope(|s| {
                 let handles: Vec<_> = mirrors
                     .iter()
-                    .map(|(id, mirror_dir)| {
+                    .map(|(id, parsed)| {
                         let progress = &progress;
                         s.spawn(move || {
-                            let result = git::fetch(mirror_dir, true);
+                            let result = mirror::fetch_locked(paths, parsed, true);
                             let _lock = progress.lock().unwrap();
                             match &result {
                                 Ok(()) => eprintln!("  ok    {}", id),
@@ -118,28 +262,86 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
         }
     }
 
+    if let Some(lock) = &lock {
+        let mut missing = String::new();
+        for (id, locked) in &lock.repos {
+            let parsed = giturl::Parsed::from_identity(id)?;
+            if !git::commit_exists(&mirror::dir(&paths.mirrors_dir, &parsed), &locked.sha) {
+                missing.push_str(&format!("\n  - {} ({})", id, locked.sha));
+            }
+        }
+        if !missing.is_empty() {
+            bail!(
+                "commits in the lock are missing from the mirrors:{}\n\nWere they pushed upstream?",
+                missing
+            );
+        }
+    }
+
     let branch_prefix = cfg.branch_prefix.as_deref();
     let branch = match branch_prefix.filter(|p| !p.is_empty()) {
         Some(prefix) => format!("{}/{}", prefix, ws_name),
         None => ws_name.to_string(),
     };
 
+    let base_note = match (&base, &parent, from_lock) {
+        (Some(b), _, _) => format!(", base: {}", b),
+        (None, Some(p), _) => format!(", on: {}", p),
+        (None, None, Some(f)) => format!(", from: {}", f),
+        (None, None, None) => String::new(),
+    };
     eprintln!(
-        "Creating workspace {:?} (branch: {}) with {} repos...",
+        "Creating workspace {:?} (branch: {}{}) with {} repos...",
         ws_name,
         branch,
+        base_note,
         repo_refs.len()
     );
-    workspace::create(paths, ws_name, &repo_refs, branch_prefix, &upstream_urls)?;
+    if let Err(e) = workspace::create(
+        paths,
+        &ws_name,
+        &repo_refs,
+        &bases,
+        branch_prefix,
+        &upstream_urls,
+    ) {
+        unregister(paths, &registered);
+        return Err(e);
+    }
+    if !registered.is_empty() {
+        cfg.save_to(&paths.config_path)
+            .map_err(|e| anyhow::anyhow!("saving config: {}", e))?;
+    }
 
-    let ws_dir = workspace::dir(&paths.workspaces_dir, ws_name);
+    let ws_dir = workspace::dir(&paths.workspaces_dir, &ws_name);
+    if let Some(t) = &template
+        && !t.tasks.is_empty()
+    {
+        let mut meta = workspace::load_metadata(&ws_dir)?;
+        meta.tasks = t.tasks.clone();
+        workspace::save_metadata(&ws_dir, &meta)?;
+    }
     match workspace::load_metadata(&ws_dir) {
         Ok(meta) => crate::lang::run_integrations(&ws_dir, &meta, &cfg),
         Err(e) => eprintln!("warning: skipping language integrations: {}", e),
     }
 
+    cd::request(&ws_dir);
+
     Ok(Output::Mutation(MutationOutput {
         ok: true,
         message: format!("Workspace created: {}", ws_dir.display()),
     }))
 }
+
+/// Removes the mirrors of repos registered for a template whose workspace
+/// could not be created.
+fn unregister(paths: &Paths, identities: &[String]) {
+    for id in identities {
+        if let Ok(parsed) = giturl::Parsed::from_identity(id)
+            && let Err(e) = mirror::remove(&paths.mirrors_dir, &parsed)
+        {
+            eprintln!("warning: removing mirror for {}: {}", id, e);
+        }
+    }
+}
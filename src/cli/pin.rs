@@ -0,0 +1,230 @@
+use anyhow::{Result, bail};
+use clap::{Arg, ArgMatches, Command};
+use clap_complete::engine::ArgValueCandidates;
+
+use crate::config::{self, Paths};
+use crate::git;
+use crate::giturl;
+use crate::mirror;
+use crate::output::{MutationOutput, Output, PinUpdateEntry, PinUpdateOutput};
+use crate::workspace::{self, Metadata};
+
+use super::completers;
+
+pub fn activate_cmd() -> Command {
+    Command::new("activate")
+        .about("Turn a context repo into an active repo on the workspace branch")
+        .arg(Arg::new("repo").required(true).add(ArgValueCandidates::new(
+            completers::complete_workspace_repos,
+        )))
+}
+
+pub fn pin_cmd() -> Command {
+    Command::new("pin")
+        .about("Turn an active repo into a context repo pinned to a ref")
+        .arg(Arg::new("repo").required(true).add(ArgValueCandidates::new(
+            completers::complete_workspace_repos,
+        )))
+        .arg(
+            Arg::new("ref")
+                .required(true)
+                .help("Branch, tag or SHA to check out"),
+        )
+        .arg(
+            Arg::new("force")
+                .short('f')
+                .long("force")
+                .action(clap::ArgAction::SetTrue)
+                .help("Pin even if the repo has pending changes or an unmerged branch"),
+        )
+}
+
+pub fn update_pin_cmd() -> Command {
+    Command::new("update-pin")
+        .about("Show newer refs for context repos, or move their pins")
+        .arg(
+            Arg::new("repos")
+                .num_args(0..)
+                .help("Context repos to check [default: all]")
+                .add(ArgValueCandidates::new(
+                    completers::complete_workspace_repos,
+                )),
+        )
+        .arg(
+            Arg::new("to")
+                .long("to")
+                .value_name("REF")
+                .requires("repos")
+                .conflicts_with("latest-tag")
+                .help("Re-pin the given repos to a branch, tag or SHA"),
+        )
+        .arg(
+            Arg::new("latest-tag")
+                .long("latest-tag")
+                .action(clap::ArgAction::SetTrue)
+                .help("Re-pin to the newest tag (by version number)"),
+        )
+}
+
+pub fn run_activate(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    let repo_arg = matches.get_one::<String>("repo").unwrap();
+
+    let cwd = std::env::current_dir()?;
+    let ws_dir = workspace::detect(&cwd)?;
+    let meta = workspace::load_metadata(&ws_dir)
+        .map_err(|e| anyhow::anyhow!("reading workspace: {}", e))?;
+    let identity = resolve_repo(&meta, repo_arg)?;
+
+    workspace::activate_repo(&ws_dir, &identity)?;
+    run_integrations(&ws_dir, paths);
+
+    Ok(Output::Mutation(MutationOutput {
+        ok: true,
+        message: format!("Activated {} on branch {}", identity, meta.branch),
+    }))
+}
+
+pub fn run_pin(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    let repo_arg = matches.get_one::<String>("repo").unwrap();
+    let git_ref = matches.get_one::<String>("ref").unwrap();
+    let force = matches.get_flag("force");
+
+    let cwd = std::env::current_dir()?;
+    let ws_dir = workspace::detect(&cwd)?;
+    let meta = workspace::load_metadata(&ws_dir)
+        .map_err(|e| anyhow::anyhow!("reading workspace: {}", e))?;
+    let identity = resolve_repo(&meta, repo_arg)?;
+
+    workspace::pin_repo(&ws_dir, &identity, git_ref, force)?;
+    run_integrations(&ws_dir, paths);
+
+    Ok(Output::Mutation(MutationOutput {
+        ok: true,
+        message: format!("Pinned {} to {}", identity, git_ref),
+    }))
+}
+
+pub fn run_update_pin(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    let to = matches.get_one::<String>("to");
+    let latest_tag = matches.get_flag("latest-tag");
+
+    let cwd = std::env::current_dir()?;
+    let ws_dir = workspace::detect(&cwd)?;
+    let meta = workspace::load_metadata(&ws_dir)
+        .map_err(|e| anyhow::anyhow!("reading workspace: {}", e))?;
+
+    let identities: Vec<String> = match matches.get_many::<String>("repos") {
+        Some(args) => {
+            let mut ids = Vec::new();
+            for arg in args {
+                let id = resolve_repo(&meta, arg)?;
+                if meta.is_active(&id) {
+                    bail!("repo {} is not pinned; use `wsp repo pin`", id);
+                }
+                ids.push(id);
+            }
+            ids
+        }
+        None => meta
+            .repos
+            .keys()
+            .filter(|id| !meta.is_active(id))
+            .cloned()
+            .collect(),
+    };
+    let shortnames = giturl::shortnames(&meta.repos.keys().cloned().collect::<Vec<_>>());
+
+    if !identities.is_empty() {
+        eprintln!("Fetching {} pinned repo(s)...", identities.len());
+    }
+    let mut repos = Vec::new();
+    for id in &identities {
+        let name = shortnames.get(id).cloned().unwrap_or_else(|| id.clone());
+        let old_ref = meta.repos[id]
+            .as_ref()
+            .map(|r| r.r#ref.clone())
+            .unwrap_or_default();
+        let mut entry = PinUpdateEntry {
+            name,
+            old_ref,
+            new_ref: None,
+            newer_tags: Vec::new(),
+            behind: None,
+            ok: true,
+            error: None,
+        };
+        if let Err(e) = update_one(paths, &ws_dir, &meta, id, to, latest_tag, &mut entry) {
+            entry.ok = false;
+            entry.error = Some(e.to_string());
+        }
+        repos.push(entry);
+    }
+
+    if repos.iter().any(|r| r.new_ref.is_some()) {
+        run_integrations(&ws_dir, paths);
+    }
+
+    Ok(Output::PinUpdate(PinUpdateOutput { repos }))
+}
+
+fn update_one(
+    paths: &Paths,
+    ws_dir: &std::path::Path,
+    meta: &Metadata,
+    identity: &str,
+    to: Option<&String>,
+    latest_tag: bool,
+    entry: &mut PinUpdateEntry,
+) -> Result<()> {
+    let clone_dir = ws_dir.join(meta.dir_name(identity)?);
+    let parsed = giturl::Parsed::from_identity(identity)?;
+    mirror::fetch_locked(paths, &parsed, false)
+        .map_err(|e| anyhow::anyhow!("fetching mirror: {}", e))?;
+    git::fetch_tags(&clone_dir, "wsp-mirror")?;
+
+    let candidates = workspace::pin_candidates(&clone_dir, &entry.old_ref)?;
+    entry.newer_tags = candidates.newer_tags;
+    entry.behind = candidates.behind;
+
+    let new_ref = match to {
+        Some(r) => r.clone(),
+        None if latest_tag => match git::tags_by_version(&clone_dir)?.into_iter().next() {
+            Some(tag) => tag,
+            None => bail!("no tags found"),
+        },
+        None => return Ok(()),
+    };
+    if new_ref == entry.old_ref && entry.behind.unwrap_or(0) == 0 {
+        return Ok(());
+    }
+
+    workspace::update_pin(ws_dir, identity, &new_ref)?;
+    let candidates = workspace::pin_candidates(&clone_dir, &new_ref)?;
+    entry.newer_tags = candidates.newer_tags;
+    entry.behind = candidates.behind;
+    entry.new_ref = Some(new_ref);
+    Ok(())
+}
+
+fn resolve_repo(meta: &Metadata, repo_arg: &str) -> Result<String> {
+    let ws_identities: Vec<String> = meta.repos.keys().cloned().collect();
+    let identity = giturl::resolve(repo_arg, &ws_identities)?;
+    if !meta.repos.contains_key(&identity) {
+        bail!("repo {} is not in this workspace", identity);
+    }
+    Ok(identity)
+}
+
+fn run_integrations(ws_dir: &std::path::Path, paths: &Paths) {
+    let cfg = match config::Config::load_from(&paths.config_path) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            eprintln!("warning: skipping language integrations: {}", e);
+            return;
+        }
+    };
+    match workspace::load_metadata(ws_dir) {
+        Ok(meta) => crate::lang::run_integrations(ws_dir, &meta, &cfg),
+        Err(e) => eprintln!("warning: skipping language integrations: {}", e),
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use clap::{Arg, ArgMatches, Command};
+
+use crate::config::{self, Paths};
+use crate::giturl;
+use crate::lock;
+use crate::output::{Output, PruneOutput, PruneRepoVerdict, PruneWorkspaceResult};
+use crate::workspace::{self, Verdict};
+
+pub fn cmd() -> Command {
+    Command::new("prune")
+        .about("Remove workspaces whose branches are merged in every repo")
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .action(clap::ArgAction::SetTrue)
+                .help("Only report which workspaces would be removed"),
+        )
+        .arg(
+            Arg::new("older-than")
+                .long("older-than")
+                .value_name("AGE")
+                .help("Only consider workspaces created more than AGE ago (e.g. 30d, 12h, 2w)"),
+        )
+}
+
+pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    let dry_run = matches.get_flag("dry-run");
+    let cutoff = match matches.get_one::<String>("older-than") {
+        Some(age) => match Utc::now().checked_sub_signed(config::parse_age(age)?) {
+            Some(t) => Some(t),
+            None => bail!("--older-than {} is too far back", age),
+        },
+        None => None,
+    };
+
+    let names = workspace::list_all(&paths.workspaces_dir)?;
+
+    // Workspaces stacked on each workspace; removing a parent would break
+    // their `wsp-parent` remotes.
+    let mut children: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for name in &names {
+        if let Ok(meta) = workspace::load_metadata(&workspace::dir(&paths.workspaces_dir, name))
+            && let Some(parent) = meta.parent
+        {
+            children.entry(parent).or_default().push(name.clone());
+        }
+    }
+
+    if !names.is_empty() {
+        eprintln!("Checking {} workspace(s)...", names.len());
+    }
+    // Each workspace stays locked from its evaluation until it's removed, so
+    // nothing can land in it in between.
+    let evaluated: Vec<(PruneWorkspaceResult, Option<lock::Lock>)> = std::thread::scope(|s| {
+        let handles: Vec<_> = names
+            .iter()
+            .map(|name| {
+                let kids = children.get(name).map(|v| v.as_slice()).unwrap_or(&[]);
+                s.spawn(move || evaluate(paths, name, cutoff, kids))
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    let (mut workspaces, _locks): (Vec<_>, Vec<_>) = evaluated.into_iter().unzip();
+
+    if !dry_run {
+        for w in workspaces.iter_mut().filter(|w| w.safe) {
+            eprintln!("Removing workspace {:?}...", w.name);
+            match workspace::remove(paths, &w.name, true, "wsp prune") {
+                Ok(_) => w.removed = true,
+                Err(e) => w.error = Some(format!("removing: {}", e)),
+            }
+        }
+    }
+
+    Ok(Output::Prune(PruneOutput {
+        dry_run,
+        workspaces,
+    }))
+}
+
+/// Locks a workspace and checks it. A busy workspace is kept; otherwise it's
+/// returned still locked.
+fn evaluate(
+    paths: &Paths,
+    name: &str,
+    cutoff: Option<DateTime<Utc>>,
+    children: &[String],
+) -> (PruneWorkspaceResult, Option<lock::Lock>) {
+    match lock::workspace(paths, name) {
+        Ok(lock) => (check(paths, name, cutoff, children), Some(lock)),
+        Err(e) => {
+            let result = PruneWorkspaceResult {
+                name: name.to_string(),
+                safe: false,
+                removed: false,
+                reason: Some(e.to_string()),
+                repos: Vec::new(),
+                error: None,
+            };
+            (result, None)
+        }
+    }
+}
+
+/// Decides whether a workspace can go: created before `cutoff`, nothing
+/// stacked on it, and every active repo clean and merged.
+fn check(
+    paths: &Paths,
+    name: &str,
+    cutoff: Option<DateTime<Utc>>,
+    children: &[String],
+) -> PruneWorkspaceResult {
+    let mut result = PruneWorkspaceResult {
+        name: name.to_string(),
+        safe: false,
+        removed: false,
+        reason: None,
+        repos: Vec::new(),
+        error: None,
+    };
+
+    let ws_dir = workspace::dir(&paths.workspaces_dir, name);
+    let meta = match workspace::load_metadata(&ws_dir) {
+        Ok(m) => m,
+        Err(e) => {
+            result.error = Some(format!("reading workspace: {}", e));
+            return result;
+        }
+    };
+
+    if let Some(cutoff) = cutoff
+        && meta.created > cutoff
+    {
+        result.reason = Some(format!("created {}", meta.created.format("%Y-%m-%d")));
+        return result;
+    }
+    if !children.is_empty() {
+        result.reason = Some(format!("has stacked workspaces: {}", children.join(", ")));
+        return result;
+    }
+    let active: Vec<&String> = meta.repos.keys().filter(|id| meta.is_active(id)).collect();
+    if active.is_empty() {
+        result.reason = Some("no active repos".to_string());
+        return result;
+    }
+
+    let shortnames = giturl::shortnames(&meta.repos.keys().cloned().collect::<Vec<_>>());
+    let parent = meta.load_parent(&ws_dir);
+    let mut all_merged = true;
+    for identity in active {
+        let safety = match workspace::repo_safety(&ws_dir, &meta, parent.as_ref(), identity) {
+            Ok(s) => s,
+            Err(e) => {
+                result.error = Some(format!("{}: {}", identity, e));
+                return result;
+            }
+        };
+        all_merged &= matches!(safety.verdict, Verdict::Merged | Verdict::SquashMerged);
+        result.repos.push(PruneRepoVerdict {
+            name: shortnames
+                .get(identity)
+                .cloned()
+                .unwrap_or_else(|| identity.clone()),
+            verdict: safety.verdict,
+            fetch_failed: safety.fetch_failed,
+        });
+    }
+    result.safe = all_merged;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RepoEntry;
+    use crate::git;
+    use crate::mirror;
+    use crate::workspace::Bases;
+    use std::fs;
+    use std::path::Path;
+
+    /// One registered repo and workspaces in every state prune cares about:
+    /// `done` (merged upstream), `base` (merged, with `stacked` on top),
+    /// `fresh` (never committed on), `dirty` (untracked file) and `wip`
+    /// (a commit that never made it upstream).
+    fn setup() -> (Paths, Vec<tempfile::TempDir>) {
+        let (_clone, source, ct, st) = crate::testutil::setup_clone_repo();
+        let tmp = tempfile::tempdir().unwrap();
+        let workspaces_dir = tmp.path().join("workspaces");
+        fs::create_dir_all(&workspaces_dir).unwrap();
+        let paths = Paths::from_dirs(&tmp.path().join("data"), &workspaces_dir);
+
+        let url = source.to_str().unwrap().to_string();
+        let parsed = giturl::Parsed {
+            host: "test.local".into(),
+            owner: "user".into(),
+            repo: "api".into(),
+        };
+        let identity = parsed.identity();
+        let mut cfg = config::Config::default();
+        cfg.repos.insert(
+            identity.clone(),
+            RepoEntry {
+                url: url.clone(),
+                added: Utc::now(),
+            },
+        );
+        cfg.save_to(&paths.config_path).unwrap();
+        mirror::clone(&paths.mirrors_dir, &parsed, &url).unwrap();
+        mirror::fetch(&paths.mirrors_dir, &parsed).unwrap();
+
+        let refs = BTreeMap::from([(identity.clone(), String::new())]);
+        let urls = BTreeMap::from([(identity, url)]);
+        for name in ["done", "base", "fresh", "dirty", "wip"] {
+            workspace::create(&paths, name, &refs, &Bases::default(), None, &urls).unwrap();
+        }
+        let bases = Bases {
+            parent: Some("base".into()),
+            ..Default::default()
+        };
+        workspace::create(&paths, "stacked", &refs, &bases, None, &urls).unwrap();
+
+        let clone = |name: &str| workspace::dir(&paths.workspaces_dir, name).join("api");
+        for name in ["done", "base", "wip"] {
+            let dir = clone(name);
+            for kv in [
+                ["user.email", "test@test.com"],
+                ["user.name", "Test"],
+                ["commit.gpgsign", "false"],
+            ] {
+                git::run(Some(&dir), &["config", kv[0], kv[1]]).unwrap();
+            }
+            crate::testutil::local_commit(&dir, &format!("{}.txt", name), name);
+        }
+        for name in ["done", "base"] {
+            merge_upstream(&source, &clone(name), name);
+        }
+        fs::write(clone("dirty").join("scratch.txt"), "").unwrap();
+
+        (paths, vec![ct, st, tmp])
+    }
+
+    /// Lands a workspace branch on the source repo's main.
+    fn merge_upstream(source: &Path, clone_dir: &Path, branch: &str) {
+        let clone_dir = clone_dir.to_str().unwrap();
+        git::run(Some(source), &["fetch", clone_dir, branch]).unwrap();
+        git::run(Some(source), &["merge", "--no-edit", "FETCH_HEAD"]).unwrap();
+    }
+
+    fn prune(paths: &Paths, args: &[&str]) -> PruneOutput {
+        let matches = cmd().get_matches_from(std::iter::once("prune").chain(args.iter().copied()));
+        match run(&matches, paths).unwrap() {
+            Output::Prune(v) => v,
+            _ => panic!("expected Prune output"),
+        }
+    }
+
+    /// (name, safe, removed, verdicts) per workspace.
+    fn summary(out: &PruneOutput) -> Vec<(&str, bool, bool, Vec<Verdict>)> {
+        out.workspaces
+            .iter()
+            .map(|w| {
+                assert!(w.error.is_none(), "{}: {:?}", w.name, w.error);
+                let verdicts = w.repos.iter().map(|r| r.verdict).collect();
+                (w.name.as_str(), w.safe, w.removed, verdicts)
+            })
+            .collect()
+    }
+
+    fn remaining(paths: &Paths) -> Vec<String> {
+        workspace::list_all(&paths.workspaces_dir).unwrap()
+    }
+
+    #[test]
+    fn test_prune() {
+        let (paths, _tmp) = setup();
+        let out = prune(&paths, &[]);
+        assert_eq!(
+            summary(&out),
+            vec![
+                ("base", false, false, vec![]),
+                ("dirty", false, false, vec![Verdict::PendingChanges]),
+                ("done", true, true, vec![Verdict::Merged]),
+                ("fresh", false, false, vec![Verdict::NoCommits]),
+                ("stacked", false, false, vec![Verdict::NoCommits]),
+                ("wip", false, false, vec![Verdict::PendingChanges]),
+            ]
+        );
+        assert_eq!(
+            out.workspaces[0].reason.as_deref(),
+            Some("has stacked workspaces: stacked")
+        );
+        assert_eq!(
+            remaining(&paths),
+            vec!["base", "dirty", "fresh", "stacked", "wip"]
+        );
+    }
+
+    #[test]
+    fn test_prune_dry_run_removes_nothing() {
+        let (paths, _tmp) = setup();
+        let before = remaining(&paths);
+        let out = prune(&paths, &["--dry-run"]);
+        assert!(out.dry_run);
+        let safe: Vec<(&str, bool)> = out
+            .workspaces
+            .iter()
+            .filter(|w| w.safe)
+            .map(|w| (w.name.as_str(), w.removed))
+            .collect();
+        assert_eq!(safe, vec![("done", false)]);
+        assert_eq!(remaining(&paths), before);
+    }
+
+    #[test]
+    fn test_prune_older_than_skips_new_workspaces() {
+        let (paths, _tmp) = setup();
+        let before = remaining(&paths);
+        let out = prune(&paths, &["--older-than", "1d"]);
+        for w in &out.workspaces {
+            assert!(!w.safe && !w.removed, "{}", w.name);
+            assert!(
+                w.reason
+                    .as_deref()
+                    .is_some_and(|r| r.starts_with("created ")),
+                "{}: {:?}",
+                w.name,
+                w.reason
+            );
+        }
+        assert_eq!(remaining(&paths), before);
+    }
+}
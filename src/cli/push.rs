@@ -111,7 +111,7 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
             continue;
         }
 
-        let upstream = git::resolve_upstream_ref(&info.clone_dir);
+        let upstream = git::resolve_upstream_ref(&info.clone_dir, info.base_ref.as_deref());
         if matches!(upstream, UpstreamRef::Head) {
             results.push(PushRepoResult {
                 name: info.dir_name.clone(),
@@ -229,7 +229,7 @@ mod tests {
     fn test_push_nothing_to_push() {
         let (clone, _source, _ct, _st) = setup_clone_repo();
 
-        let upstream = git::resolve_upstream_ref(&clone);
+        let upstream = git::resolve_upstream_ref(&clone, None);
         let ahead = git::ahead_count_from(&clone, &upstream).unwrap_or(0);
         assert_eq!(ahead, 0, "fresh clone on feature should be 0 ahead");
     }
@@ -239,7 +239,7 @@ mod tests {
         let (clone, _source, _ct, _st) = setup_clone_repo();
 
         // feature branch has no tracking branch
-        let upstream = git::resolve_upstream_ref(&clone);
+        let upstream = git::resolve_upstream_ref(&clone, None);
         let needs_upstream = !matches!(upstream, UpstreamRef::Tracking)
             || !git::remote_branch_exists(&clone, "feature");
         assert!(needs_upstream, "feature branch should need upstream set");
@@ -269,7 +269,7 @@ mod tests {
 
         local_commit(&clone, "new.txt", "content");
 
-        let upstream = git::resolve_upstream_ref(&clone);
+        let upstream = git::resolve_upstream_ref(&clone, None);
         let ahead = git::ahead_count_from(&clone, &upstream).unwrap_or(0);
         assert!(ahead > 0, "should be ahead after local commit");
     }
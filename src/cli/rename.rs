@@ -0,0 +1,58 @@
+use anyhow::Result;
+use clap::{Arg, ArgMatches, Command};
+use clap_complete::engine::ArgValueCandidates;
+
+use crate::config::{self, Paths};
+use crate::output::{MutationOutput, Output};
+use crate::workspace;
+
+use super::{cd, completers};
+
+pub fn cmd() -> Command {
+    Command::new("rename")
+        .about("Rename a workspace and its branch")
+        .arg(
+            Arg::new("old")
+                .required(true)
+                .add(ArgValueCandidates::new(completers::complete_workspaces)),
+        )
+        .arg(Arg::new("new").required(true))
+        .arg(
+            Arg::new("keep-branch")
+                .long("keep-branch")
+                .action(clap::ArgAction::SetTrue)
+                .help("Keep the current branch name in every repo"),
+        )
+}
+
+pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    let old = matches.get_one::<String>("old").unwrap();
+    let new = matches.get_one::<String>("new").unwrap();
+    let keep_branch = matches.get_flag("keep-branch");
+
+    eprintln!("Renaming workspace {:?} to {:?}...", old, new);
+    let old_dir = workspace::dir(&paths.workspaces_dir, old);
+    let warnings = workspace::rename(paths, old, new, keep_branch)?;
+    for w in &warnings {
+        eprintln!("  warning: {}", w);
+    }
+
+    // Generated files can depend on the workspace name.
+    let ws_dir = workspace::dir(&paths.workspaces_dir, new);
+    if let Some(dir) = cd::shell_cwd().and_then(|cwd| cd::moved(&cwd, &old_dir, &ws_dir)) {
+        cd::request(&dir);
+    }
+    // The rename is done; a failure here must not report it as failed.
+    let loaded = config::Config::load_from(&paths.config_path)
+        .map_err(|e| anyhow::anyhow!("loading config: {}", e))
+        .and_then(|cfg| Ok((cfg, workspace::load_metadata(&ws_dir)?)));
+    match loaded {
+        Ok((cfg, meta)) => crate::lang::run_integrations(&ws_dir, &meta, &cfg),
+        Err(e) => eprintln!("warning: skipping language integrations: {}", e),
+    }
+
+    Ok(Output::Mutation(MutationOutput {
+        ok: true,
+        message: format!("Workspace renamed: {}", ws_dir.display()),
+    }))
+}
@@ -1,12 +1,22 @@
+use std::collections::{BTreeMap, HashSet};
+
 use anyhow::{Result, bail};
 use chrono::Utc;
 use clap::{Arg, ArgMatches, Command};
 use clap_complete::engine::ArgValueCandidates;
 
 use crate::config::{self, Paths, RepoEntry};
+use crate::disk;
+use crate::git;
 use crate::giturl;
+use crate::lock;
 use crate::mirror;
-use crate::output::{MutationOutput, Output, RepoListEntry, RepoListOutput};
+use crate::output::{
+    DiskUsageEntry, MutationOutput, OrphanMirror, Output, RepoGcOutput, RepoListEntry,
+    RepoListOutput,
+};
+use crate::trash;
+use crate::workspace;
 
 use super::completers;
 
@@ -30,11 +40,36 @@ pub fn remove_cmd() -> Command {
         )
 }
 
+pub fn gc_cmd() -> Command {
+    Command::new("gc")
+        .about("Remove orphaned mirrors, compact the rest, and report disk usage")
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .action(clap::ArgAction::SetTrue)
+                .help("Report orphans and sizes without removing or compacting anything"),
+        )
+}
+
 pub fn run_add(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     let raw_url = matches.get_one::<String>("url").unwrap();
 
-    let parsed = giturl::parse(raw_url)?;
     let mut cfg = config::Config::load_from(&paths.config_path)?;
+    let identity = register(paths, &mut cfg, raw_url)?;
+
+    cfg.save_to(&paths.config_path)
+        .map_err(|e| anyhow::anyhow!("saving config: {}", e))?;
+
+    Ok(Output::Mutation(MutationOutput {
+        ok: true,
+        message: format!("Registered {}", identity),
+    }))
+}
+
+/// Bare-clones `raw_url` into the mirrors dir and adds it to `cfg`, which the
+/// caller saves. Returns the repo's identity.
+pub fn register(paths: &Paths, cfg: &mut config::Config, raw_url: &str) -> Result<String> {
+    let parsed = giturl::parse(raw_url)?;
 
     let identity = parsed.identity();
     if cfg.repos.contains_key(&identity) {
@@ -53,18 +88,11 @@ pub fn run_add(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     cfg.repos.insert(
         identity.clone(),
         RepoEntry {
-            url: raw_url.clone(),
+            url: raw_url.to_string(),
             added: Utc::now(),
         },
     );
-
-    cfg.save_to(&paths.config_path)
-        .map_err(|e| anyhow::anyhow!("saving config: {}", e))?;
-
-    Ok(Output::Mutation(MutationOutput {
-        ok: true,
-        message: format!("Registered {}", identity),
-    }))
+    Ok(identity)
 }
 
 pub fn run_list(_matches: &ArgMatches, paths: &Paths) -> Result<Output> {
@@ -117,3 +145,330 @@ pub fn run_remove(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
         message: format!("Removed {}", identity),
     }))
 }
+
+pub fn run_gc(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    let dry_run = matches.get_flag("dry-run");
+
+    let cfg = config::Config::load_from(&paths.config_path)
+        .map_err(|e| anyhow::anyhow!("loading config: {}", e))?;
+    let on_disk =
+        mirror::list(&paths.mirrors_dir).map_err(|e| anyhow::anyhow!("listing mirrors: {}", e))?;
+    let ws_names = workspace::list_all(&paths.workspaces_dir)?;
+    let trashed = trash::list(&paths.trash_dir)?;
+
+    // Workspaces listing each repo, so orphans still in use are kept. Trashed
+    // workspaces count too: restoring one needs its mirrors.
+    let mut users: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let dirs = ws_names
+        .iter()
+        .map(|name| (name.clone(), workspace::dir(&paths.workspaces_dir, name)))
+        .chain(
+            trashed
+                .iter()
+                .map(|e| (format!("{} (trash)", e.id), e.content_dir())),
+        );
+    for (user, dir) in dirs {
+        if let Ok(meta) = workspace::load_metadata(&dir) {
+            for identity in meta.repos.keys() {
+                users
+                    .entry(identity.clone())
+                    .or_default()
+                    .push(user.clone());
+            }
+        }
+    }
+
+    let mut orphans = Vec::new();
+    for identity in on_disk.iter().filter(|id| !cfg.repos.contains_key(*id)) {
+        let parsed = giturl::Parsed::from_identity(identity)?;
+        let mut orphan = OrphanMirror {
+            identity: identity.clone(),
+            path: mirror::dir(&paths.mirrors_dir, &parsed),
+            removed: false,
+            used_by: users.get(identity).cloned().unwrap_or_default(),
+            error: None,
+        };
+        if !dry_run && orphan.used_by.is_empty() {
+            // Hold the mirror lock so a concurrent clone or fetch never sees
+            // it half-removed.
+            let removed = lock::mirror(paths, &parsed).and_then(|_lock| {
+                std::fs::remove_dir_all(&orphan.path)
+                    .map_err(|e| anyhow::anyhow!("removing mirror: {}", e))
+            });
+            match removed {
+                Ok(()) => orphan.removed = true,
+                Err(e) => orphan.error = Some(e.to_string()),
+            }
+        }
+        orphans.push(orphan);
+    }
+
+    let live: Vec<&String> = on_disk
+        .iter()
+        .filter(|id| cfg.repos.contains_key(*id))
+        .collect();
+    if !dry_run && !live.is_empty() {
+        eprintln!("Compacting {} mirror(s)...", live.len());
+    }
+
+    let mut usage = Vec::new();
+    let mut seen = HashSet::new();
+    let mut total_bytes = 0;
+    for identity in &on_disk {
+        if orphans.iter().any(|o| &o.identity == identity && o.removed) {
+            continue;
+        }
+        let parsed = giturl::Parsed::from_identity(identity)?;
+        let path = mirror::dir(&paths.mirrors_dir, &parsed);
+        let mut error = None;
+        if !dry_run
+            && cfg.repos.contains_key(identity)
+            && let Err(e) = lock::mirror(paths, &parsed).and_then(|_lock| git::gc(&path))
+        {
+            error = Some(format!("git gc: {}", e));
+        }
+        let mut entry = measure("mirror", identity, &path, &mut seen, &mut total_bytes);
+        entry.error = error.or(entry.error);
+        usage.push(entry);
+    }
+    for name in &ws_names {
+        let path = workspace::dir(&paths.workspaces_dir, name);
+        usage.push(measure(
+            "workspace",
+            name,
+            &path,
+            &mut seen,
+            &mut total_bytes,
+        ));
+    }
+    for entry in &trashed {
+        usage.push(measure(
+            "trash",
+            &entry.id,
+            &entry.dir,
+            &mut seen,
+            &mut total_bytes,
+        ));
+    }
+
+    Ok(Output::RepoGc(RepoGcOutput {
+        dry_run,
+        orphans,
+        usage,
+        total_bytes,
+    }))
+}
+
+fn measure(
+    kind: &str,
+    name: &str,
+    path: &std::path::Path,
+    seen: &mut HashSet<disk::FileKey>,
+    total_bytes: &mut u64,
+) -> DiskUsageEntry {
+    let mut entry = DiskUsageEntry {
+        kind: kind.to_string(),
+        name: name.to_string(),
+        unique_bytes: 0,
+        shared_bytes: 0,
+        error: None,
+    };
+    match disk::measure(path, seen) {
+        Ok((u, on_disk)) => {
+            entry.unique_bytes = u.unique;
+            entry.shared_bytes = u.shared;
+            *total_bytes += on_disk;
+        }
+        Err(e) => entry.error = Some(format!("measuring: {}", e)),
+    }
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::Bases;
+    use std::fs;
+
+    /// Mirrors for `live` (registered), `kept` (unregistered, used by
+    /// workspace `ws`) and `orphan` (unregistered, unused).
+    fn setup() -> (Paths, Vec<tempfile::TempDir>) {
+        let (_clone, source, ct, st) = crate::testutil::setup_clone_repo();
+        let tmp = tempfile::tempdir().unwrap();
+        let workspaces_dir = tmp.path().join("workspaces");
+        fs::create_dir_all(&workspaces_dir).unwrap();
+        let paths = Paths::from_dirs(&tmp.path().join("data"), &workspaces_dir);
+        let url = source.to_str().unwrap().to_string();
+
+        let mut cfg = config::Config::default();
+        for repo in ["live", "kept", "orphan"] {
+            let parsed = giturl::Parsed {
+                host: "test.local".into(),
+                owner: "user".into(),
+                repo: repo.into(),
+            };
+            mirror::clone(&paths.mirrors_dir, &parsed, &url).unwrap();
+            if repo == "live" {
+                cfg.repos.insert(
+                    parsed.identity(),
+                    RepoEntry {
+                        url: url.clone(),
+                        added: Utc::now(),
+                    },
+                );
+            }
+        }
+        cfg.save_to(&paths.config_path).unwrap();
+
+        let kept = "test.local/user/kept".to_string();
+        workspace::create(
+            &paths,
+            "ws",
+            &BTreeMap::from([(kept.clone(), String::new())]),
+            &Bases::default(),
+            None,
+            &BTreeMap::from([(kept, url)]),
+        )
+        .unwrap();
+        (paths, vec![ct, st, tmp])
+    }
+
+    fn gc(paths: &Paths, args: &[&str]) -> RepoGcOutput {
+        let matches = gc_cmd().get_matches_from(std::iter::once("gc").chain(args.iter().copied()));
+        match run_gc(&matches, paths).unwrap() {
+            Output::RepoGc(v) => v,
+            _ => panic!("expected RepoGc output"),
+        }
+    }
+
+    fn orphans(out: &RepoGcOutput) -> Vec<(&str, bool, Vec<String>)> {
+        out.orphans
+            .iter()
+            .map(|o| (o.identity.as_str(), o.removed, o.used_by.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_gc_dry_run_removes_nothing() {
+        let (paths, _tmp) = setup();
+        let out = gc(&paths, &["--dry-run"]);
+        assert!(out.dry_run);
+        assert_eq!(
+            orphans(&out),
+            vec![
+                ("test.local/user/kept", false, vec!["ws".to_string()]),
+                ("test.local/user/orphan", false, vec![]),
+            ]
+        );
+        assert_eq!(
+            mirror::list(&paths.mirrors_dir).unwrap(),
+            vec![
+                "test.local/user/kept",
+                "test.local/user/live",
+                "test.local/user/orphan",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gc_removes_unused_orphans() {
+        let (paths, _tmp) = setup();
+        let live = "test.local/user/live".to_string();
+        let url = config::Config::load_from(&paths.config_path).unwrap().repos[&live]
+            .url
+            .clone();
+        workspace::create(
+            &paths,
+            "old",
+            &BTreeMap::from([(live.clone(), String::new())]),
+            &Bases::default(),
+            None,
+            &BTreeMap::from([(live, url)]),
+        )
+        .unwrap();
+        let trash_id = workspace::remove(&paths, "old", true, "wsp rm").unwrap();
+
+        let out = gc(&paths, &[]);
+        assert!(!out.dry_run);
+        assert_eq!(
+            orphans(&out),
+            vec![
+                ("test.local/user/kept", false, vec!["ws".to_string()]),
+                ("test.local/user/orphan", true, vec![]),
+            ]
+        );
+        assert!(out.orphans.iter().all(|o| o.error.is_none()));
+        assert_eq!(
+            mirror::list(&paths.mirrors_dir).unwrap(),
+            vec!["test.local/user/kept", "test.local/user/live"]
+        );
+
+        let rows: Vec<(&str, &str)> = out
+            .usage
+            .iter()
+            .map(|u| (u.kind.as_str(), u.name.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("mirror", "test.local/user/kept"),
+                ("mirror", "test.local/user/live"),
+                ("workspace", "ws"),
+                ("trash", trash_id.as_str()),
+            ]
+        );
+        assert!(out.usage.iter().all(|u| u.error.is_none()));
+    }
+
+    #[test]
+    fn test_gc_keeps_orphans_used_by_trashed_workspaces() {
+        let (paths, _tmp) = setup();
+        let orphan = "test.local/user/orphan".to_string();
+        let url = mirror::dir(
+            &paths.mirrors_dir,
+            &giturl::Parsed::from_identity(&orphan).unwrap(),
+        );
+        workspace::create(
+            &paths,
+            "old",
+            &BTreeMap::from([(orphan.clone(), String::new())]),
+            &Bases::default(),
+            None,
+            &BTreeMap::from([(orphan, url.display().to_string())]),
+        )
+        .unwrap();
+        let trash_id = workspace::remove(&paths, "old", true, "wsp rm").unwrap();
+
+        let out = gc(&paths, &[]);
+        assert_eq!(
+            orphans(&out),
+            vec![
+                ("test.local/user/kept", false, vec!["ws".to_string()]),
+                (
+                    "test.local/user/orphan",
+                    false,
+                    vec![format!("{} (trash)", trash_id)]
+                ),
+            ]
+        );
+        assert!(url.exists());
+    }
+
+    #[test]
+    fn test_gc_waits_for_mirror_lock_before_removing() {
+        let (paths, _tmp) = setup();
+        let parsed = giturl::Parsed::from_identity("test.local/user/orphan").unwrap();
+        let dir = mirror::dir(&paths.mirrors_dir, &parsed);
+        let held = lock::mirror(&paths, &parsed).unwrap();
+
+        std::thread::scope(|s| {
+            let handle = s.spawn(|| gc(&paths, &[]));
+            std::thread::sleep(std::time::Duration::from_millis(500));
+            assert!(dir.exists(), "removed while the mirror lock was held");
+            drop(held);
+            let out = handle.join().unwrap();
+            assert!(out.orphans.iter().all(|o| o.error.is_none()));
+        });
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
+use clap::{Arg, ArgMatches, Command};
+use clap_complete::engine::ArgValueCandidates;
+
+use crate::config::Paths;
+use crate::giturl;
+use crate::output::{ExecOutput, ExecRepoResult, Output};
+use crate::workspace::{self, RepoInfo, Task};
+
+use super::completers;
+use super::exec::{self, ExecJob, WorkspaceEnv};
+use super::select::{self, RepoSelector};
+
+pub fn cmd() -> Command {
+    select::add_args(
+        Command::new("run")
+            .about("Run a task defined in .wsp.yaml across workspace repos")
+            .arg(
+                Arg::new("task")
+                    .required(true)
+                    .add(ArgValueCandidates::new(completers::complete_tasks)),
+            )
+            .arg(
+                Arg::new("workspace").add(ArgValueCandidates::new(completers::complete_workspaces)),
+            )
+            .arg(exec::parallel_arg()),
+    )
+}
+
+pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    let task_name = matches.get_one::<String>("task").unwrap();
+    let parallel = matches.get_one::<usize>("parallel").copied();
+    let json = matches.get_flag("json");
+
+    let ws_dir: PathBuf = if let Some(name) = matches.get_one::<String>("workspace") {
+        workspace::dir(&paths.workspaces_dir, name)
+    } else {
+        let cwd = std::env::current_dir()?;
+        workspace::detect(&cwd)?
+    };
+
+    let meta = workspace::load_metadata(&ws_dir)
+        .map_err(|e| anyhow::anyhow!("reading workspace: {}", e))?;
+
+    let Some(task) = meta.tasks.get(task_name) else {
+        if meta.tasks.is_empty() {
+            bail!(
+                "task {:?} not found (no tasks defined in .wsp.yaml)",
+                task_name
+            );
+        }
+        let names: Vec<&str> = meta.tasks.keys().map(|s| s.as_str()).collect();
+        bail!(
+            "task {:?} not found, available: {}",
+            task_name,
+            names.join(", ")
+        );
+    };
+
+    let identities: Vec<String> = meta.repos.keys().cloned().collect();
+    let selected = RepoSelector::from_matches(matches).apply(meta.repo_infos(&ws_dir))?;
+    let env = WorkspaceEnv::new(&meta, &ws_dir);
+    let stages = plan(task, &env, &identities, selected)
+        .map_err(|e| anyhow::anyhow!("task {:?}: {}", task_name, e))?;
+    let repos = run_stages(&stages, parallel, json);
+
+    Ok(Output::Exec(ExecOutput {
+        workspace: meta.name,
+        task: Some(task_name.clone()),
+        command: task.command.clone(),
+        repos,
+    }))
+}
+
+/// Turns a task into ordered stages of jobs. Each repo listed in `order` gets a
+/// stage of its own, in that order; all remaining repos share a final stage.
+/// Repo names in the task are resolved against the workspace's identities.
+fn plan(
+    task: &Task,
+    env: &WorkspaceEnv,
+    identities: &[String],
+    infos: Vec<RepoInfo>,
+) -> Result<Vec<Vec<ExecJob>>> {
+    let resolve_all = |names: &[String]| -> Result<Vec<String>> {
+        names
+            .iter()
+            .map(|n| giturl::resolve(n, identities))
+            .collect()
+    };
+
+    let only: HashSet<String> = resolve_all(&task.repos)?.into_iter().collect();
+    let order = resolve_all(&task.order)?;
+    let mut overrides = BTreeMap::new();
+    for (name, command) in &task.overrides {
+        overrides.insert(giturl::resolve(name, identities)?, command.clone());
+    }
+
+    let job = |info: RepoInfo| {
+        let command = overrides
+            .get(&info.identity)
+            .unwrap_or(&task.command)
+            .clone();
+        ExecJob {
+            env: env.vars(&info),
+            info,
+            command: vec!["sh".into(), "-c".into(), command.clone()],
+            label: command,
+        }
+    };
+
+    let mut rest: Vec<RepoInfo> = infos
+        .into_iter()
+        .filter(|info| only.is_empty() || only.contains(&info.identity))
+        .collect();
+
+    let mut stages = Vec::new();
+    for identity in &order {
+        if let Some(pos) = rest.iter().position(|i| &i.identity == identity) {
+            stages.push(vec![job(rest.remove(pos))]);
+        }
+    }
+    if !rest.is_empty() {
+        stages.push(rest.into_iter().map(job).collect());
+    }
+    Ok(stages)
+}
+
+/// Runs stages one after another. Once a stage has a failure, later stages
+/// are not started and their repos are reported as skipped.
+fn run_stages(
+    stages: &[Vec<ExecJob>],
+    parallel: Option<usize>,
+    capture: bool,
+) -> Vec<ExecRepoResult> {
+    let mut results: Vec<ExecRepoResult> = Vec::new();
+    let mut failed: Option<String> = None;
+    for stage in stages {
+        if let Some(ref dep) = failed {
+            for job in stage {
+                results.push(exec::failed_before_start(
+                    &job.info,
+                    format!("skipped: {} failed", dep),
+                ));
+            }
+            continue;
+        }
+        let stage_results = exec::run_jobs(stage, parallel, capture);
+        failed = stage_results.iter().find(|r| !r.ok).map(|r| r.name.clone());
+        results.extend(stage_results);
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infos(tmp: &std::path::Path, names: &[&str]) -> Vec<RepoInfo> {
+        names
+            .iter()
+            .map(|n| {
+                let dir = tmp.join(n);
+                std::fs::create_dir_all(&dir).unwrap();
+                RepoInfo {
+                    identity: format!("github.com/acme/{}", n),
+                    dir_name: n.to_string(),
+                    clone_dir: dir,
+                    is_context: false,
+                    pinned_ref: None,
+                    base_ref: None,
+                    error: None,
+                }
+            })
+            .collect()
+    }
+
+    fn ws_env() -> WorkspaceEnv {
+        WorkspaceEnv {
+            name: "ws".into(),
+            dir: PathBuf::from("/ws"),
+            branch: "ws".into(),
+        }
+    }
+
+    fn identities(names: &[&str]) -> Vec<String> {
+        names
+            .iter()
+            .map(|n| format!("github.com/acme/{}", n))
+            .collect()
+    }
+
+    /// (dir name, command) per job, grouped by stage.
+    type Shape<'a> = Vec<Vec<(&'a str, &'a str)>>;
+
+    fn shape(stages: &[Vec<ExecJob>]) -> Shape<'_> {
+        stages
+            .iter()
+            .map(|s| {
+                s.iter()
+                    .map(|j| (j.info.dir_name.as_str(), j.command[2].as_str()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_plan() {
+        let tmp = tempfile::tempdir().unwrap();
+        let all = ["api", "proto", "web"];
+
+        let cases: Vec<(&str, Task, Shape)> = vec![
+            (
+                "command only",
+                Task {
+                    command: "make".into(),
+                    ..Default::default()
+                },
+                vec![vec![("api", "make"), ("proto", "make"), ("web", "make")]],
+            ),
+            (
+                "repo filter and override",
+                Task {
+                    command: "make".into(),
+                    repos: vec!["api".into(), "web".into()],
+                    overrides: BTreeMap::from([("web".to_string(), "npm run build".to_string())]),
+                    ..Default::default()
+                },
+                vec![vec![("api", "make"), ("web", "npm run build")]],
+            ),
+            (
+                "dependency order",
+                Task {
+                    command: "make".into(),
+                    order: vec!["proto".into(), "web".into()],
+                    ..Default::default()
+                },
+                vec![
+                    vec![("proto", "make")],
+                    vec![("web", "make")],
+                    vec![("api", "make")],
+                ],
+            ),
+            (
+                "ordered repo outside filter",
+                Task {
+                    command: "make".into(),
+                    repos: vec!["api".into()],
+                    order: vec!["proto".into()],
+                    ..Default::default()
+                },
+                vec![vec![("api", "make")]],
+            ),
+        ];
+        for (name, task, want) in cases {
+            let stages =
+                plan(&task, &ws_env(), &identities(&all), infos(tmp.path(), &all)).unwrap();
+            assert_eq!(shape(&stages), want, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_plan_unknown_repo() {
+        let tmp = tempfile::tempdir().unwrap();
+        let task = Task {
+            command: "make".into(),
+            order: vec!["nope".into()],
+            ..Default::default()
+        };
+        let names = ["api"];
+        assert!(
+            plan(
+                &task,
+                &ws_env(),
+                &identities(&names),
+                infos(tmp.path(), &names)
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_run_stages_skips_after_failure() {
+        let tmp = tempfile::tempdir().unwrap();
+        let names = ["proto", "api", "web"];
+        let task = Task {
+            command: "true".into(),
+            order: vec!["proto".into()],
+            overrides: BTreeMap::from([("proto".to_string(), "exit 3".to_string())]),
+            ..Default::default()
+        };
+        let stages = plan(
+            &task,
+            &ws_env(),
+            &identities(&names),
+            infos(tmp.path(), &names),
+        )
+        .unwrap();
+        let results = run_stages(&stages, Some(0), true);
+
+        let got: Vec<(&str, bool, Option<i32>)> = results
+            .iter()
+            .map(|r| (r.name.as_str(), r.ok, r.exit_code))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                ("proto", false, Some(3)),
+                ("api", false, None),
+                ("web", false, None)
+            ]
+        );
+        assert_eq!(results[1].error.as_deref(), Some("skipped: proto failed"));
+    }
+}
@@ -0,0 +1,247 @@
+//! Shared `--active` / `--context` / `--repo` / `--changed` flags for
+//! workspace-scoped commands that iterate over repos.
+
+use anyhow::Result;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use clap_complete::engine::ArgValueCandidates;
+
+use crate::git;
+use crate::giturl;
+use crate::workspace::RepoInfo;
+
+use super::completers;
+
+/// Adds the repo selection flags to a command.
+pub fn add_args(cmd: Command) -> Command {
+    cmd.arg(
+        Arg::new("active")
+            .long("active")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("context")
+            .help("Only active repos (on the workspace branch)"),
+    )
+    .arg(
+        Arg::new("context")
+            .long("context")
+            .action(ArgAction::SetTrue)
+            .help("Only context repos (pinned with @ref)"),
+    )
+    .arg(
+        Arg::new("repo")
+            .long("repo")
+            .action(ArgAction::Append)
+            .value_name("NAME")
+            .help("Only the named repo (repeatable)")
+            .add(ArgValueCandidates::new(
+                completers::complete_workspace_repos,
+            )),
+    )
+    .arg(
+        Arg::new("changed")
+            .long("changed")
+            .action(ArgAction::SetTrue)
+            .help("Only repos with uncommitted changes or unpushed commits"),
+    )
+}
+
+/// Which repos of a workspace a command should operate on.
+/// All criteria must match; `repos` matches any of the listed names.
+#[derive(Debug, Default)]
+pub struct RepoSelector {
+    pub active: bool,
+    pub context: bool,
+    pub repos: Vec<String>,
+    pub changed: bool,
+}
+
+impl RepoSelector {
+    /// Reads the selection flags. Tolerates matches that lack them (e.g. the
+    /// root matches passed to `st` when `wsp` is run without a subcommand).
+    pub fn from_matches(matches: &ArgMatches) -> RepoSelector {
+        let flag = |id: &str| {
+            matches
+                .try_get_one::<bool>(id)
+                .ok()
+                .flatten()
+                .copied()
+                .unwrap_or(false)
+        };
+        let repos = matches
+            .try_get_many::<String>("repo")
+            .ok()
+            .flatten()
+            .map(|v| v.cloned().collect())
+            .unwrap_or_default();
+        RepoSelector {
+            active: flag("active"),
+            context: flag("context"),
+            repos,
+            changed: flag("changed"),
+        }
+    }
+
+    /// Filters repo infos down to the selected set, preserving order.
+    /// Fails if a `--repo` name does not resolve to a repo in the workspace.
+    pub fn apply(&self, infos: Vec<RepoInfo>) -> Result<Vec<RepoInfo>> {
+        let mut wanted: Vec<String> = Vec::new();
+        if !self.repos.is_empty() {
+            let identities: Vec<String> = infos.iter().map(|i| i.identity.clone()).collect();
+            for name in &self.repos {
+                wanted.push(giturl::resolve(name, &identities)?);
+            }
+        }
+
+        Ok(infos
+            .into_iter()
+            .filter(|info| !self.active || !info.is_context)
+            .filter(|info| !self.context || info.is_context)
+            .filter(|info| wanted.is_empty() || wanted.contains(&info.identity))
+            .filter(|info| !self.changed || (info.error.is_none() && has_changes(info)))
+            .collect())
+    }
+}
+
+fn has_changes(info: &RepoInfo) -> bool {
+    git::changed_file_count(&info.clone_dir).unwrap_or(0) > 0
+        || git::ahead_count(&info.clone_dir, info.base_ref.as_deref()).unwrap_or(0) > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn info(identity: &str, pinned: Option<&str>) -> RepoInfo {
+        let dir_name = identity.rsplit('/').next().unwrap().to_string();
+        RepoInfo {
+            identity: identity.to_string(),
+            dir_name,
+            clone_dir: PathBuf::new(),
+            is_context: pinned.is_some(),
+            pinned_ref: pinned.map(|s| s.to_string()),
+            base_ref: None,
+            error: None,
+        }
+    }
+
+    fn sample() -> Vec<RepoInfo> {
+        vec![
+            info("github.com/acme/api", None),
+            info("github.com/acme/proto", Some("v1.0")),
+            info("github.com/acme/web", None),
+            info("github.com/other/web", Some("main")),
+        ]
+    }
+
+    fn names(infos: &[RepoInfo]) -> Vec<&str> {
+        infos.iter().map(|i| i.identity.as_str()).collect()
+    }
+
+    #[test]
+    fn test_apply() {
+        let cases: Vec<(&str, RepoSelector, Vec<&str>)> = vec![
+            (
+                "no filter",
+                RepoSelector::default(),
+                vec![
+                    "github.com/acme/api",
+                    "github.com/acme/proto",
+                    "github.com/acme/web",
+                    "github.com/other/web",
+                ],
+            ),
+            (
+                "active only",
+                RepoSelector {
+                    active: true,
+                    ..Default::default()
+                },
+                vec!["github.com/acme/api", "github.com/acme/web"],
+            ),
+            (
+                "context only",
+                RepoSelector {
+                    context: true,
+                    ..Default::default()
+                },
+                vec!["github.com/acme/proto", "github.com/other/web"],
+            ),
+            (
+                "named repos",
+                RepoSelector {
+                    repos: vec!["api".into(), "other/web".into()],
+                    ..Default::default()
+                },
+                vec!["github.com/acme/api", "github.com/other/web"],
+            ),
+            (
+                "named and active",
+                RepoSelector {
+                    active: true,
+                    repos: vec!["proto".into(), "acme/web".into()],
+                    ..Default::default()
+                },
+                vec!["github.com/acme/web"],
+            ),
+        ];
+        for (name, selector, want) in cases {
+            let got = selector.apply(sample()).unwrap();
+            assert_eq!(names(&got), want, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_apply_unknown_or_ambiguous_repo() {
+        let selector = RepoSelector {
+            repos: vec!["nope".into()],
+            ..Default::default()
+        };
+        assert!(selector.apply(sample()).is_err());
+
+        let selector = RepoSelector {
+            repos: vec!["web".into()],
+            ..Default::default()
+        };
+        let err = selector.apply(sample()).unwrap_err().to_string();
+        assert!(err.contains("ambiguous"), "{}", err);
+    }
+
+    #[test]
+    fn test_apply_changed() {
+        let (clean, _s1, _c1, _t1) = crate::testutil::setup_clone_repo();
+        let (dirty, _s2, _c2, _t2) = crate::testutil::setup_clone_repo();
+        let (ahead, _s3, _c3, _t3) = crate::testutil::setup_clone_repo();
+        std::fs::write(dirty.join("scratch.txt"), "wip").unwrap();
+        crate::testutil::local_commit(&ahead, "new.txt", "new");
+
+        let mut infos = Vec::new();
+        for (id, dir) in [
+            ("github.com/acme/clean", &clean),
+            ("github.com/acme/dirty", &dirty),
+            ("github.com/acme/ahead", &ahead),
+        ] {
+            let mut i = info(id, None);
+            i.clone_dir = dir.clone();
+            infos.push(i);
+        }
+
+        let selector = RepoSelector {
+            changed: true,
+            ..Default::default()
+        };
+        let got = selector.apply(infos).unwrap();
+        assert_eq!(
+            names(&got),
+            vec!["github.com/acme/dirty", "github.com/acme/ahead"]
+        );
+    }
+
+    #[test]
+    fn test_from_matches_without_flags() {
+        // Root matches (no subcommand) don't define the selection args.
+        let matches = crate::cli::build_cli().get_matches_from(["wsp"]);
+        let selector = RepoSelector::from_matches(&matches);
+        assert!(!selector.active && !selector.context && !selector.changed);
+        assert!(selector.repos.is_empty());
+    }
+}
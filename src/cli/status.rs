@@ -10,6 +10,7 @@ use crate::output::{self, Output, RepoStatusEntry, StatusOutput};
 use crate::workspace;
 
 use super::completers;
+use super::select::{self, RepoSelector};
 
 #[cfg(test)]
 mod tests {
@@ -23,6 +24,9 @@ mod tests {
             config_path: PathBuf::from("/nonexistent/config.yaml"),
             mirrors_dir: PathBuf::from("/nonexistent/mirrors"),
             workspaces_dir: PathBuf::from("/nonexistent/workspaces"),
+            trash_dir: PathBuf::from("/nonexistent/trash"),
+            journal_path: PathBuf::from("/nonexistent/journal.jsonl"),
+            locks_dir: PathBuf::from("/nonexistent/locks"),
         }
     }
 
@@ -41,10 +45,14 @@ mod tests {
 }
 
 pub fn cmd() -> Command {
-    Command::new("st")
-        .visible_alias("status")
-        .about("Git status across workspace repos")
-        .arg(Arg::new("workspace").add(ArgValueCandidates::new(completers::complete_workspaces)))
+    select::add_args(
+        Command::new("st")
+            .visible_alias("status")
+            .about("Git status across workspace repos")
+            .arg(
+                Arg::new("workspace").add(ArgValueCandidates::new(completers::complete_workspaces)),
+            ),
+    )
 }
 
 pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
@@ -59,29 +67,28 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     let meta = workspace::load_metadata(&ws_dir)
         .map_err(|e| anyhow::anyhow!("reading workspace: {}", e))?;
 
+    let selected = RepoSelector::from_matches(matches).apply(meta.repo_infos(&ws_dir))?;
     let mut repos = Vec::new();
 
-    for identity in meta.repos.keys() {
-        let dir_name = match meta.dir_name(identity) {
-            Ok(d) => d,
-            Err(e) => {
-                repos.push(RepoStatusEntry {
-                    name: identity.clone(),
-                    branch: String::new(),
-                    ahead: 0,
-                    changed: 0,
-                    has_upstream: false,
-                    status: String::new(),
-                    error: Some(e.to_string()),
-                });
-                continue;
-            }
-        };
+    for info in selected {
+        if let Some(e) = info.error {
+            repos.push(RepoStatusEntry {
+                name: info.identity,
+                branch: String::new(),
+                ahead: 0,
+                changed: 0,
+                has_upstream: false,
+                status: String::new(),
+                error: Some(e),
+            });
+            continue;
+        }
 
-        let repo_dir = ws_dir.join(&dir_name);
+        let dir_name = info.dir_name;
+        let repo_dir = info.clone_dir;
 
         let branch = git::branch_current(&repo_dir).unwrap_or_else(|_| "?".to_string());
-        let upstream = git::resolve_upstream_ref(&repo_dir);
+        let upstream = git::resolve_upstream_ref(&repo_dir, info.base_ref.as_deref());
         let has_upstream = matches!(upstream, git::UpstreamRef::Tracking);
         let ahead = git::ahead_count_from(&repo_dir, &upstream).unwrap_or(0);
         let changed = git::changed_file_count(&repo_dir).unwrap_or(0);
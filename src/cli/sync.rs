@@ -1,17 +1,26 @@
 use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
 use clap::{Arg, ArgAction, ArgMatches, Command};
 use clap_complete::engine::ArgValueCandidates;
+use serde::{Deserialize, Serialize};
 
 use super::completers;
 use crate::config::{self, Paths};
 use crate::git::{self, SyncAction};
-use crate::output::{Output, SyncOutput, SyncRepoResult};
+use crate::output::{
+    Output, SYNC_CONFLICT_KEPT, SYNC_STASH_POP_CONFLICT, SyncOutput, SyncRepoResult,
+};
 use crate::workspace::{self, RepoInfo};
 
+/// Progress of a `--keep-conflicts` sync, kept in the workspace directory
+/// until the run completes, is continued to the end, or is aborted.
+const STATE_FILE: &str = ".wsp-sync.yaml";
+
 pub fn cmd() -> Command {
     Command::new("sync")
         .about("Fetch and rebase/merge all workspace repos")
@@ -28,6 +37,106 @@ pub fn cmd() -> Command {
                 .action(ArgAction::SetTrue)
                 .help("Preview actions without executing"),
         )
+        .arg(
+            Arg::new("autostash")
+                .long("autostash")
+                .action(ArgAction::SetTrue)
+                .help("Stash uncommitted changes before syncing and re-apply them after"),
+        )
+        .arg(
+            Arg::new("keep-conflicts")
+                .long("keep-conflicts")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("dry-run")
+                .help("Stop at the first conflict and leave it for you to resolve"),
+        )
+        .arg(
+            Arg::new("continue")
+                .long("continue")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "strategy",
+                    "dry-run",
+                    "autostash",
+                    "keep-conflicts",
+                    "abort",
+                ])
+                .help("Resume a sync stopped by --keep-conflicts"),
+        )
+        .arg(
+            Arg::new("abort")
+                .long("abort")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["strategy", "dry-run", "autostash", "keep-conflicts"])
+                .help("Roll back a sync stopped by --keep-conflicts to the pre-sync HEADs"),
+        )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum RepoSyncStatus {
+    Pending,
+    Synced,
+    Failed,
+    Conflict,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SyncStateRepo {
+    name: String,
+    pre_sync_head: String,
+    status: RepoSyncStatus,
+    /// Commit of the autostash holding local changes not yet re-applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stash: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SyncState {
+    strategy: String,
+    #[serde(default)]
+    autostash: bool,
+    repos: Vec<SyncStateRepo>,
+}
+
+/// How `sync_repo` syncs each repo.
+struct SyncOptions<'a> {
+    strategy: &'a str,
+    dry_run: bool,
+    keep_conflicts: bool,
+    autostash: bool,
+}
+
+/// Stash message for changes set aside by `--autostash`.
+const AUTOSTASH_MESSAGE: &str = "wsp sync autostash";
+
+fn load_state(ws_dir: &Path) -> Result<Option<SyncState>> {
+    let path = ws_dir.join(STATE_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data = fs::read_to_string(&path)?;
+    let state = serde_yaml_ng::from_str(&data)
+        .map_err(|e| anyhow::anyhow!("parsing {}: {}", STATE_FILE, e))?;
+    Ok(Some(state))
+}
+
+fn save_state(ws_dir: &Path, state: &SyncState) -> Result<()> {
+    let data = serde_yaml_ng::to_string(state)?;
+    let mut tmp =
+        tempfile::NamedTempFile::new_in(ws_dir).context("creating temp file for atomic save")?;
+    tmp.write_all(data.as_bytes())
+        .context("writing sync state to temp file")?;
+    tmp.persist(ws_dir.join(STATE_FILE))
+        .context("renaming temp file to sync state")?;
+    Ok(())
+}
+
+fn clear_state(ws_dir: &Path) -> Result<()> {
+    match fs::remove_file(ws_dir.join(STATE_FILE)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
 }
 
 pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
@@ -41,6 +150,24 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     let meta = workspace::load_metadata(&ws_dir)
         .map_err(|e| anyhow::anyhow!("reading workspace: {}", e))?;
 
+    if matches.get_flag("continue") || matches.get_flag("abort") {
+        let Some(state) = load_state(&ws_dir)? else {
+            bail!("no sync in progress for workspace {:?}", meta.name);
+        };
+        let repos = if matches.get_flag("continue") {
+            continue_sync(&ws_dir, &meta.repo_infos(&ws_dir), state)?
+        } else {
+            abort_sync(&ws_dir, state)?
+        };
+        return Ok(Output::Sync(SyncOutput {
+            workspace: meta.name,
+            branch: meta.branch,
+            dry_run: false,
+            repos,
+            stack: Vec::new(),
+        }));
+    }
+
     let cfg = config::Config::load_from(&paths.config_path)?;
     let strategy = matches
         .get_one::<String>("strategy")
@@ -58,8 +185,89 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     }
 
     let dry_run = matches.get_flag("dry-run");
+    let keep_conflicts = matches.get_flag("keep-conflicts");
+    let opts = SyncOptions {
+        strategy,
+        dry_run,
+        keep_conflicts,
+        autostash: matches.get_flag("autostash") || cfg.sync_autostash.unwrap_or(false),
+    };
+
+    // Stacked workspace: sync the stack root first, so each workspace rebases
+    // onto an up-to-date parent branch.
+    let ancestors = workspace::ancestors(&ws_dir, &meta)?;
+    for (dir, name) in ancestors
+        .iter()
+        .map(|p| (&p.dir, &p.meta.name))
+        .chain([(&ws_dir, &meta.name)])
+    {
+        if load_state(dir)?.is_some() {
+            bail!(
+                "a sync is already in progress in workspace {:?}; run `wsp sync --continue` or `wsp sync --abort`",
+                name
+            );
+        }
+    }
+
+    let mut stack = Vec::new();
+    for parent in ancestors {
+        let repos = sync_workspace(&parent.dir, &parent.meta, &opts)?;
+        let failed = repos.iter().any(|r| !r.ok);
+        stack.push(SyncOutput {
+            workspace: parent.meta.name.clone(),
+            branch: parent.meta.branch,
+            dry_run,
+            repos,
+            stack: Vec::new(),
+        });
+        if failed {
+            let repos = meta
+                .repo_infos(&ws_dir)
+                .into_iter()
+                .map(|info| SyncRepoResult {
+                    name: info.dir_name,
+                    action: "skipped".into(),
+                    ok: false,
+                    detail: None,
+                    error: Some(format!(
+                        "parent workspace {:?} did not sync cleanly",
+                        parent.meta.name
+                    )),
+                    repo_dir: info.clone_dir,
+                    target: String::new(),
+                    strategy: strategy.to_string(),
+                })
+                .collect();
+            return Ok(Output::Sync(SyncOutput {
+                workspace: meta.name,
+                branch: meta.branch,
+                dry_run,
+                repos,
+                stack,
+            }));
+        }
+    }
+
+    let results = sync_workspace(&ws_dir, &meta, &opts)?;
+    Ok(Output::Sync(SyncOutput {
+        workspace: meta.name,
+        branch: meta.branch,
+        dry_run,
+        repos: results,
+        stack,
+    }))
+}
 
-    let repo_infos = meta.repo_infos(&ws_dir);
+/// Fetches and syncs every repo of one workspace.
+fn sync_workspace(
+    ws_dir: &Path,
+    meta: &workspace::Metadata,
+    opts: &SyncOptions,
+) -> Result<Vec<SyncRepoResult>> {
+    let dry_run = opts.dry_run;
+    let strategy = opts.strategy;
+    let repo_infos = meta.repo_infos(ws_dir);
+    let parent = meta.load_parent(ws_dir);
 
     // Phase 1: Parallel fetch (skip if dry-run)
     let fetch_failures: HashSet<String> = if !dry_run {
@@ -74,8 +282,15 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
                 .iter()
                 .map(|info| {
                     let progress = &progress;
+                    let parent_clone = parent
+                        .as_ref()
+                        .filter(|_| !info.is_context)
+                        .and_then(|p| p.clone_dir(&info.identity));
                     s.spawn(move || {
-                        let result = git::fetch_remote_prune(&info.clone_dir, "origin");
+                        let mut result = git::fetch_remote_prune(&info.clone_dir, "origin");
+                        if let (Ok(()), Some(pc)) = (&result, &parent_clone) {
+                            result = workspace::fetch_parent(&info.clone_dir, pc);
+                        }
                         let _lock = progress.lock().unwrap_or_else(|e| e.into_inner());
                         match &result {
                             Ok(()) => eprintln!("  ok    {}", info.dir_name),
@@ -102,162 +317,476 @@ pub fn run(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
     };
 
     // Phase 2: Serial sync
+    let mut state = opts.keep_conflicts.then(|| SyncState {
+        strategy: strategy.to_string(),
+        autostash: opts.autostash,
+        repos: repo_infos
+            .iter()
+            .filter(|info| info.error.is_none())
+            .filter_map(|info| {
+                Some(SyncStateRepo {
+                    name: info.dir_name.clone(),
+                    pre_sync_head: git::head_sha(&info.clone_dir).ok()?,
+                    status: RepoSyncStatus::Pending,
+                    stash: None,
+                })
+            })
+            .collect(),
+    });
+    if let Some(ref state) = state {
+        save_state(ws_dir, state)?;
+    }
+
     let mut results = Vec::new();
     for info in &repo_infos {
-        if let Some(ref e) = info.error {
+        let fetch_failed = fetch_failures.contains(&info.dir_name);
+        let (result, stash) = sync_repo(info, opts, fetch_failed);
+        let status = status_of(&result);
+        results.push(result);
+
+        if let Some(ref mut state) = state {
+            if let Some(entry) = state.repos.iter_mut().find(|r| r.name == info.dir_name) {
+                entry.status = status;
+                entry.stash = stash;
+            }
+            save_state(ws_dir, state)?;
+        }
+
+        if status == RepoSyncStatus::Conflict {
+            break;
+        }
+    }
+
+    if let Some(ref state) = state {
+        results.extend(pending_results(state, strategy));
+        if !state.repos.iter().any(|r| is_unfinished(r.status)) {
+            clear_state(ws_dir)?;
+        }
+    }
+
+    Ok(results)
+}
+
+fn status_of(result: &SyncRepoResult) -> RepoSyncStatus {
+    if result.error.as_deref() == Some(SYNC_CONFLICT_KEPT) {
+        RepoSyncStatus::Conflict
+    } else if result.ok {
+        RepoSyncStatus::Synced
+    } else {
+        RepoSyncStatus::Failed
+    }
+}
+
+fn is_unfinished(status: RepoSyncStatus) -> bool {
+    matches!(status, RepoSyncStatus::Pending | RepoSyncStatus::Conflict)
+}
+
+/// Results for repos not yet synced because an earlier repo stopped on a conflict.
+fn pending_results(state: &SyncState, strategy: &str) -> Vec<SyncRepoResult> {
+    state
+        .repos
+        .iter()
+        .filter(|r| r.status == RepoSyncStatus::Pending)
+        .map(|r| SyncRepoResult {
+            name: r.name.clone(),
+            action: "pending".into(),
+            ok: true,
+            detail: Some("waiting for `wsp sync --continue`".into()),
+            error: None,
+            repo_dir: PathBuf::new(),
+            target: String::new(),
+            strategy: strategy.to_string(),
+        })
+        .collect()
+}
+
+/// Finishes the conflicted repo (once the user resolved it) and syncs the
+/// repos that were still pending, stopping again at the next conflict.
+fn continue_sync(
+    ws_dir: &Path,
+    repo_infos: &[RepoInfo],
+    mut state: SyncState,
+) -> Result<Vec<SyncRepoResult>> {
+    let strategy = state.strategy.clone();
+    let opts = SyncOptions {
+        strategy: &strategy,
+        dry_run: false,
+        keep_conflicts: true,
+        autostash: state.autostash,
+    };
+    let mut results = Vec::new();
+    let mut stopped = false;
+
+    for i in 0..state.repos.len() {
+        let status = state.repos[i].status;
+        if !is_unfinished(status) {
+            continue;
+        }
+        let name = state.repos[i].name.clone();
+        let Some(info) = repo_infos.iter().find(|r| r.dir_name == name) else {
+            state.repos[i].status = RepoSyncStatus::Failed;
             results.push(SyncRepoResult {
-                name: info.dir_name.clone(),
+                name,
                 action: String::new(),
                 ok: false,
                 detail: None,
-                error: Some(e.clone()),
-                repo_dir: info.clone_dir.clone(),
+                error: Some("no longer in workspace".into()),
+                repo_dir: PathBuf::new(),
                 target: String::new(),
-                strategy: strategy.to_string(),
+                strategy: strategy.clone(),
             });
             continue;
+        };
+
+        let (result, stash) = if status == RepoSyncStatus::Conflict {
+            let action = format!("{} --continue", strategy);
+            let stash = state.repos[i].stash.take();
+            match git::continue_in_progress(&info.clone_dir) {
+                Ok(()) => {
+                    let result = SyncRepoResult {
+                        name: info.dir_name.clone(),
+                        action,
+                        ok: true,
+                        detail: Some("conflicts resolved".into()),
+                        error: None,
+                        repo_dir: info.clone_dir.clone(),
+                        target: String::new(),
+                        strategy: strategy.clone(),
+                    };
+                    match stash {
+                        Some(sha) => (pop_autostash(result, &sha), None),
+                        None => (result, None),
+                    }
+                }
+                Err(_) => (
+                    SyncRepoResult {
+                        name: info.dir_name.clone(),
+                        action,
+                        ok: false,
+                        detail: None,
+                        error: Some(SYNC_CONFLICT_KEPT.into()),
+                        repo_dir: info.clone_dir.clone(),
+                        target: String::new(),
+                        strategy: strategy.clone(),
+                    },
+                    stash,
+                ),
+            }
+        } else {
+            sync_repo(info, &opts, false)
+        };
+
+        state.repos[i].status = status_of(&result);
+        state.repos[i].stash = stash;
+        results.push(result);
+        save_state(ws_dir, &state)?;
+
+        if state.repos[i].status == RepoSyncStatus::Conflict {
+            stopped = true;
+            break;
         }
+    }
 
-        let fetch_failed = fetch_failures.contains(&info.dir_name);
+    if stopped {
+        results.extend(pending_results(&state, &strategy));
+    } else {
+        clear_state(ws_dir)?;
+    }
+    Ok(results)
+}
+
+/// Rolls every repo touched by the stopped sync back to its pre-sync HEAD.
+fn abort_sync(ws_dir: &Path, state: SyncState) -> Result<Vec<SyncRepoResult>> {
+    let mut results = Vec::new();
+    let mut all_ok = true;
 
-        if info.is_context {
-            let pinned = info.pinned_ref.as_deref().unwrap_or("HEAD");
-            let action = format!("checkout {}", pinned);
-            if dry_run {
-                results.push(SyncRepoResult {
+    for entry in &state.repos {
+        if !matches!(
+            entry.status,
+            RepoSyncStatus::Synced | RepoSyncStatus::Conflict
+        ) {
+            continue;
+        }
+        let dir = ws_dir.join(&entry.name);
+        let short = &entry.pre_sync_head[..entry.pre_sync_head.len().min(7)];
+        let action = format!("reset to {}", short);
+
+        let rolled_back = git::abort_in_progress(&dir).and_then(|()| {
+            if git::head_sha(&dir)? == entry.pre_sync_head {
+                return Ok(false);
+            }
+            git::reset_keep(&dir, &entry.pre_sync_head)?;
+            Ok(true)
+        });
+
+        let (ok, detail, error) = match rolled_back {
+            Ok(true) => (true, Some("rolled back".to_string()), None),
+            Ok(false) => (true, Some("already at pre-sync HEAD".to_string()), None),
+            Err(e) => {
+                all_ok = false;
+                (false, None, Some(e.to_string()))
+            }
+        };
+        let result = SyncRepoResult {
+            name: entry.name.clone(),
+            action,
+            ok,
+            detail,
+            error,
+            repo_dir: dir,
+            target: String::new(),
+            strategy: state.strategy.clone(),
+        };
+        match entry.stash {
+            Some(ref sha) if result.ok => results.push(pop_autostash(result, sha)),
+            _ => results.push(result),
+        }
+    }
+
+    // Keep the state on failure so the abort can be retried.
+    if all_ok {
+        clear_state(ws_dir)?;
+    }
+    Ok(results)
+}
+
+/// Syncs a single repo: context repos follow their pinned ref, active repos
+/// are rebased/merged onto the default branch. Also returns the autostash
+/// commit if local changes were left in the stash (only when a conflict is
+/// kept for the user to resolve).
+fn sync_repo(
+    info: &RepoInfo,
+    opts: &SyncOptions<'_>,
+    fetch_failed: bool,
+) -> (SyncRepoResult, Option<String>) {
+    let strategy = opts.strategy;
+    if let Some(ref e) = info.error {
+        let result = SyncRepoResult {
+            name: info.dir_name.clone(),
+            action: String::new(),
+            ok: false,
+            detail: None,
+            error: Some(e.clone()),
+            repo_dir: info.clone_dir.clone(),
+            target: String::new(),
+            strategy: strategy.to_string(),
+        };
+        return (result, None);
+    }
+
+    if info.is_context {
+        let pinned = info.pinned_ref.as_deref().unwrap_or("HEAD");
+        let action = format!("checkout {}", pinned);
+        if opts.dry_run {
+            let result = SyncRepoResult {
+                name: info.dir_name.clone(),
+                action,
+                ok: true,
+                detail: Some("(dry run)".into()),
+                error: None,
+                repo_dir: info.clone_dir.clone(),
+                target: pinned.to_string(),
+                strategy: String::new(),
+            };
+            return (result, None);
+        }
+        let result = match sync_context_repo(&info.clone_dir, pinned) {
+            Ok(mut detail) => {
+                if fetch_failed {
+                    detail.push_str(" (fetch failed, data may be stale)");
+                }
+                SyncRepoResult {
                     name: info.dir_name.clone(),
                     action,
                     ok: true,
-                    detail: Some("(dry run)".into()),
+                    detail: Some(detail),
                     error: None,
                     repo_dir: info.clone_dir.clone(),
                     target: pinned.to_string(),
                     strategy: String::new(),
-                });
-            } else {
-                match sync_context_repo(&info.clone_dir, pinned) {
-                    Ok(mut detail) => {
-                        if fetch_failed {
-                            detail.push_str(" (fetch failed, data may be stale)");
-                        }
-                        results.push(SyncRepoResult {
-                            name: info.dir_name.clone(),
-                            action,
-                            ok: true,
-                            detail: Some(detail),
-                            error: None,
-                            repo_dir: info.clone_dir.clone(),
-                            target: pinned.to_string(),
-                            strategy: String::new(),
-                        });
-                    }
-                    Err(e) => {
-                        results.push(SyncRepoResult {
-                            name: info.dir_name.clone(),
-                            action,
-                            ok: false,
-                            detail: None,
-                            error: Some(e.to_string()),
-                            repo_dir: info.clone_dir.clone(),
-                            target: pinned.to_string(),
-                            strategy: String::new(),
-                        });
-                    }
                 }
             }
-        } else {
-            // Active repo: resolve default branch first (used in all paths)
-            let default_branch = match git::default_branch(&info.clone_dir) {
-                Ok(b) => b,
-                Err(e) => {
-                    results.push(SyncRepoResult {
-                        name: info.dir_name.clone(),
-                        action: format!("{} onto origin/?", strategy),
-                        ok: false,
-                        detail: None,
-                        error: Some(format!("cannot detect default branch: {}", e)),
-                        repo_dir: info.clone_dir.clone(),
-                        target: String::new(),
-                        strategy: strategy.to_string(),
-                    });
-                    continue;
-                }
+            Err(e) => SyncRepoResult {
+                name: info.dir_name.clone(),
+                action,
+                ok: false,
+                detail: None,
+                error: Some(e.to_string()),
+                repo_dir: info.clone_dir.clone(),
+                target: pinned.to_string(),
+                strategy: String::new(),
+            },
+        };
+        return (result, None);
+    }
+
+    // Active repo: resolve the base branch first (used in all paths)
+    let resolved = match &info.base_ref {
+        Some(r) => Ok(r.clone()),
+        None => git::default_branch(&info.clone_dir).map(|b| format!("origin/{}", b)),
+    };
+    let target = match resolved {
+        Ok(r) => r,
+        Err(e) => {
+            let result = SyncRepoResult {
+                name: info.dir_name.clone(),
+                action: format!("{} onto origin/?", strategy),
+                ok: false,
+                detail: None,
+                error: Some(format!("cannot detect default branch: {}", e)),
+                repo_dir: info.clone_dir.clone(),
+                target: String::new(),
+                strategy: strategy.to_string(),
             };
-            let target = format!("origin/{}", default_branch);
-            let action = format!("{} onto {}", strategy, target);
+            return (result, None);
+        }
+    };
+    let action = format!("{} onto {}", strategy, target);
+
+    // Check for dirty working tree
+    let changed = git::changed_file_count(&info.clone_dir).unwrap_or(0);
+    if changed > 0 && !opts.autostash {
+        let result = SyncRepoResult {
+            name: info.dir_name.clone(),
+            action,
+            ok: false,
+            detail: None,
+            error: Some(format!(
+                "uncommitted changes ({} file(s)), skipping",
+                changed
+            )),
+            repo_dir: info.clone_dir.clone(),
+            target,
+            strategy: strategy.to_string(),
+        };
+        return (result, None);
+    }
 
-            // Check for dirty working tree
-            let changed = git::changed_file_count(&info.clone_dir).unwrap_or(0);
-            if changed > 0 {
-                results.push(SyncRepoResult {
+    if opts.dry_run {
+        let mut detail = describe_pending_sync(&info.clone_dir, &target);
+        if changed > 0 {
+            detail.push_str(&format!(" (would autostash {} file(s))", changed));
+        }
+        let result = SyncRepoResult {
+            name: info.dir_name.clone(),
+            action,
+            ok: true,
+            detail: Some(detail),
+            error: None,
+            repo_dir: info.clone_dir.clone(),
+            target,
+            strategy: strategy.to_string(),
+        };
+        return (result, None);
+    }
+
+    let stash = if changed > 0 {
+        match git::stash_push(&info.clone_dir, AUTOSTASH_MESSAGE) {
+            Ok(sha) => Some(sha),
+            Err(e) => {
+                let result = SyncRepoResult {
                     name: info.dir_name.clone(),
                     action,
                     ok: false,
                     detail: None,
-                    error: Some(format!(
-                        "uncommitted changes ({} file(s)), skipping",
-                        changed
-                    )),
+                    error: Some(format!("autostash failed: {}", e)),
                     repo_dir: info.clone_dir.clone(),
                     target,
                     strategy: strategy.to_string(),
-                });
-                continue;
+                };
+                return (result, None);
             }
+        }
+    } else {
+        None
+    };
 
-            if dry_run {
-                let detail = describe_pending_sync(&info.clone_dir, &target);
-                results.push(SyncRepoResult {
+    let synced = if opts.keep_conflicts {
+        sync_active_repo_keep_conflicts(&info.clone_dir, &target, strategy)
+    } else {
+        sync_active_repo(&info.clone_dir, &target, strategy)
+    };
+    let result = match synced {
+        Ok(sync_action) => {
+            let mut detail = format_sync_action(&sync_action);
+            if fetch_failed {
+                detail.push_str(" (fetch failed, data may be stale)");
+            }
+            SyncRepoResult {
+                name: info.dir_name.clone(),
+                action,
+                ok: true,
+                detail: Some(detail),
+                error: None,
+                repo_dir: info.clone_dir.clone(),
+                target,
+                strategy: strategy.to_string(),
+            }
+        }
+        Err(_) => {
+            let in_progress =
+                git::rebase_in_progress(&info.clone_dir) || git::merge_in_progress(&info.clone_dir);
+            if opts.keep_conflicts && in_progress {
+                // The stash stays until the conflict is continued or aborted.
+                let result = SyncRepoResult {
                     name: info.dir_name.clone(),
                     action,
-                    ok: true,
-                    detail: Some(detail),
-                    error: None,
+                    ok: false,
+                    detail: stash.is_some().then(|| "local changes autostashed".into()),
+                    error: Some(SYNC_CONFLICT_KEPT.into()),
                     repo_dir: info.clone_dir.clone(),
                     target,
                     strategy: strategy.to_string(),
-                });
-            } else {
-                match sync_active_repo(&info.clone_dir, &target, strategy) {
-                    Ok(sync_action) => {
-                        let mut detail = format_sync_action(&sync_action);
-                        if fetch_failed {
-                            detail.push_str(" (fetch failed, data may be stale)");
-                        }
-                        results.push(SyncRepoResult {
-                            name: info.dir_name.clone(),
-                            action,
-                            ok: true,
-                            detail: Some(detail),
-                            error: None,
-                            repo_dir: info.clone_dir.clone(),
-                            target,
-                            strategy: strategy.to_string(),
-                        });
-                    }
-                    Err(_) => {
-                        results.push(SyncRepoResult {
-                            name: info.dir_name.clone(),
-                            action,
-                            ok: false,
-                            detail: None,
-                            error: Some("aborted, repo unchanged".into()),
-                            repo_dir: info.clone_dir.clone(),
-                            target,
-                            strategy: strategy.to_string(),
-                        });
-                    }
-                }
+                };
+                return (result, stash);
+            }
+            SyncRepoResult {
+                name: info.dir_name.clone(),
+                action,
+                ok: false,
+                detail: None,
+                error: Some("aborted, repo unchanged".into()),
+                repo_dir: info.clone_dir.clone(),
+                target,
+                strategy: strategy.to_string(),
             }
         }
+    };
+
+    match stash {
+        Some(sha) => (pop_autostash(result, &sha), None),
+        None => (result, None),
     }
+}
 
-    Ok(Output::Sync(SyncOutput {
-        workspace: meta.name,
-        branch: meta.branch,
-        dry_run,
-        repos: results,
-    }))
+/// Re-applies changes stashed by `--autostash` as stash commit `sha`. If they
+/// no longer apply cleanly, the result becomes a distinct stash-pop-conflict
+/// action; the stash entry is kept so nothing is lost.
+fn pop_autostash(mut result: SyncRepoResult, sha: &str) -> SyncRepoResult {
+    match git::stash_pop(&result.repo_dir, sha) {
+        Ok(()) => {
+            if result.ok
+                && let Some(ref mut detail) = result.detail
+            {
+                detail.push_str(", local changes restored");
+            }
+            result
+        }
+        Err(_) => {
+            let outcome = result
+                .error
+                .take()
+                .or(result.detail.take())
+                .unwrap_or_default();
+            result.action = SYNC_STASH_POP_CONFLICT.into();
+            result.ok = false;
+            result.detail = Some(format!(
+                "{}; local changes conflict, kept in `git stash list`",
+                outcome
+            ));
+            result
+        }
+    }
 }
 
 fn sync_active_repo(dir: &Path, target: &str, strategy: &str) -> Result<SyncAction> {
@@ -267,6 +796,13 @@ fn sync_active_repo(dir: &Path, target: &str, strategy: &str) -> Result<SyncActi
     }
 }
 
+fn sync_active_repo_keep_conflicts(dir: &Path, target: &str, strategy: &str) -> Result<SyncAction> {
+    match strategy {
+        "merge" => git::merge_from_keep_conflicts(dir, target),
+        _ => git::rebase_onto_keep_conflicts(dir, target),
+    }
+}
+
 fn sync_context_repo(dir: &Path, pinned_ref: &str) -> Result<String> {
     let origin_ref = format!("origin/{}", pinned_ref);
 
@@ -420,4 +956,225 @@ mod tests {
         assert!(result2.is_ok(), "clone2 should sync successfully");
         assert_eq!(result2.unwrap(), SyncAction::FastForward { commits: 1 });
     }
+
+    /// A workspace dir with clones `a` and `b` of one source repo, where `a`
+    /// has a local commit that conflicts with a new upstream commit and `b`
+    /// can fast-forward. Returns (ws_tempdir, source_tempdir, repo infos).
+    fn conflicting_workspace(
+        order: &[&str],
+    ) -> (tempfile::TempDir, tempfile::TempDir, Vec<RepoInfo>) {
+        use crate::testutil::{clone_source, local_commit, setup_clone_repo};
+
+        let (_clone, source, _ct, source_tmp) = setup_clone_repo();
+        let ws = tempfile::tempdir().unwrap();
+        for name in ["a", "b"] {
+            clone_source(&source, &ws.path().join(name));
+        }
+        local_commit(&ws.path().join("a"), "conflict.txt", "local version");
+        local_commit(&source, "conflict.txt", "upstream version");
+        for name in ["a", "b"] {
+            git::fetch_remote_prune(&ws.path().join(name), "origin").unwrap();
+        }
+
+        let infos = order
+            .iter()
+            .map(|name| RepoInfo {
+                identity: format!("test.local/acme/{}", name),
+                dir_name: name.to_string(),
+                clone_dir: ws.path().join(name),
+                is_context: false,
+                pinned_ref: None,
+                base_ref: None,
+                error: None,
+            })
+            .collect();
+        (ws, source_tmp, infos)
+    }
+
+    fn pending_state(infos: &[RepoInfo]) -> SyncState {
+        SyncState {
+            strategy: "rebase".into(),
+            autostash: false,
+            repos: infos
+                .iter()
+                .map(|i| SyncStateRepo {
+                    name: i.dir_name.clone(),
+                    pre_sync_head: git::head_sha(&i.clone_dir).unwrap(),
+                    status: RepoSyncStatus::Pending,
+                    stash: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn statuses(ws_dir: &Path) -> Vec<RepoSyncStatus> {
+        load_state(ws_dir)
+            .unwrap()
+            .unwrap()
+            .repos
+            .iter()
+            .map(|r| r.status)
+            .collect()
+    }
+
+    #[test]
+    fn test_keep_conflicts_then_continue() {
+        let (ws, _st, infos) = conflicting_workspace(&["a", "b"]);
+        let ws_dir = ws.path();
+        let a = &infos[0].clone_dir;
+
+        // Stops at the conflict in `a`, leaving `b` pending.
+        let results = continue_sync(ws_dir, &infos, pending_state(&infos)).unwrap();
+        assert_eq!(results[0].error.as_deref(), Some(SYNC_CONFLICT_KEPT));
+        assert_eq!(results[1].action, "pending");
+        assert!(git::rebase_in_progress(a));
+        assert_eq!(
+            statuses(ws_dir),
+            vec![RepoSyncStatus::Conflict, RepoSyncStatus::Pending]
+        );
+
+        // Unresolved: still stopped.
+        let state = load_state(ws_dir).unwrap().unwrap();
+        let results = continue_sync(ws_dir, &infos, state).unwrap();
+        assert_eq!(results[0].error.as_deref(), Some(SYNC_CONFLICT_KEPT));
+
+        // Resolve and continue: both repos finish, state file is removed.
+        std::fs::write(a.join("conflict.txt"), "resolved").unwrap();
+        git::run(Some(a), &["add", "conflict.txt"]).unwrap();
+        let state = load_state(ws_dir).unwrap().unwrap();
+        let results = continue_sync(ws_dir, &infos, state).unwrap();
+        assert!(results.iter().all(|r| r.ok), "{:?}", results[0].error);
+        assert_eq!(
+            results[1].detail.as_deref(),
+            Some("fast-forwarded 1 commit(s)")
+        );
+        assert!(!git::rebase_in_progress(a));
+        assert!(load_state(ws_dir).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_abort_rolls_back_to_pre_sync_heads() {
+        let (ws, _st, infos) = conflicting_workspace(&["b", "a"]);
+        let ws_dir = ws.path();
+        let state = pending_state(&infos);
+        let before: Vec<String> = state
+            .repos
+            .iter()
+            .map(|r| r.pre_sync_head.clone())
+            .collect();
+
+        // `b` fast-forwards, then `a` stops on the conflict.
+        continue_sync(ws_dir, &infos, state).unwrap();
+        assert_eq!(
+            statuses(ws_dir),
+            vec![RepoSyncStatus::Synced, RepoSyncStatus::Conflict]
+        );
+        assert_ne!(git::head_sha(&infos[0].clone_dir).unwrap(), before[0]);
+
+        let state = load_state(ws_dir).unwrap().unwrap();
+        let results = abort_sync(ws_dir, state).unwrap();
+        assert!(results.iter().all(|r| r.ok));
+        for (info, head) in infos.iter().zip(&before) {
+            assert_eq!(&git::head_sha(&info.clone_dir).unwrap(), head);
+            assert!(!git::rebase_in_progress(&info.clone_dir));
+        }
+        assert!(load_state(ws_dir).unwrap().is_none());
+    }
+
+    fn active_info(dir: &Path) -> RepoInfo {
+        RepoInfo {
+            identity: "test.local/acme/repo".into(),
+            dir_name: "repo".into(),
+            clone_dir: dir.to_path_buf(),
+            is_context: false,
+            pinned_ref: None,
+            base_ref: None,
+            error: None,
+        }
+    }
+
+    fn autostash_opts() -> SyncOptions<'static> {
+        SyncOptions {
+            strategy: "rebase",
+            dry_run: false,
+            keep_conflicts: false,
+            autostash: true,
+        }
+    }
+
+    #[test]
+    fn test_autostash_restores_local_changes() {
+        use crate::testutil::{local_commit, setup_clone_repo};
+
+        let (clone, source, _ct, _st) = setup_clone_repo();
+        local_commit(&clone, "local.txt", "v1");
+        std::fs::write(clone.join("local.txt"), "wip").unwrap();
+        local_commit(&source, "upstream.txt", "upstream");
+        git::fetch_remote_prune(&clone, "origin").unwrap();
+
+        let (result, stash) = sync_repo(&active_info(&clone), &autostash_opts(), false);
+        assert!(result.ok, "{:?}", result.error);
+        assert!(stash.is_none());
+        assert_eq!(
+            result.detail.as_deref(),
+            Some("1 commit(s) rebased, local changes restored")
+        );
+        assert_eq!(
+            std::fs::read_to_string(clone.join("local.txt")).unwrap(),
+            "wip"
+        );
+        assert!(
+            git::run(Some(&clone), &["stash", "list"])
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_autostash_pop_conflict_is_distinct_action() {
+        use crate::testutil::{local_commit, setup_clone_repo};
+
+        let (clone, source, _ct, _st) = setup_clone_repo();
+        local_commit(&source, "shared.txt", "v1");
+        git::fetch_remote_prune(&clone, "origin").unwrap();
+        git::rebase_onto(&clone, "origin/main").unwrap();
+
+        std::fs::write(clone.join("shared.txt"), "local wip").unwrap();
+        local_commit(&source, "shared.txt", "upstream v2");
+        git::fetch_remote_prune(&clone, "origin").unwrap();
+
+        let (result, _) = sync_repo(&active_info(&clone), &autostash_opts(), false);
+        assert!(!result.ok);
+        assert_eq!(result.action, SYNC_STASH_POP_CONFLICT);
+        assert!(result.error.is_none());
+        assert!(
+            result
+                .detail
+                .as_deref()
+                .unwrap()
+                .starts_with("fast-forwarded 1 commit(s); local changes conflict")
+        );
+        assert!(
+            !git::run(Some(&clone), &["stash", "list"])
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_dirty_repo_skipped_without_autostash() {
+        let (clone, _source, _ct, _st) = crate::testutil::setup_clone_repo();
+        std::fs::write(clone.join("wip.txt"), "wip").unwrap();
+
+        let opts = SyncOptions {
+            autostash: false,
+            ..autostash_opts()
+        };
+        let (result, _) = sync_repo(&active_info(&clone), &opts, false);
+        assert!(!result.ok);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("uncommitted changes (1 file(s)), skipping")
+        );
+    }
 }
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use clap::{Arg, ArgMatches, Command};
+
+use crate::config::{self, Paths};
+use crate::output::{MutationOutput, Output, TrashEntry, TrashListOutput};
+use crate::trash;
+
+pub fn list_cmd() -> Command {
+    Command::new("list")
+        .visible_alias("ls")
+        .about("List removed workspaces")
+}
+
+pub fn restore_cmd() -> Command {
+    Command::new("restore")
+        .about("Restore a removed workspace")
+        .arg(
+            Arg::new("workspace")
+                .required(true)
+                .help("Workspace name, or a trash ID from `wsp trash list`"),
+        )
+}
+
+pub fn empty_cmd() -> Command {
+    Command::new("empty")
+        .about("Permanently delete removed workspaces")
+        .arg(
+            Arg::new("older-than")
+                .long("older-than")
+                .value_name("AGE")
+                .help("Only delete workspaces removed more than AGE ago (e.g. 30d, 12h, 2w)"),
+        )
+}
+
+pub fn run_list(_matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    let entries = trash::list(&paths.trash_dir)?
+        .into_iter()
+        .map(|e| TrashEntry {
+            id: e.id,
+            name: e.manifest.name,
+            branch: e.manifest.branch,
+            removed: e.manifest.removed,
+            reason: e.manifest.reason,
+            path: e.manifest.path.display().to_string(),
+        })
+        .collect();
+    Ok(Output::TrashList(TrashListOutput { entries }))
+}
+
+pub fn run_restore(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    let name = matches.get_one::<String>("workspace").unwrap();
+    let entry = trash::find(&paths.trash_dir, name)?;
+    let ws_dir = trash::restore(paths, &entry)?;
+    Ok(Output::Mutation(MutationOutput {
+        ok: true,
+        message: format!(
+            "Workspace {:?} restored to {}",
+            entry.manifest.name,
+            ws_dir.display()
+        ),
+    }))
+}
+
+pub fn run_empty(matches: &ArgMatches, paths: &Paths) -> Result<Output> {
+    let older_than = match matches.get_one::<String>("older-than") {
+        Some(age) => Some(config::parse_age(age)?),
+        None => None,
+    };
+    let purged = trash::purge(&paths.trash_dir, older_than)?;
+    Ok(Output::Mutation(MutationOutput {
+        ok: true,
+        message: format!("Deleted {} workspace(s) from the trash.", purged.len()),
+    }))
+}
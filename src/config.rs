@@ -3,8 +3,8 @@ use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,8 +32,26 @@ pub struct Config {
     pub workspaces_dir: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub sync_strategy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_autostash: Option<bool>,
+    /// How long removed workspaces stay in the trash: an age like `30d`, or
+    /// `never` to keep them until `wsp trash empty`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trash_retention: Option<String>,
+    /// Which root manifest the node integration writes: `pnpm` (the
+    /// default), `npm` or `yarn`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_package_manager: Option<String>,
+    /// Have the python integration create `.venv` at the workspace root and
+    /// install the generated requirements into it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub python_venv: Option<bool>,
 }
 
+pub const DEFAULT_TRASH_RETENTION: &str = "30d";
+
+pub const DEFAULT_NODE_PACKAGE_MANAGER: &str = "pnpm";
+
 impl Config {
     pub fn load_from(path: &Path) -> Result<Config> {
         if !path.exists() {
@@ -49,6 +67,15 @@ impl Config {
         self.repos.get(identity).map(|e| e.url.as_str())
     }
 
+    /// Age after which trashed workspaces are deleted; None keeps them.
+    pub fn trash_retention(&self) -> Result<Option<Duration>> {
+        parse_retention(
+            self.trash_retention
+                .as_deref()
+                .unwrap_or(DEFAULT_TRASH_RETENTION),
+        )
+    }
+
     pub fn save_to(&self, path: &Path) -> Result<()> {
         let dir = path.parent().context("config path has no parent")?;
         fs::create_dir_all(dir)?;
@@ -67,6 +94,9 @@ pub struct Paths {
     pub config_path: PathBuf,
     pub mirrors_dir: PathBuf,
     pub workspaces_dir: PathBuf,
+    pub trash_dir: PathBuf,
+    pub journal_path: PathBuf,
+    pub locks_dir: PathBuf,
 }
 
 impl Paths {
@@ -84,6 +114,9 @@ impl Paths {
             config_path,
             mirrors_dir: data.join("mirrors"),
             workspaces_dir,
+            trash_dir: data.join("trash"),
+            journal_path: data.join("journal.jsonl"),
+            locks_dir: data.join("locks"),
         })
     }
 
@@ -94,10 +127,42 @@ impl Paths {
             config_path: data_dir.join("config.yaml"),
             mirrors_dir: data_dir.join("mirrors"),
             workspaces_dir: workspaces_dir.to_path_buf(),
+            trash_dir: data_dir.join("trash"),
+            journal_path: data_dir.join("journal.jsonl"),
+            locks_dir: data_dir.join("locks"),
         }
     }
 }
 
+/// Parses an age like `30d`, `12h` or `2w`.
+pub fn parse_age(s: &str) -> Result<Duration> {
+    let invalid = || anyhow::anyhow!("invalid age {:?}: expected e.g. 30d, 12h or 2w", s);
+    let split = s.len().saturating_sub(1);
+    let (num, unit) = match (s.get(..split), s.get(split..)) {
+        (Some(num), Some(unit)) => (num, unit),
+        _ => return Err(invalid()),
+    };
+    let n: i64 = num.parse().map_err(|_| invalid())?;
+    if n < 0 {
+        bail!(invalid());
+    }
+    let age = match unit {
+        "h" => Duration::try_hours(n),
+        "d" => Duration::try_days(n),
+        "w" => Duration::try_weeks(n),
+        _ => None,
+    };
+    age.ok_or_else(invalid)
+}
+
+/// Parses a retention setting: an age, or `never` (None).
+pub fn parse_retention(s: &str) -> Result<Option<Duration>> {
+    if s == "never" {
+        return Ok(None);
+    }
+    parse_age(s).map(Some)
+}
+
 /// Resolves the ws data directory. Accepts injectable overrides for testing.
 pub fn data_dir_with(xdg_data_home: Option<&str>, home: Option<&Path>) -> Result<PathBuf> {
     if let Some(xdg) = xdg_data_home.filter(|s| !s.is_empty()) {
@@ -129,6 +194,42 @@ mod tests {
     use super::*;
     use chrono::TimeZone;
 
+    #[test]
+    fn test_parse_age() {
+        let cases = vec![
+            ("30d", Some(Duration::days(30))),
+            ("12h", Some(Duration::hours(12))),
+            ("2w", Some(Duration::weeks(2))),
+            ("0d", Some(Duration::zero())),
+            ("30", None),
+            ("d", None),
+            ("", None),
+            ("-1d", None),
+            ("3m", None),
+            ("1.5d", None),
+            ("99999999999999w", None),
+        ];
+        for (input, want) in cases {
+            match (parse_age(input), want) {
+                (Ok(got), Some(w)) => assert_eq!(got, w, "{:?}", input),
+                (Err(e), None) => assert!(e.to_string().contains("invalid age"), "{}", e),
+                (got, want) => panic!("{:?}: got {:?}, want {:?}", input, got, want),
+            }
+        }
+    }
+
+    #[test]
+    fn test_trash_retention() {
+        let mut cfg = Config::default();
+        assert_eq!(cfg.trash_retention().unwrap(), Some(Duration::days(30)));
+        cfg.trash_retention = Some("never".into());
+        assert_eq!(cfg.trash_retention().unwrap(), None);
+        cfg.trash_retention = Some("1w".into());
+        assert_eq!(cfg.trash_retention().unwrap(), Some(Duration::weeks(1)));
+        cfg.trash_retention = Some("soon".into());
+        assert!(cfg.trash_retention().is_err());
+    }
+
     #[test]
     fn test_data_dir_xdg_set() {
         let dir = data_dir_with(Some("/custom/data"), None).unwrap();
@@ -0,0 +1,108 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+
+/// Bytes under a directory, split by whether a file has other hard links.
+/// Clones made from a mirror hardlink its object files, so those bytes show
+/// up as shared in both the mirror and the workspace.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Usage {
+    /// Files with a single link.
+    pub unique: u64,
+    /// Files with more than one link, counted in full here.
+    pub shared: u64,
+}
+
+/// Identifies a hardlinked file across directories: (device, inode).
+pub type FileKey = (u64, u64);
+
+/// Measures `dir` without following symlinks. Each hardlinked file's key is
+/// added to `seen`; the returned count is how many of its bytes were new to
+/// `seen`, for a total that counts shared files once.
+pub fn measure(dir: &Path, seen: &mut HashSet<FileKey>) -> Result<(Usage, u64)> {
+    let mut usage = Usage::default();
+    let mut on_disk = 0;
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(d) = stack.pop() {
+        let entries =
+            fs::read_dir(&d).map_err(|e| anyhow::anyhow!("reading {}: {}", d.display(), e))?;
+        for entry in entries {
+            let entry = entry?;
+            // DirEntry::metadata does not follow symlinks.
+            let md = entry.metadata()?;
+            if md.is_dir() {
+                stack.push(entry.path());
+            } else if md.is_file() {
+                match shared_key(&md) {
+                    Some(key) => {
+                        usage.shared += md.len();
+                        if seen.insert(key) {
+                            on_disk += md.len();
+                        }
+                    }
+                    None => {
+                        usage.unique += md.len();
+                        on_disk += md.len();
+                    }
+                }
+            }
+        }
+    }
+    Ok((usage, on_disk))
+}
+
+#[cfg(unix)]
+fn shared_key(md: &fs::Metadata) -> Option<FileKey> {
+    use std::os::unix::fs::MetadataExt;
+    (md.nlink() > 1).then(|| (md.dev(), md.ino()))
+}
+
+// Link counts aren't available from std here; report everything as unique.
+#[cfg(not(unix))]
+fn shared_key(_md: &fs::Metadata) -> Option<FileKey> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure() {
+        let tmp = tempfile::tempdir().unwrap();
+        let a = tmp.path().join("a");
+        let b = tmp.path().join("b");
+        fs::create_dir_all(a.join("nested")).unwrap();
+        fs::create_dir_all(&b).unwrap();
+        fs::write(a.join("nested").join("own"), vec![0u8; 100]).unwrap();
+        fs::write(a.join("pack"), vec![0u8; 1000]).unwrap();
+        fs::hard_link(a.join("pack"), b.join("pack")).unwrap();
+
+        let mut seen = HashSet::new();
+        let (ua, da) = measure(&a, &mut seen).unwrap();
+        let (ub, db) = measure(&b, &mut seen).unwrap();
+        if cfg!(unix) {
+            assert_eq!(
+                ua,
+                Usage {
+                    unique: 100,
+                    shared: 1000
+                }
+            );
+            assert_eq!(
+                ub,
+                Usage {
+                    unique: 0,
+                    shared: 1000
+                }
+            );
+            assert_eq!(da + db, 1100, "shared bytes count once in the total");
+        } else {
+            assert_eq!(ua.unique + ub.unique, 2100);
+        }
+
+        assert!(measure(&tmp.path().join("missing"), &mut seen).is_err());
+    }
+}
@@ -56,18 +56,35 @@ pub fn clone_bare(url: &str, dest: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Fetch refspec of a bare mirror's `origin` remote.
+pub const MIRROR_REFSPEC: &str = "+refs/heads/*:refs/remotes/origin/*";
+
+/// Fetch refspec of a clone's `wsp-mirror` remote.
+pub const WSP_MIRROR_REFSPEC: &str = "+refs/remotes/origin/*:refs/remotes/wsp-mirror/*";
+
 pub fn configure_fetch_refspec(dir: &Path) -> Result<()> {
     run(
         Some(dir),
         &[
             "config",
+            "--replace-all",
             "remote.origin.fetch",
-            "+refs/heads/*:refs/remotes/origin/*",
+            MIRROR_REFSPEC,
         ],
     )?;
     Ok(())
 }
 
+/// Configured fetch refspecs of `remote`; empty if it has none.
+pub fn fetch_refspecs(dir: &Path, remote: &str) -> Vec<String> {
+    run(
+        Some(dir),
+        &["config", "--get-all", &format!("remote.{}.fetch", remote)],
+    )
+    .map(|s| s.lines().map(str::to_string).collect())
+    .unwrap_or_default()
+}
+
 fn ensure_fetch_refspec(dir: &Path) -> Result<()> {
     let has_refspec = run(Some(dir), &["config", "--get", "remote.origin.fetch"]).is_ok();
     if !has_refspec {
@@ -86,6 +103,13 @@ pub fn fetch(dir: &Path, prune: bool) -> Result<()> {
     Ok(())
 }
 
+/// Repacks and prunes a repo (`git gc`), then writes its commit-graph.
+pub fn gc(dir: &Path) -> Result<()> {
+    run(Some(dir), &["gc", "--quiet"])?;
+    run(Some(dir), &["commit-graph", "write", "--reachable"])?;
+    Ok(())
+}
+
 pub fn default_branch(dir: &Path) -> Result<String> {
     let r = run(Some(dir), &["symbolic-ref", "refs/remotes/origin/HEAD"]);
     let ref_str = match r {
@@ -109,8 +133,9 @@ pub fn configure_wsp_mirror_refspec(dir: &Path) -> Result<()> {
         Some(dir),
         &[
             "config",
+            "--replace-all",
             "remote.wsp-mirror.fetch",
-            "+refs/remotes/origin/*:refs/remotes/wsp-mirror/*",
+            WSP_MIRROR_REFSPEC,
         ],
     )?;
     Ok(())
@@ -143,6 +168,31 @@ pub fn fetch_remote_prune(dir: &Path, remote: &str) -> Result<()> {
     Ok(())
 }
 
+pub fn fetch_tags(dir: &Path, remote: &str) -> Result<()> {
+    run(Some(dir), &["fetch", "--tags", remote])?;
+    Ok(())
+}
+
+/// Lists tags newest first, comparing version numbers (`v1.10` > `v1.9`).
+pub fn tags_by_version(dir: &Path) -> Result<Vec<String>> {
+    let out = run(Some(dir), &["tag", "--list", "--sort=-v:refname"])?;
+    Ok(out.lines().map(|l| l.to_string()).collect())
+}
+
+pub fn tag_exists(dir: &Path, tag: &str) -> bool {
+    ref_exists(dir, &format!("refs/tags/{}", tag))
+}
+
+pub fn merge_ff_only(dir: &Path, target: &str) -> Result<()> {
+    run(Some(dir), &["merge", "--ff-only", target])?;
+    Ok(())
+}
+
+pub fn branch_rename(dir: &Path, old: &str, new: &str) -> Result<()> {
+    run(Some(dir), &["branch", "-m", old, new])?;
+    Ok(())
+}
+
 pub fn checkout_new_branch(dir: &Path, branch: &str, start_point: &str) -> Result<()> {
     run(
         Some(dir),
@@ -177,6 +227,19 @@ pub fn default_branch_for_remote(dir: &Path, remote: &str) -> Result<String> {
     Ok(parts[parts.len() - 1].to_string())
 }
 
+/// Points `remote` at `url`, adding the remote if it doesn't exist.
+pub fn remote_set_url(dir: &Path, remote: &str, url: &str) -> Result<()> {
+    if run(Some(dir), &["remote", "set-url", remote, url]).is_err() {
+        run(Some(dir), &["remote", "add", remote, url])?;
+    }
+    Ok(())
+}
+
+pub fn remote_remove(dir: &Path, remote: &str) -> Result<()> {
+    run(Some(dir), &["remote", "remove", remote])?;
+    Ok(())
+}
+
 pub fn remote_set_head(dir: &Path, remote: &str, branch: &str) -> Result<()> {
     run(Some(dir), &["remote", "set-head", remote, branch])?;
     Ok(())
@@ -202,6 +265,28 @@ pub fn branch_is_merged(dir: &Path, branch: &str, target: &str) -> Result<bool>
     }
 }
 
+/// Whether anything was ever committed on `branch`, judged from its reflog.
+/// Moves by merge, rebase or reset don't count. Without a reflog there is
+/// no telling, so it's assumed to have commits.
+pub fn branch_has_commits(dir: &Path, branch: &str) -> bool {
+    let Ok(out) = run(
+        Some(dir),
+        &[
+            "reflog",
+            "show",
+            "--format=%gs",
+            &format!("refs/heads/{}", branch),
+            "--",
+        ],
+    ) else {
+        return true;
+    };
+    out.is_empty()
+        || out
+            .lines()
+            .any(|l| l.starts_with("commit") || l.starts_with("cherry-pick"))
+}
+
 /// Detects if a branch was squash-merged into target using the commit-tree + cherry algorithm.
 pub fn branch_is_squash_merged(dir: &Path, branch: &str, target: &str) -> Result<bool> {
     let mb = merge_base(dir, branch, target)?;
@@ -286,6 +371,27 @@ pub fn ref_exists(dir: &Path, git_ref: &str) -> bool {
     run(Some(dir), &["rev-parse", "--verify", git_ref]).is_ok()
 }
 
+/// Tag pointing exactly at HEAD, if any.
+pub fn tag_at_head(dir: &Path) -> Option<String> {
+    run(Some(dir), &["describe", "--tags", "--exact-match", "HEAD"]).ok()
+}
+
+pub fn commit_exists(dir: &Path, sha: &str) -> bool {
+    run(
+        Some(dir),
+        &["cat-file", "-e", &format!("{}^{{commit}}", sha)],
+    )
+    .is_ok()
+}
+
+/// Configured URL of `remote`, without `url.<base>.insteadOf` rewrites.
+pub fn remote_url(dir: &Path, remote: &str) -> Result<String> {
+    run(
+        Some(dir),
+        &["config", "--get", &format!("remote.{}.url", remote)],
+    )
+}
+
 pub fn branch_current(dir: &Path) -> Result<String> {
     run(Some(dir), &["rev-parse", "--abbrev-ref", "HEAD"])
 }
@@ -294,19 +400,23 @@ pub fn branch_current(dir: &Path) -> Result<String> {
 pub enum UpstreamRef {
     /// @{upstream} tracking branch exists.
     Tracking,
-    /// No tracking branch; fell back to origin/<default>.
-    DefaultBranch(String),
+    /// No tracking branch; fell back to the base ref (e.g. origin/<default>).
+    Base(String),
     /// Nothing available — use HEAD.
     Head,
 }
 
 /// Probe once and return the best upstream reference.
-pub fn resolve_upstream_ref(dir: &Path) -> UpstreamRef {
+/// `base_ref` replaces origin/<default> as the fallback when set.
+pub fn resolve_upstream_ref(dir: &Path, base_ref: Option<&str>) -> UpstreamRef {
     if run(Some(dir), &["rev-parse", "--verify", "@{upstream}"]).is_ok() {
         return UpstreamRef::Tracking;
     }
+    if let Some(r) = base_ref {
+        return UpstreamRef::Base(r.to_string());
+    }
     if let Ok(branch) = default_branch(dir) {
-        return UpstreamRef::DefaultBranch(branch);
+        return UpstreamRef::Base(format!("origin/{}", branch));
     }
     UpstreamRef::Head
 }
@@ -315,14 +425,14 @@ pub fn merge_base(dir: &Path, a: &str, b: &str) -> Result<String> {
     run(Some(dir), &["merge-base", a, b])
 }
 
-pub fn ahead_count(dir: &Path) -> Result<u32> {
-    ahead_count_from(dir, &resolve_upstream_ref(dir))
+pub fn ahead_count(dir: &Path, base_ref: Option<&str>) -> Result<u32> {
+    ahead_count_from(dir, &resolve_upstream_ref(dir, base_ref))
 }
 
 pub fn ahead_count_from(dir: &Path, upstream: &UpstreamRef) -> Result<u32> {
     let range = match upstream {
         UpstreamRef::Tracking => "@{upstream}..HEAD".to_string(),
-        UpstreamRef::DefaultBranch(b) => format!("origin/{}..HEAD", b),
+        UpstreamRef::Base(r) => format!("{}..HEAD", r),
         UpstreamRef::Head => return Ok(0),
     };
     let out = run(Some(dir), &["rev-list", "--count", &range])?;
@@ -344,6 +454,16 @@ pub fn commit_count(dir: &Path, from: &str, to: &str) -> Result<u32> {
 }
 
 pub fn rebase_onto(dir: &Path, target: &str) -> Result<SyncAction> {
+    rebase_onto_inner(dir, target, false)
+}
+
+/// Like [`rebase_onto`], but a conflicting rebase is left in progress for the
+/// user to resolve instead of being aborted.
+pub fn rebase_onto_keep_conflicts(dir: &Path, target: &str) -> Result<SyncAction> {
+    rebase_onto_inner(dir, target, true)
+}
+
+fn rebase_onto_inner(dir: &Path, target: &str, keep_conflicts: bool) -> Result<SyncAction> {
     let head_sha = run(Some(dir), &["rev-parse", "HEAD"])?;
     let target_sha = run(Some(dir), &["rev-parse", target])?;
 
@@ -369,13 +489,25 @@ pub fn rebase_onto(dir: &Path, target: &str) -> Result<SyncAction> {
     match run(Some(dir), &["rebase", target]) {
         Ok(_) => Ok(SyncAction::Rebased { commits }),
         Err(e) => {
-            let _ = run(Some(dir), &["rebase", "--abort"]);
+            if !keep_conflicts {
+                let _ = run(Some(dir), &["rebase", "--abort"]);
+            }
             Err(e)
         }
     }
 }
 
 pub fn merge_from(dir: &Path, target: &str) -> Result<SyncAction> {
+    merge_from_inner(dir, target, false)
+}
+
+/// Like [`merge_from`], but a conflicting merge is left in progress for the
+/// user to resolve instead of being aborted.
+pub fn merge_from_keep_conflicts(dir: &Path, target: &str) -> Result<SyncAction> {
+    merge_from_inner(dir, target, true)
+}
+
+fn merge_from_inner(dir: &Path, target: &str, keep_conflicts: bool) -> Result<SyncAction> {
     let head_sha = run(Some(dir), &["rev-parse", "HEAD"])?;
     let target_sha = run(Some(dir), &["rev-parse", target])?;
 
@@ -399,12 +531,92 @@ pub fn merge_from(dir: &Path, target: &str) -> Result<SyncAction> {
     match run(Some(dir), &["merge", "--no-edit", target]) {
         Ok(_) => Ok(SyncAction::Merged),
         Err(e) => {
-            let _ = run(Some(dir), &["merge", "--abort"]);
+            if !keep_conflicts {
+                let _ = run(Some(dir), &["merge", "--abort"]);
+            }
             Err(e)
         }
     }
 }
 
+pub fn rebase_in_progress(dir: &Path) -> bool {
+    ["rebase-merge", "rebase-apply"].iter().any(|name| {
+        run(Some(dir), &["rev-parse", "--git-path", name])
+            .map(|p| dir.join(p).exists())
+            .unwrap_or(false)
+    })
+}
+
+pub fn merge_in_progress(dir: &Path) -> bool {
+    ref_exists(dir, "MERGE_HEAD")
+}
+
+/// Returns true if the index still has unresolved conflicts.
+pub fn has_unmerged_files(dir: &Path) -> bool {
+    run(Some(dir), &["diff", "--name-only", "--diff-filter=U"])
+        .map(|out| !out.is_empty())
+        .unwrap_or(false)
+}
+
+/// Finishes an in-progress rebase or merge once the user has resolved and
+/// staged (`git add`) the conflicting files.
+pub fn continue_in_progress(dir: &Path) -> Result<()> {
+    if has_unmerged_files(dir) {
+        bail!("unresolved conflicts remain (resolve them and `git add` the files)");
+    }
+    // Never open an editor for commit messages.
+    let env = [("GIT_EDITOR", "true")];
+    if rebase_in_progress(dir) {
+        run_with_env(Some(dir), &["rebase", "--continue"], &env)?;
+    } else if merge_in_progress(dir) {
+        run_with_env(Some(dir), &["commit", "--no-edit"], &env)?;
+    }
+    Ok(())
+}
+
+/// Aborts an in-progress rebase or merge, if any.
+pub fn abort_in_progress(dir: &Path) -> Result<()> {
+    if rebase_in_progress(dir) {
+        run(Some(dir), &["rebase", "--abort"])?;
+    } else if merge_in_progress(dir) {
+        run(Some(dir), &["merge", "--abort"])?;
+    }
+    Ok(())
+}
+
+/// Stashes local changes, including untracked files, and returns the stash
+/// commit's SHA so that exact entry can be popped later.
+pub fn stash_push(dir: &Path, message: &str) -> Result<String> {
+    run(
+        Some(dir),
+        &["stash", "push", "--include-untracked", "-m", message],
+    )?;
+    run(Some(dir), &["rev-parse", "stash@{0}"])
+}
+
+/// Re-applies the stash entry with commit `sha` and drops it, leaving any
+/// stashes made since alone. On conflict the entry is kept and conflict
+/// markers are left in the working tree.
+pub fn stash_pop(dir: &Path, sha: &str) -> Result<()> {
+    run(Some(dir), &["stash", "apply", sha])?;
+    let list = run(Some(dir), &["stash", "list", "--format=%H"])?;
+    if let Some(i) = list.lines().position(|l| l == sha) {
+        run(Some(dir), &["stash", "drop", &format!("stash@{{{}}}", i)])?;
+    }
+    Ok(())
+}
+
+pub fn head_sha(dir: &Path) -> Result<String> {
+    run(Some(dir), &["rev-parse", "HEAD"])
+}
+
+/// Moves the current branch (or detached HEAD) to `sha`, refusing to discard
+/// local changes.
+pub fn reset_keep(dir: &Path, sha: &str) -> Result<()> {
+    run(Some(dir), &["reset", "--keep", sha])?;
+    Ok(())
+}
+
 pub fn push(
     dir: &Path,
     remote: &str,
@@ -869,6 +1081,93 @@ mod tests {
         assert_eq!(result, SyncAction::Merged);
     }
 
+    #[test]
+    fn test_rebase_onto_keep_conflicts_then_continue() {
+        let (clone, source, _ct, _st) = setup_clone_repo();
+
+        local_commit(&clone, "conflict.txt", "local version");
+        advance_origin(&source, &clone, "main", "conflict.txt", "upstream version");
+
+        assert!(rebase_onto_keep_conflicts(&clone, "origin/main").is_err());
+        assert!(rebase_in_progress(&clone));
+        assert!(has_unmerged_files(&clone));
+
+        // Still conflicted: continue must fail
+        assert!(continue_in_progress(&clone).is_err());
+
+        std::fs::write(clone.join("conflict.txt"), "resolved").unwrap();
+        run(Some(&clone), &["add", "conflict.txt"]).unwrap();
+        continue_in_progress(&clone).unwrap();
+        assert!(!rebase_in_progress(&clone));
+        assert!(branch_is_merged(&clone, "origin/main", "HEAD").unwrap());
+    }
+
+    #[test]
+    fn test_merge_from_keep_conflicts_then_abort_and_reset() {
+        let (clone, source, _ct, _st) = setup_clone_repo();
+
+        let before = head_sha(&clone).unwrap();
+        local_commit(&clone, "conflict.txt", "local version");
+        let local = head_sha(&clone).unwrap();
+        advance_origin(&source, &clone, "main", "conflict.txt", "upstream version");
+
+        assert!(merge_from_keep_conflicts(&clone, "origin/main").is_err());
+        assert!(merge_in_progress(&clone));
+
+        abort_in_progress(&clone).unwrap();
+        assert!(!merge_in_progress(&clone));
+        assert_eq!(head_sha(&clone).unwrap(), local);
+
+        reset_keep(&clone, &before).unwrap();
+        assert_eq!(head_sha(&clone).unwrap(), before);
+    }
+
+    #[test]
+    fn test_stash_push_and_pop() {
+        let (clone, source, _ct, _st) = setup_clone_repo();
+        local_commit(&clone, "tracked.txt", "v1");
+        std::fs::write(clone.join("tracked.txt"), "v2").unwrap();
+        std::fs::write(clone.join("untracked.txt"), "new").unwrap();
+
+        let sha = stash_push(&clone, "test").unwrap();
+        assert_eq!(changed_file_count(&clone).unwrap(), 0);
+
+        advance_origin(&source, &clone, "main", "upstream.txt", "upstream");
+        rebase_onto(&clone, "origin/main").unwrap();
+
+        stash_pop(&clone, &sha).unwrap();
+        assert_eq!(changed_file_count(&clone).unwrap(), 2);
+        assert!(run(Some(&clone), &["stash", "list"]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stash_pop_conflict_keeps_stash() {
+        let (clone, _source, _ct, _st) = setup_clone_repo();
+        local_commit(&clone, "file.txt", "v1");
+        std::fs::write(clone.join("file.txt"), "stashed").unwrap();
+        let sha = stash_push(&clone, "test").unwrap();
+
+        local_commit(&clone, "file.txt", "committed");
+        assert!(stash_pop(&clone, &sha).is_err());
+        assert!(!run(Some(&clone), &["stash", "list"]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stash_pop_leaves_newer_stashes() {
+        let (clone, _source, _ct, _st) = setup_clone_repo();
+        std::fs::write(clone.join("ours.txt"), "ours").unwrap();
+        let sha = stash_push(&clone, "ours").unwrap();
+        std::fs::write(clone.join("theirs.txt"), "theirs").unwrap();
+        stash_push(&clone, "theirs").unwrap();
+
+        stash_pop(&clone, &sha).unwrap();
+        assert!(clone.join("ours.txt").exists());
+        assert!(!clone.join("theirs.txt").exists());
+        let list = run(Some(&clone), &["stash", "list"]).unwrap();
+        assert_eq!(list.lines().count(), 1);
+        assert!(list.contains("theirs"), "{}", list);
+    }
+
     #[test]
     fn test_merge_from_conflict_aborts() {
         let (clone, source, _ct, _st) = setup_clone_repo();
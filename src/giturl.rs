@@ -190,6 +190,15 @@ pub fn parse_repo_ref(arg: &str) -> (&str, &str) {
     }
 }
 
+/// Splits a "repo^base" argument into the repo name and base branch.
+/// `^` can't appear in a git ref, so the split is unambiguous.
+pub fn parse_repo_base(arg: &str) -> (&str, &str) {
+    match arg.rfind('^') {
+        Some(i) if i < arg.len() - 1 => (&arg[..i], &arg[i + 1..]),
+        _ => (arg, ""),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -474,4 +483,25 @@ mod tests {
             assert_eq!(got_ref, want_ref, "{}", name);
         }
     }
+
+    #[test]
+    fn test_parse_repo_base() {
+        let cases = vec![
+            ("no base", "api-gateway", "api-gateway", ""),
+            ("branch base", "api^release/2.3", "api", "release/2.3"),
+            (
+                "full identity with base",
+                "github.com/acme/api^develop",
+                "github.com/acme/api",
+                "develop",
+            ),
+            ("trailing ^", "repo^", "repo^", ""),
+            ("ref is left alone", "proto@v1.0", "proto@v1.0", ""),
+        ];
+        for (name, input, want_name, want_base) in cases {
+            let (got_name, got_base) = parse_repo_base(input);
+            assert_eq!(got_name, want_name, "{}", name);
+            assert_eq!(got_base, want_base, "{}", name);
+        }
+    }
 }
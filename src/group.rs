@@ -81,6 +81,10 @@ mod tests {
             language_integrations: None,
             workspaces_dir: None,
             sync_strategy: None,
+            sync_autostash: None,
+            trash_retention: None,
+            node_package_manager: None,
+            python_venv: None,
         }
     }
 
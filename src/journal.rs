@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::git;
+use crate::workspace;
+
+/// One mutating command, appended as a line of JSON to the journal.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Entry {
+    pub time: DateTime<Utc>,
+    /// Subcommand path, e.g. `sync` or `setup repo add`.
+    pub command: String,
+    /// Arguments after `wsp`, as typed.
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repos: Vec<RepoHeads>,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A clone's HEAD before and after a command. `None` means the clone did not
+/// exist (or had no commits) at that point.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RepoHeads {
+    pub identity: String,
+    /// Clone directory relative to the workspace.
+    pub dir: String,
+    /// Branch checked out after the command (before it, if the clone is
+    /// gone). `None` for a detached HEAD or an entry from an older wsp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+}
+
+/// A clone's directory, HEAD and checked-out branch at one point in time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Head {
+    pub dir: String,
+    pub sha: Option<String>,
+    pub branch: Option<String>,
+}
+
+/// HEAD of every clone in a workspace, keyed by identity.
+pub type Heads = BTreeMap<String, Head>;
+
+/// Reads the HEAD of each repo listed in the workspace's `.wsp.yaml`. A
+/// missing or unreadable workspace has no heads.
+pub fn heads(ws_dir: &Path) -> Heads {
+    let mut heads = Heads::new();
+    let Ok(meta) = workspace::load_metadata(ws_dir) else {
+        return heads;
+    };
+    for identity in meta.repos.keys() {
+        let Ok(dn) = meta.dir_name(identity) else {
+            continue;
+        };
+        let dir = ws_dir.join(&dn);
+        let sha = git::head_sha(&dir).ok();
+        let branch = git::branch_current(&dir).ok().filter(|b| b != "HEAD");
+        heads.insert(
+            identity.clone(),
+            Head {
+                dir: dn,
+                sha,
+                branch,
+            },
+        );
+    }
+    heads
+}
+
+/// Pairs up heads taken before and after a command, keeping every repo that
+/// appears in either.
+pub fn diff_heads(before: &Heads, after: &Heads) -> Vec<RepoHeads> {
+    let mut repos: BTreeMap<&String, RepoHeads> = BTreeMap::new();
+    for (identity, head) in before {
+        repos.insert(
+            identity,
+            RepoHeads {
+                identity: identity.clone(),
+                dir: head.dir.clone(),
+                branch: head.branch.clone(),
+                before: head.sha.clone(),
+                after: None,
+            },
+        );
+    }
+    for (identity, head) in after {
+        let entry = repos.entry(identity).or_insert_with(|| RepoHeads {
+            identity: identity.clone(),
+            dir: head.dir.clone(),
+            branch: None,
+            before: None,
+            after: None,
+        });
+        entry.dir = head.dir.clone();
+        entry.branch = head.branch.clone();
+        entry.after = head.sha.clone();
+    }
+    repos.into_values().collect()
+}
+
+/// Appends an entry as a single write, so concurrent `wsp` processes don't
+/// interleave lines.
+pub fn append(path: &Path, entry: &Entry) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("opening {}: {}", path.display(), e))?;
+    file.write_all(line.as_bytes())
+        .map_err(|e| anyhow::anyhow!("writing {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+/// Reads the journal, oldest first. Each entry is paired with its ID, its
+/// 1-based line number. Lines that don't parse are skipped with a warning.
+pub fn read(path: &Path) -> Result<Vec<(usize, Entry)>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("reading {}: {}", path.display(), e))?;
+    let mut entries = Vec::new();
+    for (i, line) in data.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push((i + 1, entry)),
+            Err(e) => eprintln!("warning: skipping journal line {}: {}", i + 1, e),
+        }
+    }
+    Ok(entries)
+}
+
+/// Looks up an entry by ID.
+pub fn find(path: &Path, id: usize) -> Result<Entry> {
+    match read(path)?.into_iter().find(|(n, _)| *n == id) {
+        Some((_, entry)) => Ok(entry),
+        None => bail!("no history entry {}", id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(command: &str, workspace: Option<&str>) -> Entry {
+        Entry {
+            time: Utc::now(),
+            command: command.into(),
+            args: vec![command.into()],
+            workspace: workspace.map(String::from),
+            repos: Vec::new(),
+            ok: true,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_append_read_find() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("data").join("journal.jsonl");
+        assert!(read(&path).unwrap().is_empty());
+
+        let mut sync = entry("sync", Some("ws"));
+        sync.repos.push(RepoHeads {
+            identity: "example.com/acme/api".into(),
+            dir: "api".into(),
+            branch: Some("feature".into()),
+            before: Some("aaa".into()),
+            after: Some("bbb".into()),
+        });
+        append(&path, &entry("setup repo add", None)).unwrap();
+        append(&path, &sync).unwrap();
+
+        // A torn line doesn't hide the entries after it.
+        let mut f = OpenOptions::new().append(true).open(&path).unwrap();
+        f.write_all(b"{\"time\":\n").unwrap();
+        append(&path, &entry("push", Some("ws"))).unwrap();
+
+        let entries = read(&path).unwrap();
+        let ids: Vec<usize> = entries.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![1, 2, 4]);
+        assert_eq!(find(&path, 2).unwrap(), sync);
+        assert!(find(&path, 3).is_err());
+    }
+
+    #[test]
+    fn test_diff_heads() {
+        let head = |dir: &str, sha: &str, branch: &str| Head {
+            dir: dir.into(),
+            sha: Some(sha.into()),
+            branch: Some(branch.into()),
+        };
+        let before: Heads = [
+            ("a".to_string(), head("a", "1", "main")),
+            ("gone".to_string(), head("gone", "2", "old")),
+        ]
+        .into();
+        let after: Heads = [
+            ("a".to_string(), head("a", "3", "feature")),
+            ("new".to_string(), head("new", "4", "feature")),
+        ]
+        .into();
+        let repos = diff_heads(&before, &after);
+        let got: Vec<(String, Option<String>, Option<String>)> = repos
+            .iter()
+            .map(|r| (r.identity.clone(), r.before.clone(), r.after.clone()))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                ("a".into(), Some("1".into()), Some("3".into())),
+                ("gone".into(), Some("2".into()), None),
+                ("new".into(), None, Some("4".into())),
+            ]
+        );
+        // The branch comes from after the command, unless the clone is gone.
+        let branches: Vec<Option<&str>> = repos.iter().map(|r| r.branch.as_deref()).collect();
+        assert_eq!(
+            branches,
+            vec![Some("feature"), Some("old"), Some("feature")]
+        );
+    }
+}
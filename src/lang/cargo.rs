@@ -0,0 +1,363 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::giturl;
+use crate::workspace::Metadata;
+
+use super::{
+    GENERATED, LanguageIntegration, check_generated, find_manifests, repo_dirs, write_generated,
+    ws_relative,
+};
+
+/// Dependency tables that can hold git dependencies.
+const DEP_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+pub struct CargoIntegration;
+
+impl LanguageIntegration for CargoIntegration {
+    fn name(&self) -> &str {
+        "cargo"
+    }
+
+    fn detect(&self, ws_dir: &Path, metadata: &Metadata) -> bool {
+        repo_dirs(ws_dir, metadata)
+            .iter()
+            .any(|(_, _, path)| path.join("Cargo.toml").exists())
+    }
+
+    /// Writes `.cargo/config.toml` at the workspace root with a `[patch]`
+    /// entry for every git dependency on a crate in another repo of the
+    /// workspace, so cargo builds against the local clone. Cargo reads the
+    /// file from any directory below the workspace root.
+    fn apply(&self, ws_dir: &Path, metadata: &Metadata) -> Result<()> {
+        let mut crates: BTreeMap<(String, String), String> = BTreeMap::new();
+        let mut deps: Vec<GitDep> = Vec::new();
+        for (identity, dn, path) in repo_dirs(ws_dir, metadata) {
+            if !path.join("Cargo.toml").exists() {
+                continue;
+            }
+            for manifest in find_manifests(&path, "Cargo.toml")? {
+                let content = fs::read_to_string(&manifest)
+                    .with_context(|| format!("reading {}", manifest.display()))?;
+                let table: toml::Table = content
+                    .parse()
+                    .with_context(|| format!("parsing {}", manifest.display()))?;
+                if let Some(name) = package_name(&table) {
+                    let dir = manifest.parent().unwrap_or(&path);
+                    crates.insert((identity.clone(), name), ws_relative(&dn, &path, dir));
+                }
+                deps.extend(git_deps(&table));
+            }
+        }
+
+        let patches = patches(&crates, &deps);
+        write_config(ws_dir, &patches)
+    }
+}
+
+fn package_name(manifest: &toml::Table) -> Option<String> {
+    manifest
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(String::from)
+}
+
+/// A dependency on a crate from a git repo.
+#[derive(Debug, PartialEq)]
+struct GitDep {
+    /// The package name, which differs from the dependency key when renamed.
+    package: String,
+    url: String,
+}
+
+/// Collects git dependencies from a manifest's dependency tables, including
+/// `[workspace.dependencies]` and `[target.'cfg(..)'.dependencies]`.
+fn git_deps(manifest: &toml::Table) -> Vec<GitDep> {
+    fn dep_tables(t: &toml::Table) -> impl Iterator<Item = &toml::Table> {
+        DEP_TABLES
+            .iter()
+            .filter_map(|key| t.get(*key).and_then(|v| v.as_table()))
+    }
+
+    let mut tables: Vec<&toml::Table> = dep_tables(manifest).collect();
+    if let Some(targets) = manifest.get("target").and_then(|v| v.as_table()) {
+        for target in targets.values().filter_map(|v| v.as_table()) {
+            tables.extend(dep_tables(target));
+        }
+    }
+    if let Some(deps) = manifest
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .and_then(|v| v.as_table())
+    {
+        tables.push(deps);
+    }
+
+    let mut deps = Vec::new();
+    for table in tables {
+        for (key, spec) in table {
+            let Some(url) = spec.get("git").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let package = spec.get("package").and_then(|v| v.as_str()).unwrap_or(key);
+            deps.push(GitDep {
+                package: package.to_string(),
+                url: url.to_string(),
+            });
+        }
+    }
+    deps
+}
+
+/// Maps each git source URL (as cargo canonicalizes it) to the URL as written
+/// and the local path of every crate from it that a workspace repo provides.
+type Patches = BTreeMap<String, (String, BTreeMap<String, String>)>;
+
+fn patches(crates: &BTreeMap<(String, String), String>, deps: &[GitDep]) -> Patches {
+    let mut patches = Patches::new();
+    for dep in deps {
+        let Ok(parsed) = giturl::parse(&dep.url) else {
+            continue;
+        };
+        let Some(path) = crates.get(&(parsed.identity(), dep.package.clone())) else {
+            continue;
+        };
+        patches
+            .entry(canonical_url(&dep.url))
+            .or_insert_with(|| (dep.url.clone(), BTreeMap::new()))
+            .1
+            .insert(dep.package.clone(), path.clone());
+    }
+    patches
+}
+
+/// Approximates cargo's URL canonicalization, under which `foo`, `foo.git`
+/// and `foo/` are the same source and patching both is an error.
+fn canonical_url(url: &str) -> String {
+    let mut url = url.trim_end_matches('/').to_string();
+    if url.contains("github.com") {
+        url = url.to_lowercase();
+    }
+    url.strip_suffix(".git").map(String::from).unwrap_or(url)
+}
+
+/// Writes (or, with nothing to patch, removes) the generated config. A
+/// config the user wrote is left alone.
+fn write_config(ws_dir: &Path, patches: &Patches) -> Result<()> {
+    let cargo_dir = ws_dir.join(".cargo");
+    let path = cargo_dir.join("config.toml");
+    let exists = check_generated(&path)?;
+    if patches.is_empty() {
+        if exists {
+            fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))?;
+            let _ = fs::remove_dir(&cargo_dir);
+        }
+        return Ok(());
+    }
+
+    let mut out = format!("# {}\n", GENERATED);
+    out.push_str("# Points git dependencies on crates in this workspace at the local clones.\n");
+    for (url, crates) in patches.values() {
+        out.push_str(&format!("\n[patch.{}]\n", quote(url)));
+        for (name, crate_path) in crates {
+            out.push_str(&format!(
+                "{} = {{ path = {} }}\n",
+                key(name),
+                quote(crate_path)
+            ));
+        }
+    }
+
+    write_generated(ws_dir, &path, &out)
+}
+
+fn quote(s: &str) -> String {
+    toml::Value::String(s.to_string()).to_string()
+}
+
+fn key(s: &str) -> String {
+    if !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        s.to_string()
+    } else {
+        quote(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::tests::{make_metadata, write};
+
+    #[test]
+    fn test_detect() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+        write(&ws_dir.join("frontend").join("package.json"), "{}");
+        let meta = make_metadata(&["github.com/acme/frontend", "github.com/acme/core"]);
+        assert!(!CargoIntegration.detect(ws_dir, &meta));
+
+        write(
+            &ws_dir.join("core").join("Cargo.toml"),
+            "[package]\nname = \"core\"\n",
+        );
+        assert!(CargoIntegration.detect(ws_dir, &meta));
+    }
+
+    #[test]
+    fn test_git_deps() {
+        let manifest: toml::Table = r#"
+            [dependencies]
+            serde = "1"
+            core = { git = "https://github.com/acme/core" }
+            proto-types = { git = "git@github.com:acme/proto.git", package = "proto" }
+
+            [target.'cfg(unix)'.dev-dependencies]
+            testkit = { git = "https://github.com/acme/core", branch = "main" }
+
+            [workspace.dependencies]
+            shared = { git = "ssh://git@github.com/acme/shared.git", tag = "v1" }
+            local = { path = "../local" }
+        "#
+        .parse()
+        .unwrap();
+        let mut got: Vec<(String, String)> = git_deps(&manifest)
+            .into_iter()
+            .map(|d| (d.package, d.url))
+            .collect();
+        got.sort();
+        let want = vec![
+            ("core", "https://github.com/acme/core"),
+            ("proto", "git@github.com:acme/proto.git"),
+            ("shared", "ssh://git@github.com/acme/shared.git"),
+            ("testkit", "https://github.com/acme/core"),
+        ];
+        assert_eq!(
+            got,
+            want.into_iter()
+                .map(|(p, u)| (p.to_string(), u.to_string()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_canonical_url() {
+        let cases = vec![
+            (
+                "https://github.com/acme/core",
+                "https://github.com/acme/core",
+            ),
+            (
+                "https://github.com/acme/core.git",
+                "https://github.com/acme/core",
+            ),
+            (
+                "https://github.com/Acme/Core/",
+                "https://github.com/acme/core",
+            ),
+            (
+                "https://gitlab.com/Acme/Core.git",
+                "https://gitlab.com/Acme/Core",
+            ),
+        ];
+        for (input, want) in cases {
+            assert_eq!(canonical_url(input), want, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+
+        // core is a cargo workspace with two member crates.
+        write(
+            &ws_dir.join("core").join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\n",
+        );
+        write(
+            &ws_dir
+                .join("core")
+                .join("crates")
+                .join("core")
+                .join("Cargo.toml"),
+            "[package]\nname = \"core\"\n",
+        );
+        write(
+            &ws_dir
+                .join("core")
+                .join("crates")
+                .join("testkit")
+                .join("Cargo.toml"),
+            "[package]\nname = \"testkit\"\n",
+        );
+        // Build output is never scanned.
+        write(
+            &ws_dir
+                .join("core")
+                .join("target")
+                .join("package")
+                .join("Cargo.toml"),
+            "[package]\nname = \"core\"\n",
+        );
+        write(
+            &ws_dir.join("api").join("Cargo.toml"),
+            r#"[package]
+name = "api"
+
+[dependencies]
+core = { git = "https://github.com/acme/core.git" }
+serde = "1"
+elsewhere = { git = "https://github.com/other/elsewhere" }
+
+[dev-dependencies]
+testkit = { git = "https://github.com/acme/core", branch = "main" }
+"#,
+        );
+
+        let meta = make_metadata(&["github.com/acme/api", "github.com/acme/core"]);
+        CargoIntegration.apply(ws_dir, &meta).unwrap();
+
+        let config = ws_dir.join(".cargo").join("config.toml");
+        let content = fs::read_to_string(&config).unwrap();
+        assert_eq!(
+            content,
+            "# Code generated by wsp. DO NOT EDIT.\n\
+             # Points git dependencies on crates in this workspace at the local clones.\n\
+             \n\
+             [patch.\"https://github.com/acme/core.git\"]\n\
+             core = { path = \"core/crates/core\" }\n\
+             testkit = { path = \"core/crates/testkit\" }\n"
+        );
+        let parsed: toml::Table = content.parse().unwrap();
+        assert!(parsed["patch"]["https://github.com/acme/core.git"]["core"].is_table());
+
+        // Dropping core from the workspace removes the generated config.
+        let meta = make_metadata(&["github.com/acme/api"]);
+        CargoIntegration.apply(ws_dir, &meta).unwrap();
+        assert!(!config.exists());
+    }
+
+    #[test]
+    fn test_apply_keeps_user_config() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+        write(
+            &ws_dir.join("api").join("Cargo.toml"),
+            "[package]\nname = \"api\"\n",
+        );
+        let config = ws_dir.join(".cargo").join("config.toml");
+        write(&config, "[build]\njobs = 4\n");
+
+        let meta = make_metadata(&["github.com/acme/api"]);
+        let err = CargoIntegration.apply(ws_dir, &meta).unwrap_err();
+        assert!(err.to_string().contains("not generated by wsp"), "{}", err);
+        assert_eq!(fs::read_to_string(&config).unwrap(), "[build]\njobs = 4\n");
+    }
+}
@@ -362,7 +362,10 @@ mod tests {
             branch: "test".into(),
             repos: map,
             created: Utc::now(),
+            base: None,
+            parent: None,
             dirs: BTreeMap::new(),
+            tasks: BTreeMap::new(),
         }
     }
 
@@ -378,6 +381,7 @@ mod tests {
                         id.to_string(),
                         Some(WorkspaceRepoRef {
                             r#ref: ref_str.to_string(),
+                            base: String::new(),
                         }),
                     );
                 }
@@ -388,7 +392,10 @@ mod tests {
             branch: "test".into(),
             repos: map,
             created: Utc::now(),
+            base: None,
+            parent: None,
             dirs: BTreeMap::new(),
+            tasks: BTreeMap::new(),
         }
     }
 
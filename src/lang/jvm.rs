@@ -0,0 +1,237 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::workspace::Metadata;
+
+use super::{GENERATED, LanguageIntegration, check_generated, repo_dirs, write_generated};
+
+const GRADLE_SETTINGS: &str = "settings.gradle.kts";
+const POM: &str = "pom.xml";
+
+/// Files that make a directory a Gradle build.
+const GRADLE_FILES: &[&str] = &[
+    "settings.gradle.kts",
+    "settings.gradle",
+    "build.gradle.kts",
+    "build.gradle",
+];
+
+pub struct JvmIntegration;
+
+impl LanguageIntegration for JvmIntegration {
+    fn name(&self) -> &str {
+        "jvm"
+    }
+
+    fn detect(&self, ws_dir: &Path, metadata: &Metadata) -> bool {
+        repo_dirs(ws_dir, metadata)
+            .iter()
+            .any(|(_, _, path)| is_gradle(path) || is_maven(path))
+    }
+
+    /// Writes a Gradle composite build (`settings.gradle.kts` including each
+    /// Gradle clone) and a Maven aggregator `pom.xml` (with each Maven clone
+    /// as a module) at the workspace root. Building from the root then
+    /// substitutes dependencies on artifacts from other clones with the local
+    /// sources.
+    fn apply(&self, ws_dir: &Path, metadata: &Metadata) -> Result<()> {
+        let mut gradle = Vec::new();
+        let mut maven = Vec::new();
+        for (_, dn, path) in repo_dirs(ws_dir, metadata) {
+            if is_gradle(&path) {
+                gradle.push(dn.clone());
+            }
+            if is_maven(&path) {
+                maven.push(dn);
+            }
+        }
+        gradle.sort();
+        maven.sort();
+
+        // A user-written file for one build tool doesn't hold up the other.
+        let gradle_result = update(
+            ws_dir,
+            GRADLE_SETTINGS,
+            (!gradle.is_empty()).then(|| gradle_settings(&metadata.name, &gradle)),
+        );
+        let maven_result = update(
+            ws_dir,
+            POM,
+            (!maven.is_empty()).then(|| aggregator_pom(&metadata.name, &maven)),
+        );
+        gradle_result.and(maven_result)
+    }
+}
+
+fn is_gradle(dir: &Path) -> bool {
+    GRADLE_FILES.iter().any(|f| dir.join(f).exists())
+}
+
+fn is_maven(dir: &Path) -> bool {
+    dir.join(POM).exists()
+}
+
+/// Writes a generated file, or removes it when there is nothing to generate.
+fn update(ws_dir: &Path, file_name: &str, content: Option<String>) -> Result<()> {
+    let path = ws_dir.join(file_name);
+    let exists = check_generated(&path)?;
+    match content {
+        Some(content) => write_generated(ws_dir, &path, &content),
+        None if exists => {
+            fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))
+        }
+        None => Ok(()),
+    }
+}
+
+fn gradle_settings(ws_name: &str, dirs: &[String]) -> String {
+    let mut out = format!("// {}\n", GENERATED);
+    out.push_str(&format!(
+        "rootProject.name = {}\n\n",
+        kotlin_string(ws_name)
+    ));
+    for dn in dirs {
+        out.push_str(&format!("includeBuild({})\n", kotlin_string(dn)));
+    }
+    out
+}
+
+fn aggregator_pom(ws_name: &str, dirs: &[String]) -> String {
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!-- {} -->\n",
+        GENERATED
+    );
+    out.push_str(
+        "<project xmlns=\"http://maven.apache.org/POM/4.0.0\"\n\
+         \x20        xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\"\n\
+         \x20        xsi:schemaLocation=\"http://maven.apache.org/POM/4.0.0 https://maven.apache.org/xsd/maven-4.0.0.xsd\">\n",
+    );
+    out.push_str("  <modelVersion>4.0.0</modelVersion>\n");
+    out.push_str("  <groupId>wsp.workspace</groupId>\n");
+    out.push_str(&format!(
+        "  <artifactId>{}</artifactId>\n",
+        xml_escape(ws_name)
+    ));
+    out.push_str("  <version>0-SNAPSHOT</version>\n");
+    out.push_str("  <packaging>pom</packaging>\n");
+    out.push_str("  <modules>\n");
+    for dn in dirs {
+        out.push_str(&format!("    <module>{}</module>\n", xml_escape(dn)));
+    }
+    out.push_str("  </modules>\n");
+    out.push_str("</project>\n");
+    out
+}
+
+fn kotlin_string(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('$', "\\$");
+    format!("\"{}\"", escaped)
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::tests::{make_metadata, write};
+
+    #[test]
+    fn test_detect() {
+        let cases = vec![
+            ("settings.gradle.kts", true),
+            ("settings.gradle", true),
+            ("build.gradle", true),
+            ("pom.xml", true),
+            ("package.json", false),
+        ];
+        for (file, want) in cases {
+            let tmp = tempfile::tempdir().unwrap();
+            write(&tmp.path().join("svc").join(file), "");
+            let meta = make_metadata(&["github.com/acme/svc"]);
+            assert_eq!(JvmIntegration.detect(tmp.path(), &meta), want, "{}", file);
+        }
+    }
+
+    #[test]
+    fn test_kotlin_string() {
+        let cases = vec![
+            ("api", "\"api\""),
+            ("a\"b", "\"a\\\"b\""),
+            ("$x", "\"\\$x\""),
+        ];
+        for (input, want) in cases {
+            assert_eq!(kotlin_string(input), want, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+        write(&ws_dir.join("orders").join("settings.gradle.kts"), "");
+        write(&ws_dir.join("billing").join("build.gradle"), "");
+        write(&ws_dir.join("common").join("pom.xml"), "<project/>");
+        write(&ws_dir.join("web").join("package.json"), "{}");
+        let meta = make_metadata(&[
+            "github.com/acme/orders",
+            "github.com/acme/billing",
+            "github.com/acme/common",
+            "github.com/acme/web",
+        ]);
+
+        JvmIntegration.apply(ws_dir, &meta).unwrap();
+        assert_eq!(
+            fs::read_to_string(ws_dir.join(GRADLE_SETTINGS)).unwrap(),
+            "// Code generated by wsp. DO NOT EDIT.\n\
+             rootProject.name = \"test\"\n\n\
+             includeBuild(\"billing\")\n\
+             includeBuild(\"orders\")\n"
+        );
+        let pom = fs::read_to_string(ws_dir.join(POM)).unwrap();
+        assert!(
+            pom.starts_with(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <!-- Code generated by wsp. DO NOT EDIT. -->\n"
+            ),
+            "{}",
+            pom
+        );
+        assert!(pom.contains("<packaging>pom</packaging>"), "{}", pom);
+        assert!(
+            pom.contains("  <modules>\n    <module>common</module>\n  </modules>\n"),
+            "{}",
+            pom
+        );
+
+        // Without Maven repos the aggregator goes away.
+        let meta = make_metadata(&["github.com/acme/orders", "github.com/acme/billing"]);
+        JvmIntegration.apply(ws_dir, &meta).unwrap();
+        assert!(ws_dir.join(GRADLE_SETTINGS).exists());
+        assert!(!ws_dir.join(POM).exists());
+    }
+
+    #[test]
+    fn test_apply_keeps_user_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+        write(&ws_dir.join("common").join("pom.xml"), "<project/>");
+        write(&ws_dir.join(POM), "<project>mine</project>\n");
+        let meta = make_metadata(&["github.com/acme/common"]);
+
+        let err = JvmIntegration.apply(ws_dir, &meta).unwrap_err();
+        assert!(err.to_string().contains("not generated by wsp"), "{}", err);
+        assert_eq!(
+            fs::read_to_string(ws_dir.join(POM)).unwrap(),
+            "<project>mine</project>\n"
+        );
+    }
+}
@@ -1,8 +1,15 @@
+mod cargo;
 mod go;
+mod jvm;
+mod node;
+mod python;
+mod vscode;
 
-use std::path::Path;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 
 use crate::config::Config;
 use crate::workspace::Metadata;
@@ -11,34 +18,61 @@ pub trait LanguageIntegration {
     fn name(&self) -> &str;
     fn detect(&self, ws_dir: &Path, metadata: &Metadata) -> bool;
     fn apply(&self, ws_dir: &Path, metadata: &Metadata) -> Result<()>;
+
+    /// Whether it runs when `language-integrations.<name>` is not set.
+    fn enabled_by_default(&self) -> bool {
+        true
+    }
 }
 
-fn all_integrations() -> Vec<Box<dyn LanguageIntegration>> {
-    vec![Box::new(go::GoIntegration)]
+fn all_integrations(config: &Config) -> Vec<Box<dyn LanguageIntegration>> {
+    vec![
+        Box::new(go::GoIntegration),
+        Box::new(cargo::CargoIntegration),
+        Box::new(node::NodeIntegration::new(config)),
+        Box::new(python::PythonIntegration::new(config)),
+        Box::new(jvm::JvmIntegration),
+        Box::new(vscode::VsCodeIntegration),
+    ]
 }
 
 /// Returns the names of all known language integrations.
 pub fn integration_names() -> Vec<String> {
-    all_integrations()
+    all_integrations(&Config::default())
         .iter()
         .map(|i| i.name().to_string())
         .collect()
 }
 
+/// Whether the named integration runs when its key is not set.
+pub fn enabled_by_default(name: &str) -> bool {
+    all_integrations(&Config::default())
+        .iter()
+        .find(|i| i.name() == name)
+        .is_none_or(|i| i.enabled_by_default())
+}
+
+/// Whether the named integration runs: its `language-integrations` setting,
+/// or its default when absent.
+pub fn is_enabled(config: &Config, name: &str) -> bool {
+    setting(config, name).unwrap_or_else(|| enabled_by_default(name))
+}
+
+fn setting(config: &Config, name: &str) -> Option<bool> {
+    config
+        .language_integrations
+        .as_ref()
+        .and_then(|m| m.get(name))
+        .copied()
+}
+
 /// Runs all enabled language integrations for the given workspace.
 /// Failures produce warnings via eprintln, never abort the workspace operation.
 pub fn run_integrations(ws_dir: &Path, metadata: &Metadata, config: &Config) {
-    for integration in all_integrations() {
+    for integration in all_integrations(config) {
         let name = integration.name();
 
-        // Check config: absent key = enabled, explicit false = disabled
-        let enabled = config
-            .language_integrations
-            .as_ref()
-            .and_then(|m| m.get(name))
-            .copied()
-            .unwrap_or(true);
-
+        let enabled = setting(config, name).unwrap_or_else(|| integration.enabled_by_default());
         if !enabled {
             continue;
         }
@@ -53,6 +87,111 @@ pub fn run_integrations(ws_dir: &Path, metadata: &Metadata, config: &Config) {
     }
 }
 
+/// How deep to look for manifests of nested packages below a repo root.
+const MAX_DEPTH: usize = 4;
+
+/// Returns (identity, dir_name, repo_path) for all repos in the workspace.
+fn repo_dirs(ws_dir: &Path, metadata: &Metadata) -> Vec<(String, String, PathBuf)> {
+    let mut result = Vec::new();
+    for identity in metadata.repos.keys() {
+        if let Ok(dn) = metadata.dir_name(identity) {
+            if dn.contains("..") || dn.starts_with('/') {
+                continue;
+            }
+            let path = ws_dir.join(&dn);
+            result.push((identity.clone(), dn, path));
+        }
+    }
+    result
+}
+
+/// Finds every `file_name` in a repo, skipping build output, dependencies
+/// and hidden dirs.
+fn find_manifests(repo: &Path, file_name: &str) -> Result<Vec<PathBuf>> {
+    let mut manifests = Vec::new();
+    let mut stack = vec![(repo.to_path_buf(), 0)];
+    while let Some((dir, depth)) = stack.pop() {
+        let manifest = dir.join(file_name);
+        if manifest.is_file() {
+            manifests.push(manifest);
+        }
+        if depth == MAX_DEPTH {
+            continue;
+        }
+        let entries = fs::read_dir(&dir).with_context(|| format!("reading {}", dir.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || name == "target" || name == "node_modules" {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                stack.push((entry.path(), depth + 1));
+            }
+        }
+    }
+    manifests.sort();
+    Ok(manifests)
+}
+
+/// Path of `dir` (inside the clone `repo`, checked out at `dn`) relative to
+/// the workspace root, with forward slashes.
+fn ws_relative(dn: &str, repo: &Path, dir: &Path) -> String {
+    let mut path = dn.to_string();
+    if let Ok(rel) = dir.strip_prefix(repo) {
+        for part in rel.components() {
+            path.push('/');
+            path.push_str(&part.as_os_str().to_string_lossy());
+        }
+    }
+    path
+}
+
+/// Marks a file wsp owns and rewrites, in a comment (or in JSON, a `//`
+/// key) at the top of the file.
+const GENERATED: &str = "Code generated by wsp. DO NOT EDIT.";
+
+/// Lines the marker may be on: an XML declaration or JSON's opening brace
+/// can come first.
+const MARKER_LINES: usize = 2;
+
+/// Fails unless `path` is absent or carries the `GENERATED` marker, so files
+/// the user wrote are never overwritten. Returns whether the file exists.
+fn check_generated(path: &Path) -> Result<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+    let existing =
+        fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    if !existing
+        .lines()
+        .take(MARKER_LINES)
+        .any(|l| l.contains(GENERATED))
+    {
+        bail!(
+            "{} was not generated by wsp; not overwriting it",
+            path.display()
+        );
+    }
+    Ok(true)
+}
+
+/// Writes a generated file atomically. The temp file goes in the workspace
+/// root, where `wsp doctor` looks for leftovers.
+fn write_generated(ws_dir: &Path, path: &Path, content: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+    let mut tmp =
+        tempfile::NamedTempFile::new_in(ws_dir).context("creating temp file for atomic save")?;
+    tmp.write_all(content.as_bytes())
+        .with_context(|| format!("writing {}", path.display()))?;
+    tmp.persist(path)
+        .with_context(|| format!("renaming temp file to {}", path.display()))?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -63,7 +202,8 @@ mod tests {
 
     use crate::workspace::Metadata;
 
-    fn make_metadata(repos: &[&str]) -> Metadata {
+    /// A workspace named `test` with `repos` active.
+    pub(super) fn make_metadata(repos: &[&str]) -> Metadata {
         let mut map = BTreeMap::new();
         for id in repos {
             map.insert(id.to_string(), None);
@@ -73,10 +213,43 @@ mod tests {
             branch: "test".into(),
             repos: map,
             created: Utc::now(),
+            base: None,
+            parent: None,
             dirs: BTreeMap::new(),
+            tasks: BTreeMap::new(),
         }
     }
 
+    pub(super) fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_check_generated() {
+        let cases = vec![
+            ("# Code generated by wsp. DO NOT EDIT.\nx = 1\n", true),
+            (
+                "<?xml version=\"1.0\"?>\n<!-- Code generated by wsp. DO NOT EDIT. -->\n",
+                true,
+            ),
+            (
+                "{\n  \"//\": \"Code generated by wsp. DO NOT EDIT.\"\n}\n",
+                true,
+            ),
+            ("x = 1\n\n# Code generated by wsp. DO NOT EDIT.\n", false),
+            ("x = 1\n", false),
+        ];
+        for (content, want) in cases {
+            let tmp = tempfile::tempdir().unwrap();
+            let path = tmp.path().join("file");
+            fs::write(&path, content).unwrap();
+            assert_eq!(check_generated(&path).is_ok(), want, "{}", content);
+        }
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(!check_generated(&tmp.path().join("absent")).unwrap());
+    }
+
     #[test]
     fn test_run_integrations_default_enabled() {
         let tmp = tempfile::tempdir().unwrap();
@@ -146,6 +319,26 @@ mod tests {
         assert!(ws_dir.join("go.work").exists());
     }
 
+    #[test]
+    fn test_run_integrations_vscode_opt_in() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+        fs::create_dir_all(ws_dir.join("api")).unwrap();
+        let meta = make_metadata(&["github.com/acme/api"]);
+        let file = ws_dir.join("test.code-workspace");
+
+        let mut cfg = Config::default();
+        assert!(!is_enabled(&cfg, "vscode"));
+        assert!(is_enabled(&cfg, "go"));
+        run_integrations(ws_dir, &meta, &cfg);
+        assert!(!file.exists());
+
+        cfg.language_integrations = Some(BTreeMap::from([("vscode".to_string(), true)]));
+        assert!(is_enabled(&cfg, "vscode"));
+        run_integrations(ws_dir, &meta, &cfg);
+        assert!(file.exists());
+    }
+
     #[test]
     fn test_run_integrations_no_detection() {
         let tmp = tempfile::tempdir().unwrap();
@@ -0,0 +1,376 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value, json};
+
+use crate::config::{Config, DEFAULT_NODE_PACKAGE_MANAGER};
+use crate::workspace::Metadata;
+
+use super::{
+    GENERATED, LanguageIntegration, check_generated, find_manifests, repo_dirs, write_generated,
+    ws_relative,
+};
+
+/// Dependency fields that can name a package from another repo.
+const DEP_FIELDS: &[&str] = &[
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Manager {
+    Pnpm,
+    Npm,
+    Yarn,
+}
+
+impl Manager {
+    fn parse(s: &str) -> Option<Manager> {
+        match s {
+            "pnpm" => Some(Manager::Pnpm),
+            "npm" => Some(Manager::Npm),
+            "yarn" => Some(Manager::Yarn),
+            _ => None,
+        }
+    }
+
+    /// The root manifest this manager reads workspaces from.
+    fn file_name(self) -> &'static str {
+        match self {
+            Manager::Pnpm => "pnpm-workspace.yaml",
+            Manager::Npm | Manager::Yarn => "package.json",
+        }
+    }
+
+    /// Dependency spec pointing at a local directory.
+    fn local_spec(self, path: &str) -> String {
+        match self {
+            Manager::Npm => format!("file:./{}", path),
+            Manager::Pnpm | Manager::Yarn => format!("link:./{}", path),
+        }
+    }
+}
+
+pub struct NodeIntegration {
+    manager: Manager,
+}
+
+impl NodeIntegration {
+    /// Uses the `node-package-manager` config key, falling back to pnpm.
+    pub fn new(config: &Config) -> NodeIntegration {
+        let name = config
+            .node_package_manager
+            .as_deref()
+            .unwrap_or(DEFAULT_NODE_PACKAGE_MANAGER);
+        NodeIntegration {
+            manager: Manager::parse(name).unwrap_or(Manager::Pnpm),
+        }
+    }
+}
+
+impl LanguageIntegration for NodeIntegration {
+    fn name(&self) -> &str {
+        "node"
+    }
+
+    fn detect(&self, ws_dir: &Path, metadata: &Metadata) -> bool {
+        repo_dirs(ws_dir, metadata)
+            .iter()
+            .any(|(_, _, path)| path.join("package.json").exists())
+    }
+
+    /// Writes a root workspace manifest listing every clone with a
+    /// package.json, with overrides so a dependency on a package from another
+    /// repo of the workspace resolves to the local clone.
+    fn apply(&self, ws_dir: &Path, metadata: &Metadata) -> Result<()> {
+        let mut members = Vec::new();
+        // package name -> (providing repo, path relative to the workspace)
+        let mut packages: BTreeMap<String, (String, String)> = BTreeMap::new();
+        // (depending repo, package name)
+        let mut deps: BTreeSet<(String, String)> = BTreeSet::new();
+        for (identity, dn, path) in repo_dirs(ws_dir, metadata) {
+            if !path.join("package.json").exists() {
+                continue;
+            }
+            members.push(dn.clone());
+            for manifest in find_manifests(&path, "package.json")? {
+                let content = fs::read_to_string(&manifest)
+                    .with_context(|| format!("reading {}", manifest.display()))?;
+                let pkg: Value = serde_json::from_str(&content)
+                    .with_context(|| format!("parsing {}", manifest.display()))?;
+                if let Some(name) = pkg.get("name").and_then(|v| v.as_str()) {
+                    let dir = manifest.parent().unwrap_or(&path);
+                    packages.insert(
+                        name.to_string(),
+                        (identity.clone(), ws_relative(&dn, &path, dir)),
+                    );
+                }
+                for field in DEP_FIELDS {
+                    if let Some(names) = pkg.get(*field).and_then(|v| v.as_object()) {
+                        for name in names.keys() {
+                            deps.insert((identity.clone(), name.clone()));
+                        }
+                    }
+                }
+            }
+        }
+        members.sort();
+
+        let overrides = overrides(&packages, &deps);
+        let content = match self.manager {
+            Manager::Pnpm => pnpm_workspace(&members, &overrides),
+            Manager::Npm | Manager::Yarn => {
+                package_json(&metadata.name, &members, &overrides, self.manager)?
+            }
+        };
+
+        // Switching managers leaves the other manager's file behind; drop it
+        // if we wrote it.
+        for other in [Manager::Pnpm, Manager::Npm] {
+            if other.file_name() == self.manager.file_name() {
+                continue;
+            }
+            let path = ws_dir.join(other.file_name());
+            if matches!(check_generated(&path), Ok(true)) {
+                fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))?;
+            }
+        }
+
+        let path = ws_dir.join(self.manager.file_name());
+        check_generated(&path)?;
+        write_generated(ws_dir, &path, &content)
+    }
+}
+
+/// Picks the packages that one repo depends on and another repo provides.
+fn overrides(
+    packages: &BTreeMap<String, (String, String)>,
+    deps: &BTreeSet<(String, String)>,
+) -> BTreeMap<String, String> {
+    let mut overrides = BTreeMap::new();
+    for (repo, name) in deps {
+        if let Some((provider, path)) = packages.get(name)
+            && provider != repo
+        {
+            overrides.insert(name.clone(), path.clone());
+        }
+    }
+    overrides
+}
+
+fn pnpm_workspace(members: &[String], overrides: &BTreeMap<String, String>) -> String {
+    // JSON strings are valid double-quoted YAML scalars.
+    let quote = |s: &str| Value::String(s.to_string()).to_string();
+    let mut out = format!("# {}\n", GENERATED);
+    out.push_str("packages:\n");
+    for member in members {
+        out.push_str(&format!("  - {}\n", quote(member)));
+    }
+    if !overrides.is_empty() {
+        out.push_str("overrides:\n");
+        for (name, path) in overrides {
+            out.push_str(&format!(
+                "  {}: {}\n",
+                quote(name),
+                quote(&Manager::Pnpm.local_spec(path))
+            ));
+        }
+    }
+    out
+}
+
+fn package_json(
+    ws_name: &str,
+    members: &[String],
+    overrides: &BTreeMap<String, String>,
+    manager: Manager,
+) -> Result<String> {
+    let mut root = Map::new();
+    // JSON has no comments, so the marker goes in a `//` key. Maps keep
+    // insertion order, so inserting it first puts it on the second line,
+    // where `check_generated` looks.
+    root.insert("//".into(), json!(GENERATED));
+    root.insert("name".into(), json!(format!("{}-workspace", ws_name)));
+    root.insert("private".into(), json!(true));
+    root.insert("workspaces".into(), json!(members));
+    if !overrides.is_empty() {
+        let specs: Map<String, Value> = overrides
+            .iter()
+            .map(|(name, path)| (name.clone(), json!(manager.local_spec(path))))
+            .collect();
+        let field = match manager {
+            Manager::Yarn => "resolutions",
+            Manager::Npm | Manager::Pnpm => "overrides",
+        };
+        root.insert(field.into(), Value::Object(specs));
+    }
+    let mut out = serde_json::to_string_pretty(&Value::Object(root))?;
+    out.push('\n');
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::tests::{make_metadata, write};
+
+    /// web depends on @acme/ui from the ui repo, which is a workspace of its
+    /// own; api is not a node repo.
+    fn setup(ws_dir: &Path) -> Metadata {
+        write(
+            &ws_dir.join("web").join("package.json"),
+            r#"{"name": "web", "dependencies": {"@acme/ui": "^1.0.0", "react": "^18"}}"#,
+        );
+        write(
+            &ws_dir.join("ui").join("package.json"),
+            r#"{"name": "ui-root", "private": true, "workspaces": ["packages/*"]}"#,
+        );
+        write(
+            &ws_dir
+                .join("ui")
+                .join("packages")
+                .join("ui")
+                .join("package.json"),
+            r#"{"name": "@acme/ui", "devDependencies": {"ui-root": "*"}}"#,
+        );
+        // Installed dependencies are never scanned.
+        write(
+            &ws_dir
+                .join("web")
+                .join("node_modules")
+                .join("@acme")
+                .join("ui")
+                .join("package.json"),
+            r#"{"name": "@acme/ui"}"#,
+        );
+        write(&ws_dir.join("api").join("go.mod"), "module api\n");
+        make_metadata(&[
+            "github.com/acme/web",
+            "github.com/acme/ui",
+            "github.com/acme/api",
+        ])
+    }
+
+    fn integration(manager: &str) -> NodeIntegration {
+        let cfg = Config {
+            node_package_manager: Some(manager.into()),
+            ..Config::default()
+        };
+        NodeIntegration::new(&cfg)
+    }
+
+    #[test]
+    fn test_new() {
+        let cases = vec![
+            (None, Manager::Pnpm),
+            (Some("npm"), Manager::Npm),
+            (Some("yarn"), Manager::Yarn),
+            (Some("bun"), Manager::Pnpm),
+        ];
+        for (value, want) in cases {
+            let cfg = Config {
+                node_package_manager: value.map(String::from),
+                ..Config::default()
+            };
+            assert_eq!(NodeIntegration::new(&cfg).manager, want, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+        write(&ws_dir.join("api").join("go.mod"), "module api\n");
+        let meta = make_metadata(&["github.com/acme/api", "github.com/acme/web"]);
+        assert!(!integration("pnpm").detect(ws_dir, &meta));
+
+        write(&ws_dir.join("web").join("package.json"), "{}");
+        assert!(integration("pnpm").detect(ws_dir, &meta));
+    }
+
+    #[test]
+    fn test_apply_pnpm() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+        let meta = setup(ws_dir);
+
+        integration("pnpm").apply(ws_dir, &meta).unwrap();
+        let content = fs::read_to_string(ws_dir.join("pnpm-workspace.yaml")).unwrap();
+        assert_eq!(
+            content,
+            "# Code generated by wsp. DO NOT EDIT.\n\
+             packages:\n  - \"ui\"\n  - \"web\"\n\
+             overrides:\n  \"@acme/ui\": \"link:./ui/packages/ui\"\n"
+        );
+        let parsed: serde_yaml_ng::Value = serde_yaml_ng::from_str(&content).unwrap();
+        assert_eq!(parsed["packages"][1], "web");
+    }
+
+    #[test]
+    fn test_apply_npm_and_yarn() {
+        let cases = vec![
+            ("npm", "overrides", "file:./ui/packages/ui"),
+            ("yarn", "resolutions", "link:./ui/packages/ui"),
+        ];
+        for (manager, field, spec) in cases {
+            let tmp = tempfile::tempdir().unwrap();
+            let ws_dir = tmp.path();
+            let meta = setup(ws_dir);
+
+            integration(manager).apply(ws_dir, &meta).unwrap();
+            let content = fs::read_to_string(ws_dir.join("package.json")).unwrap();
+            assert!(
+                content.starts_with("{\n  \"//\": \"Code generated by wsp. DO NOT EDIT.\""),
+                "{}",
+                content
+            );
+            let parsed: Value = serde_json::from_str(&content).unwrap();
+            assert_eq!(parsed["name"], "test-workspace", "{}", manager);
+            assert_eq!(parsed["workspaces"], json!(["ui", "web"]), "{}", manager);
+            assert_eq!(parsed[field], json!({"@acme/ui": spec}), "{}", manager);
+        }
+    }
+
+    #[test]
+    fn test_apply_switch_manager() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+        let meta = setup(ws_dir);
+
+        integration("pnpm").apply(ws_dir, &meta).unwrap();
+        integration("npm").apply(ws_dir, &meta).unwrap();
+        assert!(!ws_dir.join("pnpm-workspace.yaml").exists());
+        assert!(ws_dir.join("package.json").exists());
+
+        integration("pnpm").apply(ws_dir, &meta).unwrap();
+        assert!(ws_dir.join("pnpm-workspace.yaml").exists());
+        assert!(!ws_dir.join("package.json").exists());
+    }
+
+    #[test]
+    fn test_apply_keeps_user_manifest() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+        let meta = setup(ws_dir);
+        let user = "{\"name\": \"mine\"}\n";
+        write(&ws_dir.join("package.json"), user);
+
+        let err = integration("npm").apply(ws_dir, &meta).unwrap_err();
+        assert!(err.to_string().contains("not generated by wsp"), "{}", err);
+        assert_eq!(
+            fs::read_to_string(ws_dir.join("package.json")).unwrap(),
+            user
+        );
+
+        // Writing pnpm-workspace.yaml leaves it alone too.
+        integration("pnpm").apply(ws_dir, &meta).unwrap();
+        assert_eq!(
+            fs::read_to_string(ws_dir.join("package.json")).unwrap(),
+            user
+        );
+    }
+}
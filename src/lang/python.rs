@@ -0,0 +1,250 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+
+use crate::config::Config;
+use crate::workspace::Metadata;
+
+use super::{GENERATED, LanguageIntegration, check_generated, repo_dirs, write_generated};
+
+const REQUIREMENTS: &str = "requirements.txt";
+const VENV: &str = ".venv";
+
+/// Copy of the requirements last installed into `.venv`.
+const INSTALLED: &str = "wsp-requirements.txt";
+
+pub struct PythonIntegration {
+    /// Create `.venv` and install the requirements into it (`python-venv`).
+    venv: bool,
+}
+
+impl PythonIntegration {
+    pub fn new(config: &Config) -> PythonIntegration {
+        PythonIntegration {
+            venv: config.python_venv.unwrap_or(false),
+        }
+    }
+}
+
+impl LanguageIntegration for PythonIntegration {
+    fn name(&self) -> &str {
+        "python"
+    }
+
+    fn detect(&self, ws_dir: &Path, metadata: &Metadata) -> bool {
+        repo_dirs(ws_dir, metadata)
+            .iter()
+            .any(|(_, _, path)| is_python_project(path))
+    }
+
+    /// Writes `requirements.txt` at the workspace root with an editable
+    /// install of every Python clone, so installing it makes each package
+    /// import the others from their local clones. With `python-venv` on, also
+    /// creates `.venv` and installs the file into it whenever it changes.
+    fn apply(&self, ws_dir: &Path, metadata: &Metadata) -> Result<()> {
+        let mut dirs: Vec<String> = repo_dirs(ws_dir, metadata)
+            .into_iter()
+            .filter(|(_, _, path)| is_installable(path))
+            .map(|(_, dn, _)| dn)
+            .collect();
+        dirs.sort();
+
+        let mut out = format!("# {}\n", GENERATED);
+        out.push_str("# Install with: pip install -r requirements.txt\n");
+        for dn in &dirs {
+            out.push_str(&format!("-e ./{}\n", dn));
+        }
+
+        let path = ws_dir.join(REQUIREMENTS);
+        check_generated(&path)?;
+        write_generated(ws_dir, &path, &out)?;
+
+        if self.venv {
+            create_venv(ws_dir)?;
+            // Reinstall only when the requirements changed since the last
+            // successful install, so a failed one is retried next time.
+            let stamp = ws_dir.join(VENV).join(INSTALLED);
+            if !dirs.is_empty() && fs::read_to_string(&stamp).ok().as_deref() != Some(&out) {
+                install(ws_dir)?;
+                fs::write(&stamp, &out).with_context(|| format!("writing {}", stamp.display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_python_project(dir: &Path) -> bool {
+    dir.join("pyproject.toml").exists() || dir.join("setup.py").exists()
+}
+
+/// Whether pip can install the dir. A pyproject.toml that only configures
+/// tools (black, ruff, ...) describes no package.
+fn is_installable(dir: &Path) -> bool {
+    if dir.join("setup.py").exists() {
+        return true;
+    }
+    let Ok(content) = fs::read_to_string(dir.join("pyproject.toml")) else {
+        return false;
+    };
+    match content.parse::<toml::Table>() {
+        Ok(table) => table.contains_key("project") || table.contains_key("build-system"),
+        Err(_) => false,
+    }
+}
+
+/// Creates `.venv` unless it exists, preferring uv.
+fn create_venv(ws_dir: &Path) -> Result<()> {
+    if ws_dir.join(VENV).exists() {
+        return Ok(());
+    }
+    if has_uv() {
+        run(ws_dir, "uv", &["venv", "--quiet", VENV])?;
+    } else {
+        run(ws_dir, "python3", &["-m", "venv", VENV])?;
+    }
+    Ok(())
+}
+
+fn install(ws_dir: &Path) -> Result<()> {
+    let python = ws_dir.join(VENV).join("bin").join("python");
+    let python = python
+        .to_str()
+        .context("path contains non-UTF8 characters")?;
+    if has_uv() {
+        run(
+            ws_dir,
+            "uv",
+            &[
+                "pip",
+                "install",
+                "--quiet",
+                "--python",
+                python,
+                "-r",
+                REQUIREMENTS,
+            ],
+        )
+    } else {
+        run(
+            ws_dir,
+            python,
+            &["-m", "pip", "install", "--quiet", "-r", REQUIREMENTS],
+        )
+    }
+}
+
+fn has_uv() -> bool {
+    Command::new("uv")
+        .arg("--version")
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+fn run(dir: &Path, program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| anyhow::anyhow!("running {}: {}", program, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        bail!(
+            "{} {}: {}\n{}",
+            program,
+            args.join(" "),
+            output.status,
+            stderr
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::tests::{make_metadata, write};
+
+    #[test]
+    fn test_detect() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+        write(&ws_dir.join("web").join("package.json"), "{}");
+        let meta = make_metadata(&["github.com/acme/web", "github.com/acme/etl"]);
+        assert!(!PythonIntegration { venv: false }.detect(ws_dir, &meta));
+
+        write(&ws_dir.join("etl").join("setup.py"), "");
+        assert!(PythonIntegration { venv: false }.detect(ws_dir, &meta));
+    }
+
+    #[test]
+    fn test_is_installable() {
+        let cases = vec![
+            ("[project]\nname = \"etl\"\n", true),
+            (
+                "[build-system]\nrequires = [\"hatchling\"]\nbuild-backend = \"hatchling.build\"\n",
+                true,
+            ),
+            ("[tool.ruff]\nline-length = 100\n", false),
+            ("not toml [", false),
+        ];
+        for (pyproject, want) in cases {
+            let tmp = tempfile::tempdir().unwrap();
+            fs::write(tmp.path().join("pyproject.toml"), pyproject).unwrap();
+            assert_eq!(is_installable(tmp.path()), want, "{}", pyproject);
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+        write(
+            &ws_dir.join("models").join("pyproject.toml"),
+            "[project]\nname = \"acme-models\"\n",
+        );
+        write(&ws_dir.join("etl").join("setup.py"), "");
+        write(
+            &ws_dir.join("tools").join("pyproject.toml"),
+            "[tool.black]\n",
+        );
+        let meta = make_metadata(&[
+            "github.com/acme/models",
+            "github.com/acme/etl",
+            "github.com/acme/tools",
+        ]);
+
+        PythonIntegration { venv: false }
+            .apply(ws_dir, &meta)
+            .unwrap();
+        let content = fs::read_to_string(ws_dir.join(REQUIREMENTS)).unwrap();
+        assert_eq!(
+            content,
+            "# Code generated by wsp. DO NOT EDIT.\n\
+             # Install with: pip install -r requirements.txt\n\
+             -e ./etl\n\
+             -e ./models\n"
+        );
+        assert!(!ws_dir.join(VENV).exists());
+    }
+
+    #[test]
+    fn test_apply_keeps_user_requirements() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+        write(&ws_dir.join("etl").join("setup.py"), "");
+        write(&ws_dir.join(REQUIREMENTS), "requests\n");
+        let meta = make_metadata(&["github.com/acme/etl"]);
+
+        let err = PythonIntegration { venv: false }
+            .apply(ws_dir, &meta)
+            .unwrap_err();
+        assert!(err.to_string().contains("not generated by wsp"), "{}", err);
+        assert_eq!(
+            fs::read_to_string(ws_dir.join(REQUIREMENTS)).unwrap(),
+            "requests\n"
+        );
+    }
+}
@@ -0,0 +1,473 @@
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Map, Value, json};
+
+use crate::workspace::Metadata;
+
+use super::{LanguageIntegration, write_generated};
+
+/// Marks a file wsp maintains. Unlike the other generated files it may be
+/// edited: only `folders` is rewritten.
+const HEADER: &str =
+    "// Generated by wsp. Folders are rewritten when repos change; other settings are kept.\n";
+
+const EXTENSION: &str = ".code-workspace";
+
+pub struct VsCodeIntegration;
+
+impl LanguageIntegration for VsCodeIntegration {
+    fn name(&self) -> &str {
+        "vscode"
+    }
+
+    fn detect(&self, _ws_dir: &Path, metadata: &Metadata) -> bool {
+        !metadata.repos.is_empty()
+    }
+
+    /// Not everyone uses VS Code; the file is only written on request.
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    /// Writes `<workspace>.code-workspace` with each clone as a folder of a
+    /// multi-root workspace, labeled active or context, so VS Code tracks
+    /// each repo's git state separately. Only the `folders` array of an
+    /// existing file is rewritten, so settings, extensions, launch configs
+    /// and comments outside it are kept as written. Folders the user added
+    /// are kept too; folders that no longer exist are dropped.
+    fn apply(&self, ws_dir: &Path, metadata: &Metadata) -> Result<()> {
+        let path = ws_dir.join(format!("{}{}", metadata.name, EXTENSION));
+        let existing = match read_generated(&path)? {
+            Some(file) => Some(file),
+            // After `wsp rename`, carry the settings over from the old file.
+            None => match stale_file(ws_dir, &path)? {
+                Some(old) => {
+                    let file = read_generated(&old)?;
+                    fs::remove_file(&old).with_context(|| format!("removing {}", old.display()))?;
+                    file
+                }
+                None => None,
+            },
+        };
+
+        let out = match existing {
+            Some((content, root)) => {
+                let folders = Value::Array(folders(ws_dir, metadata, root.get("folders")));
+                match folders_span(&content) {
+                    Some(span) => splice(&content, span, &folders)?,
+                    // No `folders` array to replace; write the file afresh.
+                    None => render(merge(root, folders))?,
+                }
+            }
+            None => render(merge(
+                Map::new(),
+                Value::Array(folders(ws_dir, metadata, None)),
+            ))?,
+        };
+        write_generated(ws_dir, &path, &out)
+    }
+}
+
+/// Reads a file wsp generated, with its parsed contents. None if it doesn't
+/// exist; an error if the user wrote it.
+fn read_generated(path: &Path) -> Result<Option<(String, Map<String, Value>)>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content =
+        fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    if !content.starts_with(HEADER) {
+        bail!(
+            "{} was not generated by wsp; not overwriting it",
+            path.display()
+        );
+    }
+    match serde_json::from_str(&strip_jsonc(&content)) {
+        Ok(Value::Object(root)) => Ok(Some((content, root))),
+        Ok(_) => bail!("{}: expected a JSON object", path.display()),
+        Err(e) => bail!("parsing {}: {}", path.display(), e),
+    }
+}
+
+/// Finds another `.code-workspace` file wsp generated in the workspace root,
+/// left behind by a rename.
+fn stale_file(ws_dir: &Path, path: &Path) -> Result<Option<PathBuf>> {
+    let entries = fs::read_dir(ws_dir).with_context(|| format!("reading {}", ws_dir.display()))?;
+    let mut stale: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p != path)
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.ends_with(EXTENSION))
+        })
+        .filter(|p| {
+            fs::read_to_string(p)
+                .map(|c| c.starts_with(HEADER))
+                .unwrap_or(false)
+        })
+        .collect();
+    stale.sort();
+    Ok(stale.into_iter().next())
+}
+
+/// The workspace's folders: active repos first, then context repos, then
+/// folders from `old` the user added that still exist.
+fn folders(ws_dir: &Path, metadata: &Metadata, old: Option<&Value>) -> Vec<Value> {
+    let mut active = Vec::new();
+    let mut context = Vec::new();
+    for info in metadata.repo_infos(ws_dir) {
+        if info.error.is_some() {
+            continue;
+        }
+        match info.pinned_ref {
+            Some(r) => context.push(json!({
+                "name": format!("{} (context @ {})", info.dir_name, r),
+                "path": info.dir_name,
+            })),
+            None => active.push(json!({
+                "name": format!("{} (active)", info.dir_name),
+                "path": info.dir_name,
+            })),
+        }
+    }
+    let repo_paths: Vec<String> = active
+        .iter()
+        .chain(&context)
+        .filter_map(|f| f["path"].as_str().map(String::from))
+        .collect();
+
+    let mut folders: Vec<Value> = active.into_iter().chain(context).collect();
+    if let Some(Value::Array(old)) = old {
+        for folder in old {
+            let Some(p) = folder.get("path").and_then(|v| v.as_str()) else {
+                // `uri` folders point elsewhere; keep them.
+                folders.push(folder.clone());
+                continue;
+            };
+            if repo_paths.iter().any(|r| r == p) || !ws_dir.join(p).exists() {
+                continue;
+            }
+            folders.push(folder.clone());
+        }
+    }
+    folders
+}
+
+/// Puts `folders` into `root`. `folders` goes first, as VS Code writes it,
+/// and the user's keys keep their order; maps keep insertion order.
+fn merge(mut root: Map<String, Value>, folders: Value) -> Map<String, Value> {
+    let mut merged = Map::new();
+    merged.insert("folders".into(), folders);
+    root.shift_remove("folders");
+    if !root.contains_key("settings") {
+        merged.insert("settings".into(), json!({}));
+    }
+    merged.extend(root);
+    merged
+}
+
+fn render(root: Map<String, Value>) -> Result<String> {
+    let mut out = String::from(HEADER);
+    out.push_str(&serde_json::to_string_pretty(&Value::Object(root))?);
+    out.push('\n');
+    Ok(out)
+}
+
+/// Replaces the `span` of `content` with `folders`, indented to match the
+/// line it starts on.
+fn splice(content: &str, span: Range<usize>, folders: &Value) -> Result<String> {
+    let line_start = content[..span.start].rfind('\n').map_or(0, |i| i + 1);
+    let indent: String = content[line_start..span.start]
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect();
+    let pretty = serde_json::to_string_pretty(folders)?;
+    let mut out = String::with_capacity(content.len() + pretty.len());
+    out.push_str(&content[..span.start]);
+    out.push_str(&pretty.replace('\n', &format!("\n{}", indent)));
+    out.push_str(&content[span.end..]);
+    Ok(out)
+}
+
+/// Finds the byte range of the top-level `folders` array in JSONC text.
+/// None if there is no such array.
+fn folders_span(s: &str) -> Option<Range<usize>> {
+    let b = s.as_bytes();
+    let mut depth = 0;
+    let mut key = "";
+    let mut in_folders = false;
+    let mut start = None;
+    let mut i = 0;
+    while i < b.len() {
+        match b[i] {
+            b'"' => {
+                let open = i;
+                i += 1;
+                while i < b.len() && b[i] != b'"' {
+                    if b[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                if depth == 1 {
+                    key = s.get(open + 1..i).unwrap_or("");
+                }
+            }
+            b'/' if b.get(i + 1) == Some(&b'/') => {
+                while i < b.len() && b[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if b.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i + 1 < b.len() && !(b[i] == b'*' && b[i + 1] == b'/') {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b':' if depth == 1 => in_folders = key == "folders",
+            b',' if depth == 1 => in_folders = false,
+            b'[' | b'{' => {
+                if depth == 1 && in_folders && b[i] == b'[' {
+                    start = Some(i);
+                }
+                depth += 1;
+            }
+            b']' | b'}' => {
+                depth -= 1;
+                if depth == 1
+                    && let Some(start) = start
+                {
+                    return Some(start..i + 1);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Strips `//` and `/* */` comments and trailing commas, which VS Code
+/// allows in its JSON files.
+fn strip_jsonc(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                out.push(c);
+                while let Some(c) = chars.next() {
+                    out.push(c);
+                    match c {
+                        '\\' => {
+                            if let Some(escaped) = chars.next() {
+                                out.push(escaped);
+                            }
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push(c);
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            ']' | '}' => {
+                let trimmed = out.trim_end().len();
+                if out[..trimmed].ends_with(',') {
+                    out.truncate(trimmed - 1);
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::workspace::WorkspaceRepoRef;
+
+    /// Workspace `name` with `(identity, ref)` repos; an empty ref is active.
+    fn make_metadata(name: &str, repos: &[(&str, &str)]) -> Metadata {
+        let ids: Vec<&str> = repos.iter().map(|(id, _)| *id).collect();
+        let mut meta = crate::lang::tests::make_metadata(&ids);
+        meta.name = name.into();
+        meta.branch = name.into();
+        for (id, r) in repos.iter().filter(|(_, r)| !r.is_empty()) {
+            meta.repos.insert(
+                id.to_string(),
+                Some(WorkspaceRepoRef {
+                    r#ref: r.to_string(),
+                    base: String::new(),
+                }),
+            );
+        }
+        meta
+    }
+
+    fn read(path: &Path) -> Value {
+        let content = fs::read_to_string(path).unwrap();
+        assert!(content.starts_with(HEADER), "{}", content);
+        serde_json::from_str(&strip_jsonc(&content)).unwrap()
+    }
+
+    #[test]
+    fn test_strip_jsonc() {
+        let cases = vec![
+            ("{\"a\": 1}", "{\"a\": 1}"),
+            ("{\"a\": 1, // one\n}", "{\"a\": 1}"),
+            ("{/* x */\"a\": [1, 2,],}", "{\"a\": [1, 2]}"),
+            (
+                "{\"url\": \"http://x/*y*/\"}",
+                "{\"url\": \"http://x/*y*/\"}",
+            ),
+            ("{\"q\": \"a\\\"//b\"}", "{\"q\": \"a\\\"//b\"}"),
+        ];
+        for (input, want) in cases {
+            assert_eq!(strip_jsonc(input), want, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_folders_span() {
+        let cases = vec![
+            ("{\"folders\": [1, [2]], \"x\": 3}", Some("[1, [2]]")),
+            (
+                "{\"settings\": {\"folders\": [1]}, \"folders\": []}",
+                Some("[]"),
+            ),
+            (
+                "{/* \"folders\": [1] */ \"folders\" : [\"]\"]}",
+                Some("[\"]\"]"),
+            ),
+            (
+                "// \"folders\": [1]\n{\"a\": [\"folders\"], \"b\": 1}",
+                None,
+            ),
+            ("{\"folders\": null}", None),
+        ];
+        for (input, want) in cases {
+            assert_eq!(folders_span(input).map(|r| &input[r]), want, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+        for dir in ["api", "web", "proto", "notes"] {
+            fs::create_dir_all(ws_dir.join(dir)).unwrap();
+        }
+        let meta = make_metadata(
+            "feat",
+            &[
+                ("github.com/acme/web", ""),
+                ("github.com/acme/proto", "v1.0"),
+                ("github.com/acme/api", ""),
+            ],
+        );
+        let path = ws_dir.join("feat.code-workspace");
+
+        VsCodeIntegration.apply(ws_dir, &meta).unwrap();
+        assert_eq!(
+            read(&path),
+            json!({
+                "folders": [
+                    {"name": "api (active)", "path": "api"},
+                    {"name": "web (active)", "path": "web"},
+                    {"name": "proto (context @ v1.0)", "path": "proto"},
+                ],
+                "settings": {},
+            })
+        );
+
+        // The user adds a folder and settings, with comments.
+        let settings = "  // Two spaces, like the rest of the team.\n  \"settings\": {\"editor.tabSize\": 2,},\n  \"extensions\": {\"recommendations\": [\"rust-lang.rust-analyzer\"]} /* for api */\n}\n";
+        fs::write(
+            &path,
+            format!(
+                "{}{{\n  \"folders\": [\n    {{\"path\": \"api\"}},\n    {{\"name\": \"notes\", \"path\": \"notes\"}}, // mine\n  ],\n{}",
+                HEADER, settings
+            ),
+        )
+        .unwrap();
+
+        // web is removed from the workspace.
+        fs::remove_dir(ws_dir.join("web")).unwrap();
+        let meta = make_metadata(
+            "feat",
+            &[
+                ("github.com/acme/proto", "v1.0"),
+                ("github.com/acme/api", ""),
+            ],
+        );
+        VsCodeIntegration.apply(ws_dir, &meta).unwrap();
+        assert_eq!(
+            read(&path),
+            json!({
+                "folders": [
+                    {"name": "api (active)", "path": "api"},
+                    {"name": "proto (context @ v1.0)", "path": "proto"},
+                    {"name": "notes", "path": "notes"},
+                ],
+                "settings": {"editor.tabSize": 2},
+                "extensions": {"recommendations": ["rust-lang.rust-analyzer"]},
+            })
+        );
+        let keys: Vec<String> = read(&path).as_object().unwrap().keys().cloned().collect();
+        assert_eq!(keys, vec!["folders", "settings", "extensions"]);
+        // Only `folders` is rewritten; comments outside it are kept.
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.ends_with(settings), "{}", content);
+        assert!(
+            content.contains("\n  \"folders\": [\n    {\n      \"name\": \"api (active)\","),
+            "{}",
+            content
+        );
+
+        // A rename carries the file over.
+        let meta = make_metadata("renamed", &[("github.com/acme/api", "")]);
+        VsCodeIntegration.apply(ws_dir, &meta).unwrap();
+        assert!(!path.exists());
+        let renamed = read(&ws_dir.join("renamed.code-workspace"));
+        assert_eq!(renamed["settings"], json!({"editor.tabSize": 2}));
+    }
+
+    #[test]
+    fn test_apply_keeps_user_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_dir = tmp.path();
+        let path = ws_dir.join("feat.code-workspace");
+        fs::write(&path, "{\"folders\": []}\n").unwrap();
+        let meta = make_metadata("feat", &[("github.com/acme/api", "")]);
+
+        let err = VsCodeIntegration.apply(ws_dir, &meta).unwrap_err();
+        assert!(err.to_string().contains("not generated by wsp"), "{}", err);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"folders\": []}\n");
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, bail};
+
+use crate::config::Paths;
+use crate::giturl::Parsed;
+use crate::workspace;
+
+/// How long to wait for another `wsp` to finish with the config or a
+/// workspace before giving up.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Fetches can take a while; waiting on one beats failing.
+pub const MIRROR_TIMEOUT: Duration = Duration::from_secs(300);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Lock files held by this process. OS file locks don't exclude other
+/// threads of the same process, so those check here first.
+static HELD: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// An exclusive advisory lock, released on drop. The lock file records the
+/// holder's pid and command line for "busy" errors.
+pub struct Lock {
+    path: PathBuf,
+    _file: File,
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        release(&self.path);
+    }
+}
+
+/// Locks the global config for a read-modify-write cycle.
+pub fn config(paths: &Paths) -> Result<Lock> {
+    acquire(
+        &paths.locks_dir.join("config.lock"),
+        "config",
+        DEFAULT_TIMEOUT,
+    )
+}
+
+/// Locks a workspace's `.wsp.yaml` and clones. Keyed by name so the lock
+/// outlives renames and removal of the directory. The name is validated
+/// first, since it becomes part of the lock path.
+pub fn workspace(paths: &Paths, name: &str) -> Result<Lock> {
+    workspace::validate_name(name)?;
+    acquire(
+        &paths
+            .locks_dir
+            .join("workspaces")
+            .join(format!("{}.lock", name)),
+        &format!("workspace {:?}", name),
+        DEFAULT_TIMEOUT,
+    )
+}
+
+/// Locks a bare mirror for a fetch or gc. Kept outside the mirror so taking
+/// it never recreates a mirror that was removed.
+pub fn mirror(paths: &Paths, parsed: &Parsed) -> Result<Lock> {
+    acquire(
+        &paths
+            .locks_dir
+            .join("mirrors")
+            .join(parsed.mirror_path())
+            .with_extension("lock"),
+        &format!("mirror {}", parsed.identity()),
+        MIRROR_TIMEOUT,
+    )
+}
+
+/// Takes an exclusive lock on `path`, retrying until `timeout`. `what` names
+/// the locked thing in the error, e.g. `workspace "foo" is busy (pid 123,
+/// command wsp sync)`.
+pub fn acquire(path: &Path, what: &str, timeout: Duration) -> Result<Lock> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| anyhow::anyhow!("creating {}: {}", dir.display(), e))?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("opening lock {}: {}", path.display(), e))?;
+
+    let start = Instant::now();
+    let mut waiting = false;
+    loop {
+        if claim(path) {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) => release(path),
+                Err(TryLockError::Error(e)) => {
+                    release(path);
+                    bail!("locking {}: {}", path.display(), e);
+                }
+            }
+        }
+        if start.elapsed() >= timeout {
+            bail!("{} is busy ({})", what, holder(path));
+        }
+        if !waiting && !timeout.is_zero() {
+            eprintln!("Waiting for {} ({})...", what, holder(path));
+            waiting = true;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    // Best-effort: the lock is held either way.
+    let _ = file
+        .set_len(0)
+        .and_then(|()| file.rewind())
+        .and_then(|()| writeln!(file, "{} {}", std::process::id(), command_line()));
+    Ok(Lock {
+        path: path.to_path_buf(),
+        _file: file,
+    })
+}
+
+/// Reserves `path` within this process. False if another thread holds it.
+fn claim(path: &Path) -> bool {
+    let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+    held.insert(path.to_path_buf())
+}
+
+fn release(path: &Path) {
+    let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+    held.remove(path);
+}
+
+/// Describes who holds a lock, from what they wrote into the lock file.
+fn holder(path: &Path) -> String {
+    let mut data = String::new();
+    let read = File::open(path).and_then(|mut f| f.read_to_string(&mut data));
+    match read.ok().and_then(|_| data.trim().split_once(' ')) {
+        Some((pid, command)) => format!("pid {}, command {}", pid, command),
+        None => "locked by another wsp process".to_string(),
+    }
+}
+
+fn command_line() -> String {
+    let mut args = std::env::args();
+    let program = args
+        .next()
+        .and_then(|p| {
+            Path::new(&p)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "wsp".to_string());
+    std::iter::once(program)
+        .chain(args)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acquire_busy_and_release() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("locks").join("ws.lock");
+
+        let held = acquire(&path, "workspace \"ws\"", DEFAULT_TIMEOUT).unwrap();
+        let err = acquire(&path, "workspace \"ws\"", Duration::from_millis(200))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            err.starts_with(&format!(
+                "workspace \"ws\" is busy (pid {}, command ",
+                std::process::id()
+            )),
+            "{}",
+            err
+        );
+
+        // Another thread waits for the lock rather than failing.
+        let waiter = {
+            let path = path.clone();
+            thread::spawn(move || acquire(&path, "ws", DEFAULT_TIMEOUT).is_ok())
+        };
+        thread::sleep(Duration::from_millis(300));
+        drop(held);
+        assert!(waiter.join().unwrap());
+
+        acquire(&path, "ws", Duration::ZERO).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::git;
+use crate::workspace::Metadata;
+
+pub const LOCK_FILE: &str = "wsp.lock";
+
+/// Exact commit of every repo in a workspace, written by `wsp freeze` and
+/// replayed by `wsp new --from-lock`.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Lockfile {
+    pub workspace: String,
+    pub branch: String,
+    pub created: DateTime<Utc>,
+    pub repos: BTreeMap<String, LockedRepo>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct LockedRepo {
+    pub role: Role,
+    pub sha: String,
+    /// Branch checked out when frozen; empty for a detached HEAD.
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub branch: String,
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Active,
+    Context,
+}
+
+/// Records the HEAD of every clone in the workspace.
+pub fn capture(ws_dir: &Path, meta: &Metadata) -> Result<Lockfile> {
+    let mut repos = BTreeMap::new();
+    for identity in meta.repos.keys() {
+        let clone_dir = ws_dir.join(meta.dir_name(identity)?);
+        let sha = git::head_sha(&clone_dir)
+            .map_err(|e| anyhow::anyhow!("reading HEAD of {}: {}", identity, e))?;
+        let branch = git::branch_current(&clone_dir)
+            .ok()
+            .filter(|b| b != "HEAD")
+            .unwrap_or_default();
+        let url = git::remote_url(&clone_dir, "origin").unwrap_or_default();
+        let role = if meta.is_active(identity) {
+            Role::Active
+        } else {
+            Role::Context
+        };
+        repos.insert(
+            identity.clone(),
+            LockedRepo {
+                role,
+                sha,
+                branch,
+                url,
+            },
+        );
+    }
+    Ok(Lockfile {
+        workspace: meta.name.clone(),
+        branch: meta.branch.clone(),
+        created: Utc::now(),
+        repos,
+    })
+}
+
+pub fn load(path: &Path) -> Result<Lockfile> {
+    let data = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("reading {}: {}", path.display(), e))?;
+    serde_yaml_ng::from_str(&data).map_err(|e| anyhow::anyhow!("parsing {}: {}", path.display(), e))
+}
+
+pub fn save(path: &Path, lock: &Lockfile) -> Result<()> {
+    save_yaml(path, lock, "lockfile")
+}
+
+/// Writes `value` as YAML through a temp file in the same directory, so
+/// readers never see a partial file. `what` names the file in errors.
+pub fn save_yaml<T: Serialize>(path: &Path, value: &T, what: &str) -> Result<()> {
+    let data = serde_yaml_ng::to_string(value)?;
+    let dir = path.parent().filter(|d| !d.as_os_str().is_empty());
+    let mut tmp = tempfile::NamedTempFile::new_in(dir.unwrap_or(Path::new(".")))
+        .context("creating temp file for atomic save")?;
+    tmp.write_all(data.as_bytes())
+        .with_context(|| format!("writing {} to temp file", what))?;
+    tmp.persist(path)
+        .with_context(|| format!("renaming temp file to {}", what))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join(LOCK_FILE);
+        let lock = Lockfile {
+            workspace: "bug-123".into(),
+            branch: "me/bug-123".into(),
+            created: Utc::now(),
+            repos: BTreeMap::from([
+                (
+                    "github.com/acme/api".to_string(),
+                    LockedRepo {
+                        role: Role::Active,
+                        sha: "0123abcd".into(),
+                        branch: "me/bug-123".into(),
+                        url: "git@github.com:acme/api.git".into(),
+                    },
+                ),
+                (
+                    "github.com/acme/proto".to_string(),
+                    LockedRepo {
+                        role: Role::Context,
+                        sha: "4567ef01".into(),
+                        branch: String::new(),
+                        url: String::new(),
+                    },
+                ),
+            ]),
+        };
+        save(&path, &lock).unwrap();
+
+        let data = fs::read_to_string(&path).unwrap();
+        assert!(data.contains("role: context"), "got:\n{}", data);
+        assert!(
+            !data.contains("url: ''"),
+            "empty fields are omitted:\n{}",
+            data
+        );
+        assert_eq!(load(&path).unwrap(), lock);
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let tmp = tempfile::tempdir().unwrap();
+        let missing = tmp.path().join("nope.lock");
+        assert!(load(&missing).unwrap_err().to_string().contains("reading"));
+
+        let bad = tmp.path().join(LOCK_FILE);
+        fs::write(&bad, "workspace: x\nrepos: [").unwrap();
+        assert!(load(&bad).unwrap_err().to_string().contains("parsing"));
+    }
+}
@@ -2,12 +2,18 @@
 
 mod cli;
 mod config;
+mod disk;
 mod git;
 mod giturl;
 mod group;
+mod journal;
 mod lang;
+mod lock;
+mod lockfile;
 mod mirror;
 mod output;
+mod template;
+mod trash;
 mod workspace;
 
 #[cfg(test)]
@@ -40,7 +46,8 @@ fn main() {
         }
     };
 
-    match cli::dispatch(&matches, &paths) {
+    let args = std::env::args().skip(1).collect();
+    match cli::run(&matches, &paths, args) {
         Ok(out) => {
             let code = output::exit_code(&out);
             if let Err(err) = output::render(out, json) {
@@ -1,10 +1,12 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 
+use crate::config::Paths;
 use crate::git;
 use crate::giturl::Parsed;
+use crate::lock;
 
 pub fn dir(mirrors_dir: &Path, parsed: &Parsed) -> PathBuf {
     mirrors_dir.join(parsed.mirror_path())
@@ -26,6 +28,17 @@ pub fn fetch(mirrors_dir: &Path, parsed: &Parsed) -> Result<()> {
     git::fetch(&d, true)
 }
 
+/// Fetches a mirror, waiting for any other `wsp` process fetching or gc'ing
+/// it. Fails if the mirror is missing.
+pub fn fetch_locked(paths: &Paths, parsed: &Parsed, prune: bool) -> Result<()> {
+    let _lock = lock::mirror(paths, parsed)?;
+    let d = dir(&paths.mirrors_dir, parsed);
+    if !d.exists() {
+        bail!("mirror missing at {}", d.display());
+    }
+    git::fetch(&d, prune)
+}
+
 pub fn remove(mirrors_dir: &Path, parsed: &Parsed) -> Result<()> {
     let d = dir(mirrors_dir, parsed);
     fs::remove_dir_all(d)?;
@@ -36,6 +49,37 @@ pub fn exists(mirrors_dir: &Path, parsed: &Parsed) -> bool {
     dir(mirrors_dir, parsed).exists()
 }
 
+/// Identities of every mirror on disk (each `*.git` dir under `mirrors_dir`),
+/// registered or not, sorted.
+pub fn list(mirrors_dir: &Path) -> Result<Vec<String>> {
+    let mut identities = Vec::new();
+    if !mirrors_dir.exists() {
+        return Ok(identities);
+    }
+    let mut stack = vec![mirrors_dir.to_path_buf()];
+    while let Some(d) = stack.pop() {
+        for entry in fs::read_dir(&d)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let path = entry.path();
+            if path.extension().is_some_and(|e| e == "git") {
+                let rel = path.strip_prefix(mirrors_dir)?.with_extension("");
+                let segments: Vec<String> = rel
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().to_string())
+                    .collect();
+                identities.push(segments.join("/"));
+            } else {
+                stack.push(path);
+            }
+        }
+    }
+    identities.sort();
+    Ok(identities)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -67,6 +111,28 @@ mod tests {
         tmp
     }
 
+    #[test]
+    fn test_list() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mirrors_dir = tmp.path().join("mirrors");
+        assert!(list(&mirrors_dir).unwrap().is_empty());
+
+        for rel in [
+            "github.com/acme/api.git",
+            "gitlab.com/group/sub/lib.git",
+            "github.com/acme/api.git/refs",
+            "github.com/acme/not-a-mirror",
+        ] {
+            fs::create_dir_all(mirrors_dir.join(rel)).unwrap();
+        }
+        fs::write(mirrors_dir.join("github.com/acme/stray.git"), "").unwrap();
+
+        assert_eq!(
+            list(&mirrors_dir).unwrap(),
+            vec!["github.com/acme/api", "gitlab.com/group/sub/lib"]
+        );
+    }
+
     #[test]
     fn test_clone_and_exists() {
         let tmp_data = tempfile::tempdir().unwrap();
@@ -116,6 +182,26 @@ mod tests {
         assert_eq!(refspec, "+refs/heads/*:refs/remotes/origin/*");
     }
 
+    #[test]
+    fn test_fetch_locked_does_not_recreate_removed_mirror() {
+        let tmp = tempfile::tempdir().unwrap();
+        let paths = Paths::from_dirs(tmp.path(), &tmp.path().join("workspaces"));
+
+        let repo = create_test_repo();
+        let parsed = Parsed {
+            host: "test.local".into(),
+            owner: "user".into(),
+            repo: "test-repo".into(),
+        };
+        clone(&paths.mirrors_dir, &parsed, repo.path().to_str().unwrap()).unwrap();
+        fetch_locked(&paths, &parsed, true).unwrap();
+
+        remove(&paths.mirrors_dir, &parsed).unwrap();
+        let err = fetch_locked(&paths, &parsed, true).unwrap_err();
+        assert!(err.to_string().starts_with("mirror missing"), "{}", err);
+        assert!(!exists(&paths.mirrors_dir, &parsed));
+    }
+
     #[test]
     fn test_remove() {
         let tmp_data = tempfile::tempdir().unwrap();
@@ -5,6 +5,8 @@ use anyhow::{Result, bail};
 use serde::Serialize;
 use tabwriter::TabWriter;
 
+use crate::workspace::Verdict;
+
 // ---------------------------------------------------------------------------
 // Table helper (existing)
 // ---------------------------------------------------------------------------
@@ -232,12 +234,184 @@ pub struct FetchRepoResult {
     pub error: Option<String>,
 }
 
+#[derive(Serialize)]
+pub struct PinUpdateOutput {
+    pub repos: Vec<PinUpdateEntry>,
+}
+
+#[derive(Serialize)]
+pub struct PinUpdateEntry {
+    pub name: String,
+    pub old_ref: String,
+    /// Set when the pin was moved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_ref: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub newer_tags: Vec<String>,
+    /// Commits on the mirror's branch not yet checked out (branch pins only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub behind: Option<u32>,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PruneOutput {
+    pub dry_run: bool,
+    pub workspaces: Vec<PruneWorkspaceResult>,
+}
+
+#[derive(Serialize)]
+pub struct PruneWorkspaceResult {
+    pub name: String,
+    /// Every active repo is clean and merged into its base.
+    pub safe: bool,
+    pub removed: bool,
+    /// Why the workspace was kept without checking its repos.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub repos: Vec<PruneRepoVerdict>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PruneRepoVerdict {
+    pub name: String,
+    pub verdict: Verdict,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub fetch_failed: bool,
+}
+
+#[derive(Serialize)]
+pub struct TrashListOutput {
+    pub entries: Vec<TrashEntry>,
+}
+
+#[derive(Serialize)]
+pub struct TrashEntry {
+    /// Pass to `wsp trash restore` to pick this entry over newer ones with
+    /// the same name.
+    pub id: String,
+    pub name: String,
+    pub branch: String,
+    pub removed: chrono::DateTime<chrono::Utc>,
+    pub reason: String,
+    /// Where the workspace lived before removal.
+    pub path: String,
+}
+
+#[derive(Serialize)]
+pub struct HistoryOutput {
+    /// Newest first.
+    pub entries: Vec<HistoryEntry>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryEntry {
+    /// Pass to `wsp history --restore`.
+    pub id: usize,
+    pub time: chrono::DateTime<chrono::Utc>,
+    pub command: String,
+    pub args: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub repos: Vec<HistoryRepo>,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryRepo {
+    pub name: String,
+    pub identity: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RepoGcOutput {
+    pub dry_run: bool,
+    pub orphans: Vec<OrphanMirror>,
+    pub usage: Vec<DiskUsageEntry>,
+    /// Bytes on disk across all entries, counting hardlinked files once.
+    pub total_bytes: u64,
+}
+
+/// A mirror on disk whose repo is not in the config.
+#[derive(Serialize)]
+pub struct OrphanMirror {
+    pub identity: String,
+    pub path: PathBuf,
+    pub removed: bool,
+    /// Workspaces (live or trashed) that still list the repo; the mirror is
+    /// kept for them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub used_by: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DiskUsageEntry {
+    /// `mirror`, `workspace` or `trash`.
+    pub kind: String,
+    pub name: String,
+    /// Bytes in files with a single link.
+    pub unique_bytes: u64,
+    /// Bytes in files hardlinked elsewhere (mirror objects shared with clones).
+    pub shared_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DoctorOutput {
+    /// Whether fixes were applied (`--fix`).
+    pub fix: bool,
+    pub findings: Vec<DoctorFinding>,
+}
+
+#[derive(Serialize)]
+pub struct DoctorFinding {
+    pub severity: Severity,
+    /// What the problem is in: `config`, `mirror <identity>`, a workspace
+    /// name, or `<workspace>/<repo dir>`.
+    pub scope: String,
+    pub message: String,
+    /// The repair `--fix` makes; absent when it must be fixed by hand.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+    pub fixed: bool,
+    /// Why the repair failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
 #[derive(Serialize)]
 pub struct MutationOutput {
     pub ok: bool,
     pub message: String,
 }
 
+#[derive(Serialize)]
+pub struct ExportOutput {
+    pub command: String,
+}
+
 #[derive(Serialize)]
 pub struct PathOutput {
     pub path: String,
@@ -254,8 +428,19 @@ pub struct SyncOutput {
     pub branch: String,
     pub dry_run: bool,
     pub repos: Vec<SyncRepoResult>,
+    /// Workspaces this one is stacked on, synced first (root first).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stack: Vec<SyncOutput>,
 }
 
+/// Sync error for a repo whose rebase/merge conflict was left for the user
+/// (`wsp sync --keep-conflicts`).
+pub const SYNC_CONFLICT_KEPT: &str = "conflict left in place";
+
+/// Sync action for a repo whose autostashed changes conflicted when
+/// re-applied (`wsp sync --autostash`).
+pub const SYNC_STASH_POP_CONFLICT: &str = "stash pop conflict";
+
 #[derive(Serialize)]
 pub struct SyncRepoResult {
     pub name: String,
@@ -301,6 +486,32 @@ pub struct PushRepoResult {
     pub branch: String,
 }
 
+#[derive(Serialize)]
+pub struct ExecOutput {
+    pub workspace: String,
+    /// Set when the commands came from a workspace task (`wsp run`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    pub command: String,
+    pub repos: Vec<ExecRepoResult>,
+}
+
+#[derive(Serialize)]
+pub struct ExecRepoResult {
+    pub name: String,
+    pub ok: bool,
+    /// Process exit code; absent if the command could not be started or was killed by a signal.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    /// Captured stdout — empty when output was streamed straight to the terminal.
+    pub stdout: String,
+    /// Captured stderr — empty when output was streamed straight to the terminal.
+    pub stderr: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 // ---------------------------------------------------------------------------
 // Output enum — returned by all command handlers
 // ---------------------------------------------------------------------------
@@ -317,10 +528,18 @@ pub enum Output {
     Fetch(FetchOutput),
     Sync(SyncOutput),
     Push(PushOutput),
+    PinUpdate(PinUpdateOutput),
+    Doctor(DoctorOutput),
+    RepoGc(RepoGcOutput),
+    Prune(PruneOutput),
+    TrashList(TrashListOutput),
+    History(HistoryOutput),
+    Exec(ExecOutput),
     ConfigList(ConfigListOutput),
     ConfigGet(ConfigGetOutput),
     Mutation(MutationOutput),
     Path(PathOutput),
+    Export(ExportOutput),
     None,
 }
 
@@ -343,10 +562,18 @@ pub fn render(output: Output, json: bool) -> Result<()> {
             Output::Fetch(v) => print_json(&v),
             Output::Sync(v) => print_json(&v),
             Output::Push(v) => print_json(&v),
+            Output::PinUpdate(v) => print_json(&v),
+            Output::Doctor(v) => print_json(&v),
+            Output::RepoGc(v) => print_json(&v),
+            Output::Prune(v) => print_json(&v),
+            Output::TrashList(v) => print_json(&v),
+            Output::History(v) => print_json(&v),
+            Output::Exec(v) => print_json(&v),
             Output::ConfigList(v) => print_json(&v),
             Output::ConfigGet(v) => print_json(&v),
             Output::Mutation(v) => print_json(&v),
             Output::Path(v) => print_json(&v),
+            Output::Export(v) => print_json(&v),
         };
     }
     match output {
@@ -362,10 +589,18 @@ pub fn render(output: Output, json: bool) -> Result<()> {
         Output::Fetch(v) => render_fetch_text(v),
         Output::Sync(v) => render_sync_text(v),
         Output::Push(v) => render_push_text(v),
+        Output::PinUpdate(v) => render_pin_update_text(v),
+        Output::Doctor(v) => render_doctor_text(v),
+        Output::RepoGc(v) => render_repo_gc_text(v),
+        Output::Prune(v) => render_prune_text(v),
+        Output::TrashList(v) => render_trash_list_table(v),
+        Output::History(v) => render_history_table(v),
+        Output::Exec(v) => render_exec_text(v),
         Output::ConfigList(v) => render_config_list_text(v),
         Output::ConfigGet(v) => render_config_get_text(v),
         Output::Mutation(v) => render_mutation_text(v),
         Output::Path(v) => render_path_text(v),
+        Output::Export(v) => render_export_text(v),
     }
 }
 
@@ -375,6 +610,22 @@ pub fn exit_code(output: &Output) -> i32 {
         Output::Fetch(v) if v.repos.iter().any(|r| !r.ok) => 1,
         Output::Sync(v) if v.repos.iter().any(|r| !r.ok) => 1,
         Output::Push(v) if v.repos.iter().any(|r| !r.ok) => 1,
+        Output::PinUpdate(v) if v.repos.iter().any(|r| !r.ok) => 1,
+        Output::Doctor(v)
+            if v.findings
+                .iter()
+                .any(|f| f.severity == Severity::Error && !f.fixed) =>
+        {
+            1
+        }
+        Output::Exec(v) if v.repos.iter().any(|r| !r.ok) => 1,
+        Output::Prune(v) if v.workspaces.iter().any(|w| w.error.is_some()) => 1,
+        Output::RepoGc(v)
+            if v.orphans.iter().any(|o| o.error.is_some())
+                || v.usage.iter().any(|u| u.error.is_some()) =>
+        {
+            1
+        }
         _ => 0,
     }
 }
@@ -535,7 +786,12 @@ fn render_fetch_text(v: FetchOutput) -> Result<()> {
     Ok(())
 }
 
-fn render_sync_text(v: SyncOutput) -> Result<()> {
+fn render_sync_text(mut v: SyncOutput) -> Result<()> {
+    for parent in std::mem::take(&mut v.stack) {
+        render_sync_text(parent)?;
+        println!();
+    }
+
     if v.dry_run {
         println!(
             "Workspace: {}  Branch: {}  (dry run)\n",
@@ -583,9 +839,363 @@ fn render_sync_text(v: SyncOutput) -> Result<()> {
         }
     }
 
+    let kept: Vec<&SyncRepoResult> = v
+        .repos
+        .iter()
+        .filter(|r| r.error.as_deref() == Some(SYNC_CONFLICT_KEPT))
+        .collect();
+    if !kept.is_empty() {
+        eprintln!("\nResolve the conflicts and `git add` the files in:");
+        for r in &kept {
+            eprintln!("  {}", r.repo_dir.display());
+        }
+        eprintln!("then run `wsp sync --continue` (or `wsp sync --abort` to roll back).");
+    }
+
+    let unpopped: Vec<&SyncRepoResult> = v
+        .repos
+        .iter()
+        .filter(|r| r.action == SYNC_STASH_POP_CONFLICT)
+        .collect();
+    if !unpopped.is_empty() {
+        eprintln!(
+            "\n{} repo(s) could not re-apply autostashed changes. Resolve the conflicts, then drop the \"wsp sync autostash\" entry:",
+            unpopped.len()
+        );
+        for r in &unpopped {
+            eprintln!("  cd {}", r.repo_dir.display());
+            eprintln!("  git stash list");
+            eprintln!("  git stash drop stash@{{N}}");
+        }
+    }
+
+    Ok(())
+}
+
+fn render_pin_update_text(v: PinUpdateOutput) -> Result<()> {
+    if v.repos.is_empty() {
+        println!("No pinned repos in this workspace.");
+        return Ok(());
+    }
+    let mut table = Table::new(
+        Box::new(std::io::stdout()),
+        vec![
+            "Repository".to_string(),
+            "Pinned".to_string(),
+            "Update".to_string(),
+        ],
+    );
+    for r in &v.repos {
+        table.add_row(vec![
+            r.name.clone(),
+            r.old_ref.clone(),
+            pin_update_detail(r),
+        ])?;
+    }
+    table.render()
+}
+
+fn pin_update_detail(r: &PinUpdateEntry) -> String {
+    const MAX_TAGS: usize = 5;
+    if let Some(ref e) = r.error {
+        return format!("ERROR — {}", e);
+    }
+    if let Some(ref new_ref) = r.new_ref {
+        return format!("updated to {}", new_ref);
+    }
+    if !r.newer_tags.is_empty() {
+        let mut detail = format!(
+            "newer tags: {}",
+            r.newer_tags
+                .iter()
+                .take(MAX_TAGS)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if r.newer_tags.len() > MAX_TAGS {
+            detail.push_str(&format!(" (+{} more)", r.newer_tags.len() - MAX_TAGS));
+        }
+        return detail;
+    }
+    match r.behind {
+        Some(n) if n > 0 => format!("{} new commit(s) on {}", n, r.old_ref),
+        _ => "up to date".to_string(),
+    }
+}
+
+fn render_prune_text(v: PruneOutput) -> Result<()> {
+    if v.workspaces.is_empty() {
+        println!("No workspaces.");
+        return Ok(());
+    }
+    let mut table = Table::new(
+        Box::new(std::io::stdout()),
+        vec![
+            "Workspace".to_string(),
+            "Action".to_string(),
+            "Detail".to_string(),
+        ],
+    );
+    for w in &v.workspaces {
+        let action = if w.error.is_some() {
+            "ERROR"
+        } else if w.removed {
+            "removed"
+        } else if w.safe && v.dry_run {
+            "would remove"
+        } else {
+            "kept"
+        };
+        table.add_row(vec![w.name.clone(), action.to_string(), prune_detail(w)])?;
+    }
+    table.render()?;
+
+    let safe = v.workspaces.iter().filter(|w| w.safe).count();
+    if v.dry_run && safe > 0 {
+        eprintln!(
+            "\n{} workspace(s) can be removed. Run `wsp prune` without --dry-run to remove them.",
+            safe
+        );
+    }
+    Ok(())
+}
+
+fn prune_detail(w: &PruneWorkspaceResult) -> String {
+    if let Some(ref e) = w.error {
+        return e.clone();
+    }
+    if let Some(ref reason) = w.reason {
+        return reason.clone();
+    }
+    w.repos
+        .iter()
+        .map(|r| {
+            let mut s = format!("{}: {}", r.name, verdict_text(r.verdict));
+            if r.fetch_failed {
+                s.push_str(" (fetch failed)");
+            }
+            s
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn verdict_text(v: Verdict) -> &'static str {
+    match v {
+        Verdict::PendingChanges => "pending changes",
+        Verdict::Merged => "merged",
+        Verdict::SquashMerged => "squash merged",
+        Verdict::PushedToRemote => "pushed to remote",
+        Verdict::Unmerged => "unmerged",
+        Verdict::NoBranch => "no branch",
+        Verdict::NoCommits => "no commits",
+        Verdict::UnknownBase => "unknown base",
+    }
+}
+
+fn render_trash_list_table(v: TrashListOutput) -> Result<()> {
+    if v.entries.is_empty() {
+        println!("Trash is empty.");
+        return Ok(());
+    }
+    let mut table = Table::new(
+        Box::new(std::io::stdout()),
+        vec![
+            "Name".to_string(),
+            "Branch".to_string(),
+            "Removed".to_string(),
+            "Reason".to_string(),
+            "ID".to_string(),
+        ],
+    );
+    for e in &v.entries {
+        table.add_row(vec![
+            e.name.clone(),
+            e.branch.clone(),
+            e.removed
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+            e.reason.clone(),
+            e.id.clone(),
+        ])?;
+    }
+    table.render()
+}
+
+fn render_history_table(v: HistoryOutput) -> Result<()> {
+    if v.entries.is_empty() {
+        println!("No history.");
+        return Ok(());
+    }
+    let mut table = Table::new(
+        Box::new(std::io::stdout()),
+        vec![
+            "ID".to_string(),
+            "Time".to_string(),
+            "Workspace".to_string(),
+            "Command".to_string(),
+            "Result".to_string(),
+            "Changes".to_string(),
+        ],
+    );
+    for e in &v.entries {
+        let result = match e.error {
+            Some(ref err) => format!("ERROR — {}", err),
+            None if e.ok => "ok".to_string(),
+            None => "ERROR".to_string(),
+        };
+        table.add_row(vec![
+            e.id.to_string(),
+            e.time
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+            e.workspace.clone().unwrap_or_else(|| "-".to_string()),
+            format!("wsp {}", e.args.join(" ")),
+            result,
+            history_changes(&e.repos),
+        ])?;
+    }
+    table.render()
+}
+
+/// Summarizes moved HEADs: `api abc1234..def5678`, `+` for a new clone and
+/// `-` for a removed one. Repos whose HEAD didn't move are left out.
+fn history_changes(repos: &[HistoryRepo]) -> String {
+    let short = |sha: &str| sha[..sha.len().min(7)].to_string();
+    repos
+        .iter()
+        .filter_map(|r| match (r.before.as_deref(), r.after.as_deref()) {
+            (Some(b), Some(a)) if a != b => Some(format!("{} {}..{}", r.name, short(b), short(a))),
+            (None, Some(a)) => Some(format!("{} +{}", r.name, short(a))),
+            (Some(b), None) => Some(format!("{} -{}", r.name, short(b))),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn render_repo_gc_text(v: RepoGcOutput) -> Result<()> {
+    if !v.orphans.is_empty() {
+        println!("Orphaned mirrors:");
+        for o in &v.orphans {
+            let status = if let Some(ref e) = o.error {
+                format!("ERROR — {}", e)
+            } else if !o.used_by.is_empty() {
+                format!("kept, used by {}", o.used_by.join(", "))
+            } else if o.removed {
+                "removed".to_string()
+            } else {
+                "would remove".to_string()
+            };
+            println!("  {}  {}", o.identity, status);
+        }
+        println!();
+    }
+
+    if v.usage.is_empty() {
+        println!("No mirrors, workspaces or trash.");
+        return Ok(());
+    }
+    let mut table = Table::new(
+        Box::new(std::io::stdout()),
+        vec![
+            "Kind".to_string(),
+            "Name".to_string(),
+            "Unique".to_string(),
+            "Shared".to_string(),
+        ],
+    );
+    for u in &v.usage {
+        table.add_row(vec![
+            u.kind.clone(),
+            u.name.clone(),
+            format_bytes(u.unique_bytes),
+            format_bytes(u.shared_bytes),
+        ])?;
+    }
+    table.render()?;
+    println!(
+        "\nTotal on disk: {} (hardlinked files counted once)",
+        format_bytes(v.total_bytes)
+    );
+
+    for u in v.usage.iter().filter(|u| u.error.is_some()) {
+        eprintln!(
+            "{} {}: {}",
+            u.kind,
+            u.name,
+            u.error.as_deref().unwrap_or("")
+        );
+    }
+    Ok(())
+}
+
+/// Formats a byte count with binary units: `512 B`, `1.5 KiB`, `2.0 GiB`.
+fn format_bytes(n: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if n < 1024 {
+        return format!("{} B", n);
+    }
+    let mut value = n as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+fn render_doctor_text(v: DoctorOutput) -> Result<()> {
+    if v.findings.is_empty() {
+        println!("No problems found.");
+        return Ok(());
+    }
+    let mut table = Table::new(
+        Box::new(std::io::stdout()),
+        vec![
+            "Severity".to_string(),
+            "Where".to_string(),
+            "Problem".to_string(),
+            "Fix".to_string(),
+        ],
+    );
+    for f in &v.findings {
+        let severity = match f.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        table.add_row(vec![
+            severity.to_string(),
+            f.scope.clone(),
+            f.message.clone(),
+            doctor_fix_detail(f),
+        ])?;
+    }
+    table.render()?;
+
+    let fixable = v
+        .findings
+        .iter()
+        .filter(|f| f.fix.is_some() && !f.fixed)
+        .count();
+    if !v.fix && fixable > 0 {
+        eprintln!("\nRun `wsp doctor --fix` to repair {} problem(s).", fixable);
+    }
     Ok(())
 }
 
+fn doctor_fix_detail(f: &DoctorFinding) -> String {
+    match (&f.fix, &f.error) {
+        (None, _) => "manual".to_string(),
+        (Some(fix), Some(e)) => format!("FAILED to {} — {}", fix, e),
+        (Some(fix), None) if f.fixed => format!("fixed: {}", fix),
+        (Some(fix), None) => fix.clone(),
+    }
+}
+
 fn render_push_text(v: PushOutput) -> Result<()> {
     if v.dry_run {
         println!(
@@ -629,6 +1239,24 @@ fn render_push_text(v: PushOutput) -> Result<()> {
     Ok(())
 }
 
+fn render_exec_text(v: ExecOutput) -> Result<()> {
+    // Command output was already streamed while running; only summarize failures.
+    let failed: Vec<&ExecRepoResult> = v.repos.iter().filter(|r| !r.ok).collect();
+    if failed.is_empty() {
+        return Ok(());
+    }
+    eprintln!("{} of {} command(s) failed:", failed.len(), v.repos.len());
+    for r in &failed {
+        let reason = match (&r.error, r.exit_code) {
+            (Some(e), _) => format!("error: {}", e),
+            (None, Some(code)) => format!("exit status {}", code),
+            (None, None) => "terminated by signal".to_string(),
+        };
+        eprintln!("  [{}] {}", r.name, reason);
+    }
+    Ok(())
+}
+
 fn render_config_list_text(v: ConfigListOutput) -> Result<()> {
     if v.entries.is_empty() {
         println!("No config values set.");
@@ -662,6 +1290,11 @@ fn render_path_text(v: PathOutput) -> Result<()> {
     Ok(())
 }
 
+fn render_export_text(v: ExportOutput) -> Result<()> {
+    println!("{}", v.command);
+    Ok(())
+}
+
 fn render_log_text(v: LogOutput) -> Result<()> {
     if v.oneline {
         render_log_oneline(&v.repos)
@@ -1215,6 +1848,7 @@ mod tests {
                         target: "origin/main".into(),
                         strategy: "rebase".into(),
                     }],
+                    stack: vec![],
                 },
                 serde_json::json!({
                     "workspace": "my-ws",
@@ -1244,6 +1878,7 @@ mod tests {
                         target: "origin/main".into(),
                         strategy: "rebase".into(),
                     }],
+                    stack: vec![],
                 },
                 serde_json::json!({
                     "workspace": "my-ws",
@@ -1273,6 +1908,7 @@ mod tests {
                         target: "origin/main".into(),
                         strategy: "rebase".into(),
                     }],
+                    stack: vec![],
                 },
                 serde_json::json!({
                     "workspace": "my-ws",
@@ -1286,6 +1922,34 @@ mod tests {
                     }]
                 }),
             ),
+            (
+                "stacked sync",
+                SyncOutput {
+                    workspace: "part-2".into(),
+                    branch: "part-2".into(),
+                    dry_run: false,
+                    repos: vec![],
+                    stack: vec![SyncOutput {
+                        workspace: "part-1".into(),
+                        branch: "part-1".into(),
+                        dry_run: false,
+                        repos: vec![],
+                        stack: vec![],
+                    }],
+                },
+                serde_json::json!({
+                    "workspace": "part-2",
+                    "branch": "part-2",
+                    "dry_run": false,
+                    "repos": [],
+                    "stack": [{
+                        "workspace": "part-1",
+                        "branch": "part-1",
+                        "dry_run": false,
+                        "repos": []
+                    }]
+                }),
+            ),
         ];
         for (name, output, want) in cases {
             let val = serde_json::to_value(&output).unwrap();
@@ -1415,6 +2079,271 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_json_pin_update() {
+        let entry =
+            |old: &str, new: Option<&str>, tags: &[&str], behind: Option<u32>| PinUpdateEntry {
+                name: "proto".into(),
+                old_ref: old.into(),
+                new_ref: new.map(String::from),
+                newer_tags: tags.iter().map(|t| t.to_string()).collect(),
+                behind,
+                ok: true,
+                error: None,
+            };
+        let cases: Vec<(&str, PinUpdateEntry, serde_json::Value)> = vec![
+            (
+                "newer tags",
+                entry("v1.0", None, &["v1.2", "v1.1"], None),
+                serde_json::json!({"name": "proto", "old_ref": "v1.0", "newer_tags": ["v1.2", "v1.1"], "ok": true}),
+            ),
+            (
+                "updated",
+                entry("v1.0", Some("v1.2"), &[], None),
+                serde_json::json!({"name": "proto", "old_ref": "v1.0", "new_ref": "v1.2", "ok": true}),
+            ),
+            (
+                "branch tip",
+                entry("main", None, &[], Some(3)),
+                serde_json::json!({"name": "proto", "old_ref": "main", "behind": 3, "ok": true}),
+            ),
+        ];
+        for (name, e, want) in cases {
+            let val = serde_json::to_value(PinUpdateOutput { repos: vec![e] }).unwrap();
+            assert_eq!(val, serde_json::json!({"repos": [want]}), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_json_prune() {
+        let out = PruneOutput {
+            dry_run: true,
+            workspaces: vec![
+                PruneWorkspaceResult {
+                    name: "done".into(),
+                    safe: true,
+                    removed: false,
+                    reason: None,
+                    repos: vec![PruneRepoVerdict {
+                        name: "api".into(),
+                        verdict: Verdict::SquashMerged,
+                        fetch_failed: false,
+                    }],
+                    error: None,
+                },
+                PruneWorkspaceResult {
+                    name: "fresh".into(),
+                    safe: false,
+                    removed: false,
+                    reason: Some("created 2026-01-02".into()),
+                    repos: vec![],
+                    error: None,
+                },
+                PruneWorkspaceResult {
+                    name: "wip".into(),
+                    safe: false,
+                    removed: false,
+                    reason: None,
+                    repos: vec![PruneRepoVerdict {
+                        name: "api".into(),
+                        verdict: Verdict::Unmerged,
+                        fetch_failed: true,
+                    }],
+                    error: None,
+                },
+            ],
+        };
+        assert_eq!(
+            prune_detail(&out.workspaces[2]),
+            "api: unmerged (fetch failed)"
+        );
+        assert_eq!(
+            serde_json::to_value(&out).unwrap(),
+            serde_json::json!({
+                "dry_run": true,
+                "workspaces": [
+                    {"name": "done", "safe": true, "removed": false, "repos": [{"name": "api", "verdict": "squash_merged"}]},
+                    {"name": "fresh", "safe": false, "removed": false, "reason": "created 2026-01-02"},
+                    {"name": "wip", "safe": false, "removed": false, "repos": [{"name": "api", "verdict": "unmerged", "fetch_failed": true}]}
+                ]
+            })
+        );
+        assert_eq!(exit_code(&Output::Prune(out)), 0);
+    }
+
+    #[test]
+    fn test_history_changes() {
+        let repo = |name: &str, before: Option<&str>, after: Option<&str>| HistoryRepo {
+            name: name.into(),
+            identity: format!("github.com/acme/{}", name),
+            before: before.map(String::from),
+            after: after.map(String::from),
+        };
+        let repos = vec![
+            repo("api", Some("1111111aaa"), Some("2222222bbb")),
+            repo("web", Some("3333333"), Some("3333333")),
+            repo("new", None, Some("4444444ccc")),
+            repo("old", Some("5555555ddd"), None),
+        ];
+        assert_eq!(
+            history_changes(&repos),
+            "api 1111111..2222222, new +4444444, old -5555555"
+        );
+        assert_eq!(history_changes(&[]), "");
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        let cases = vec![
+            (0, "0 B"),
+            (1023, "1023 B"),
+            (1024, "1.0 KiB"),
+            (1536, "1.5 KiB"),
+            (5 * 1024 * 1024, "5.0 MiB"),
+            (3 * 1024 * 1024 * 1024, "3.0 GiB"),
+            (2048 * 1024 * 1024 * 1024 * 1024, "2048.0 TiB"),
+        ];
+        for (n, want) in cases {
+            assert_eq!(format_bytes(n), want, "{}", n);
+        }
+    }
+
+    #[test]
+    fn test_json_doctor() {
+        let finding = |severity: Severity, fix: Option<&str>, fixed: bool| DoctorFinding {
+            severity,
+            scope: "my-ws/api".into(),
+            message: "missing wsp-mirror remote".into(),
+            fix: fix.map(String::from),
+            fixed,
+            error: None,
+        };
+        let cases: Vec<(&str, DoctorFinding, serde_json::Value, i32)> = vec![
+            (
+                "fixable warning",
+                finding(Severity::Warning, Some("re-add wsp-mirror remote"), false),
+                serde_json::json!({"severity": "warning", "scope": "my-ws/api", "message": "missing wsp-mirror remote", "fix": "re-add wsp-mirror remote", "fixed": false}),
+                0,
+            ),
+            (
+                "manual error",
+                finding(Severity::Error, None, false),
+                serde_json::json!({"severity": "error", "scope": "my-ws/api", "message": "missing wsp-mirror remote", "fixed": false}),
+                1,
+            ),
+            (
+                "fixed error",
+                finding(Severity::Error, Some("re-add wsp-mirror remote"), true),
+                serde_json::json!({"severity": "error", "scope": "my-ws/api", "message": "missing wsp-mirror remote", "fix": "re-add wsp-mirror remote", "fixed": true}),
+                0,
+            ),
+        ];
+        for (name, f, want, code) in cases {
+            let out = DoctorOutput {
+                fix: false,
+                findings: vec![f],
+            };
+            let val = serde_json::to_value(&out).unwrap();
+            assert_eq!(
+                val,
+                serde_json::json!({"fix": false, "findings": [want]}),
+                "{}",
+                name
+            );
+            assert_eq!(exit_code(&Output::Doctor(out)), code, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_json_exec() {
+        let cases: Vec<(&str, ExecOutput, serde_json::Value)> = vec![
+            (
+                "success and failure",
+                ExecOutput {
+                    workspace: "my-ws".into(),
+                    task: None,
+                    command: "make test".into(),
+                    repos: vec![
+                        ExecRepoResult {
+                            name: "api-gateway".into(),
+                            ok: true,
+                            exit_code: Some(0),
+                            duration_ms: 1200,
+                            stdout: "ok\n".into(),
+                            stderr: String::new(),
+                            error: None,
+                        },
+                        ExecRepoResult {
+                            name: "user-service".into(),
+                            ok: false,
+                            exit_code: Some(2),
+                            duration_ms: 800,
+                            stdout: String::new(),
+                            stderr: "FAIL\n".into(),
+                            error: None,
+                        },
+                    ],
+                },
+                serde_json::json!({
+                    "workspace": "my-ws",
+                    "command": "make test",
+                    "repos": [
+                        {
+                            "name": "api-gateway",
+                            "ok": true,
+                            "exit_code": 0,
+                            "duration_ms": 1200,
+                            "stdout": "ok\n",
+                            "stderr": ""
+                        },
+                        {
+                            "name": "user-service",
+                            "ok": false,
+                            "exit_code": 2,
+                            "duration_ms": 800,
+                            "stdout": "",
+                            "stderr": "FAIL\n"
+                        }
+                    ]
+                }),
+            ),
+            (
+                "spawn error",
+                ExecOutput {
+                    workspace: "my-ws".into(),
+                    task: Some("build".into()),
+                    command: "nope".into(),
+                    repos: vec![ExecRepoResult {
+                        name: "api-gateway".into(),
+                        ok: false,
+                        exit_code: None,
+                        duration_ms: 0,
+                        stdout: String::new(),
+                        stderr: String::new(),
+                        error: Some("No such file or directory".into()),
+                    }],
+                },
+                serde_json::json!({
+                    "workspace": "my-ws",
+                    "task": "build",
+                    "command": "nope",
+                    "repos": [{
+                        "name": "api-gateway",
+                        "ok": false,
+                        "duration_ms": 0,
+                        "stdout": "",
+                        "stderr": "",
+                        "error": "No such file or directory"
+                    }]
+                }),
+            ),
+        ];
+        for (name, output, want) in cases {
+            let val = serde_json::to_value(&output).unwrap();
+            assert_eq!(val, want, "{}", name);
+        }
+    }
+
     #[test]
     fn test_format_relative_time() {
         let now = 1700000000i64;
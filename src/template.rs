@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::lockfile;
+use crate::workspace::{Metadata, Task, WorkspaceRepoRef};
+
+pub const TEMPLATE_SUFFIX: &str = ".wsp-template.yaml";
+
+/// A shareable recipe for a workspace, written by `wsp export -o` and read by
+/// `wsp new --from`.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct WorkspaceTemplate {
+    /// Workspace name. `{name}` is replaced by the name given to `wsp new`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Workspace to stack on, as with `wsp new --on`. It must exist where the
+    /// template is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Repos as `wsp new` takes them: `repo`, `repo@ref` or `repo^base`.
+    pub repos: Vec<String>,
+    /// Clone URLs keyed by identity, used to register repos that aren't yet.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub urls: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tasks: BTreeMap<String, Task>,
+}
+
+impl WorkspaceTemplate {
+    /// Captures a workspace's repos (by full identity), base, parent and
+    /// tasks.
+    pub fn from_workspace(meta: &Metadata, urls: BTreeMap<String, String>) -> Self {
+        WorkspaceTemplate {
+            name: meta.name.clone(),
+            base: meta.base.clone(),
+            parent: meta.parent.clone(),
+            repos: meta
+                .repos
+                .iter()
+                .map(|(id, entry)| repo_spec(id, entry.as_ref()))
+                .collect(),
+            urls,
+            tasks: meta.tasks.clone(),
+        }
+    }
+
+    /// Resolves the workspace name from the template's pattern and the name
+    /// given on the command line, if any.
+    pub fn workspace_name(&self, arg: Option<&str>) -> Result<String> {
+        match arg {
+            Some(a) if self.name.contains("{name}") => Ok(self.name.replace("{name}", a)),
+            Some(a) => Ok(a.to_string()),
+            None if self.name.contains("{name}") => {
+                bail!("template name {:?} needs a workspace name", self.name)
+            }
+            None if self.name.is_empty() => bail!("template has no name; pass a workspace name"),
+            None => Ok(self.name.clone()),
+        }
+    }
+}
+
+/// Formats a repo as a `wsp new` argument: `name`, `name@ref` or `name^base`.
+fn repo_spec(name: &str, entry: Option<&WorkspaceRepoRef>) -> String {
+    match entry {
+        Some(re) if !re.r#ref.is_empty() => format!("{}@{}", name, re.r#ref),
+        Some(re) if !re.base.is_empty() => format!("{}^{}", name, re.base),
+        _ => name.to_string(),
+    }
+}
+
+/// The `wsp new` command that recreates a workspace, naming repos by
+/// `shortnames` where one is known.
+pub fn new_command(meta: &Metadata, shortnames: &HashMap<String, String>) -> String {
+    let mut cmd = format!("wsp new {}", meta.name);
+    for (id, entry) in &meta.repos {
+        let name = shortnames.get(id).map(|s| s.as_str()).unwrap_or(id);
+        cmd.push(' ');
+        cmd.push_str(&repo_spec(name, entry.as_ref()));
+    }
+    if let Some(parent) = &meta.parent {
+        cmd.push_str(&format!(" --on {}", parent));
+    } else if let Some(base) = &meta.base {
+        cmd.push_str(&format!(" --base {}", base));
+    }
+    cmd
+}
+
+/// Reads a template from a path, a `file://` URL, or stdin for `-`.
+pub fn read(source: &str) -> Result<WorkspaceTemplate> {
+    let data = if source == "-" {
+        let mut data = String::new();
+        std::io::stdin()
+            .read_to_string(&mut data)
+            .context("reading template from stdin")?;
+        data
+    } else {
+        let path = source.strip_prefix("file://").unwrap_or(source);
+        fs::read_to_string(path).map_err(|e| anyhow::anyhow!("reading {}: {}", path, e))?
+    };
+    parse(&data)
+}
+
+pub fn parse(data: &str) -> Result<WorkspaceTemplate> {
+    let t: WorkspaceTemplate =
+        serde_yaml_ng::from_str(data).map_err(|e| anyhow::anyhow!("parsing template: {}", e))?;
+    if t.repos.is_empty() {
+        bail!("template has no repos");
+    }
+    Ok(t)
+}
+
+pub fn save(path: &Path, t: &WorkspaceTemplate) -> Result<()> {
+    lockfile::save_yaml(path, t, "template")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn meta() -> Metadata {
+        Metadata {
+            name: "add-billing".into(),
+            branch: "me/add-billing".into(),
+            repos: BTreeMap::from([
+                ("github.com/acme/api-gateway".into(), None),
+                (
+                    "github.com/acme/proto".into(),
+                    Some(WorkspaceRepoRef {
+                        r#ref: "v1.0".into(),
+                        base: String::new(),
+                    }),
+                ),
+                (
+                    "github.com/acme/user-service".into(),
+                    Some(WorkspaceRepoRef {
+                        r#ref: String::new(),
+                        base: "release/2.3".into(),
+                    }),
+                ),
+            ]),
+            created: Utc::now(),
+            base: Some("develop".into()),
+            parent: None,
+            dirs: BTreeMap::new(),
+            tasks: BTreeMap::from([(
+                "test".into(),
+                Task {
+                    command: "make test".into(),
+                    ..Task::default()
+                },
+            )]),
+        }
+    }
+
+    #[test]
+    fn test_new_command() {
+        let shortnames = HashMap::from([
+            (
+                "github.com/acme/api-gateway".to_string(),
+                "api-gateway".to_string(),
+            ),
+            ("github.com/acme/proto".to_string(), "proto".to_string()),
+        ]);
+        assert_eq!(
+            new_command(&meta(), &shortnames),
+            "wsp new add-billing api-gateway proto@v1.0 \
+             github.com/acme/user-service^release/2.3 --base develop"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let urls = BTreeMap::from([(
+            "github.com/acme/proto".to_string(),
+            "git@github.com:acme/proto.git".to_string(),
+        )]);
+        let t = WorkspaceTemplate::from_workspace(&meta(), urls);
+        assert_eq!(
+            t.repos,
+            vec![
+                "github.com/acme/api-gateway",
+                "github.com/acme/proto@v1.0",
+                "github.com/acme/user-service^release/2.3",
+            ]
+        );
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join(format!("add-billing{}", TEMPLATE_SUFFIX));
+        save(&path, &t).unwrap();
+        assert_eq!(read(path.to_str().unwrap()).unwrap(), t);
+        assert_eq!(read(&format!("file://{}", path.display())).unwrap(), t);
+    }
+
+    #[test]
+    fn test_from_stacked_workspace() {
+        let mut stacked = meta();
+        stacked.parent = Some("billing-base".into());
+        let t = WorkspaceTemplate::from_workspace(&stacked, BTreeMap::new());
+        assert_eq!(t.parent.as_deref(), Some("billing-base"));
+
+        let yaml = serde_yaml_ng::to_string(&t).unwrap();
+        assert!(yaml.contains("parent: billing-base"), "{}", yaml);
+        assert_eq!(parse(&yaml).unwrap(), t);
+        assert!(
+            !serde_yaml_ng::to_string(&WorkspaceTemplate::from_workspace(&meta(), BTreeMap::new()))
+                .unwrap()
+                .contains("parent:")
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let t = parse("name: billing-{name}\nrepos:\n  - api-gateway\n  - proto@v1.0\n").unwrap();
+        assert_eq!(t.repos, vec!["api-gateway", "proto@v1.0"]);
+        assert!(t.urls.is_empty());
+
+        assert!(
+            parse("name: x\nrepos: []\n")
+                .unwrap_err()
+                .to_string()
+                .contains("no repos")
+        );
+        assert!(
+            parse("repos: {")
+                .unwrap_err()
+                .to_string()
+                .contains("parsing template")
+        );
+    }
+
+    #[test]
+    fn test_workspace_name() {
+        let cases: Vec<(&str, Option<&str>, Result<&str, &str>)> = vec![
+            ("billing", None, Ok("billing")),
+            ("billing", Some("other"), Ok("other")),
+            ("billing-{name}", Some("tax"), Ok("billing-tax")),
+            ("billing-{name}", None, Err("needs a workspace name")),
+            ("", None, Err("has no name")),
+            ("", Some("adhoc"), Ok("adhoc")),
+        ];
+        for (pattern, arg, want) in cases {
+            let t = WorkspaceTemplate {
+                name: pattern.into(),
+                ..WorkspaceTemplate::default()
+            };
+            match (t.workspace_name(arg), want) {
+                (Ok(got), Ok(w)) => assert_eq!(got, w, "{:?} {:?}", pattern, arg),
+                (Err(e), Err(w)) => assert!(e.to_string().contains(w), "{}", e),
+                (got, want) => panic!("{:?} {:?}: got {:?}, want {:?}", pattern, arg, got, want),
+            }
+        }
+    }
+}
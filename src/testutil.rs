@@ -31,6 +31,14 @@ pub fn setup_clone_repo() -> (PathBuf, PathBuf, tempfile::TempDir, tempfile::Tem
 
     let clone_tmp = tempfile::tempdir().unwrap();
     let clone_dir = clone_tmp.path().join("repo");
+    clone_source(&source, &clone_dir);
+
+    (clone_dir, source, clone_tmp, source_tmp)
+}
+
+/// Clones `source` into `clone_dir` and checks out a `feature` branch from
+/// origin/main, configured for committing.
+pub fn clone_source(source: &Path, clone_dir: &Path) {
     let out = Command::new("git")
         .args([
             "clone",
@@ -53,7 +61,7 @@ pub fn setup_clone_repo() -> (PathBuf, PathBuf, tempfile::TempDir, tempfile::Tem
     ] {
         let out = Command::new(args[0])
             .args(&args[1..])
-            .current_dir(&clone_dir)
+            .current_dir(clone_dir)
             .output()
             .unwrap();
         assert!(out.status.success());
@@ -62,12 +70,10 @@ pub fn setup_clone_repo() -> (PathBuf, PathBuf, tempfile::TempDir, tempfile::Tem
     // Create a feature branch from main
     let out = Command::new("git")
         .args(["checkout", "-b", "feature", "--no-track", "origin/main"])
-        .current_dir(&clone_dir)
+        .current_dir(clone_dir)
         .output()
         .unwrap();
     assert!(out.status.success());
-
-    (clone_dir, source, clone_tmp, source_tmp)
 }
 
 /// Commits a file in a repo on the current branch.
@@ -0,0 +1,331 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, Paths};
+use crate::workspace::{self, Metadata};
+
+pub const MANIFEST_FILE: &str = "manifest.yaml";
+
+/// Subdirectory of a trash entry holding the workspace itself.
+const CONTENT_DIR: &str = "workspace";
+
+/// Why and when a workspace was moved to the trash, stored next to it.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Manifest {
+    pub name: String,
+    pub branch: String,
+    /// Where the workspace lived.
+    pub path: PathBuf,
+    pub removed: DateTime<Utc>,
+    /// The command that removed it, e.g. `wsp rm --force`.
+    pub reason: String,
+}
+
+pub struct Entry {
+    /// Directory name under the trash dir: `<name>-<timestamp>`.
+    pub id: String,
+    pub dir: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Entry {
+    /// The trashed workspace directory itself.
+    pub fn content_dir(&self) -> PathBuf {
+        self.dir.join(CONTENT_DIR)
+    }
+}
+
+/// Moves a workspace into the trash and returns its entry id.
+pub fn put(trash_dir: &Path, ws_dir: &Path, meta: &Metadata, reason: &str) -> Result<String> {
+    fs::create_dir_all(trash_dir)
+        .map_err(|e| anyhow::anyhow!("creating {}: {}", trash_dir.display(), e))?;
+
+    let removed = Utc::now();
+    let stem = format!("{}-{}", meta.name, removed.format("%Y%m%dT%H%M%S"));
+    let mut id = stem.clone();
+    let mut n = 1;
+    while trash_dir.join(&id).exists() {
+        n += 1;
+        id = format!("{}-{}", stem, n);
+    }
+    let entry_dir = trash_dir.join(&id);
+    fs::create_dir(&entry_dir)?;
+
+    let manifest = Manifest {
+        name: meta.name.clone(),
+        branch: meta.branch.clone(),
+        path: ws_dir.to_path_buf(),
+        removed,
+        reason: reason.to_string(),
+    };
+    let result = save_manifest(&entry_dir, &manifest)
+        .and_then(|()| move_dir(ws_dir, &entry_dir.join(CONTENT_DIR)));
+    if let Err(e) = result {
+        let _ = fs::remove_dir_all(&entry_dir);
+        bail!("moving workspace to trash: {}", e);
+    }
+    Ok(id)
+}
+
+/// Lists trash entries, newest first. Entries without a readable manifest are
+/// skipped with a warning.
+pub fn list(trash_dir: &Path) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    if !trash_dir.exists() {
+        return Ok(entries);
+    }
+    for dirent in fs::read_dir(trash_dir)? {
+        let dirent = dirent?;
+        if !dirent.file_type()?.is_dir() {
+            continue;
+        }
+        let Some(id) = dirent.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        let dir = dirent.path();
+        match load_manifest(&dir) {
+            Ok(manifest) => entries.push(Entry { id, dir, manifest }),
+            Err(e) => eprintln!("warning: skipping trash entry {}: {}", id, e),
+        }
+    }
+    entries.sort_by_key(|e| std::cmp::Reverse(e.manifest.removed));
+    Ok(entries)
+}
+
+/// Finds an entry by id, or the newest one for a workspace name.
+pub fn find(trash_dir: &Path, name_or_id: &str) -> Result<Entry> {
+    let mut entries = list(trash_dir)?;
+    let pos = entries
+        .iter()
+        .position(|e| e.id == name_or_id)
+        .or_else(|| entries.iter().position(|e| e.manifest.name == name_or_id));
+    match pos {
+        Some(i) => Ok(entries.swap_remove(i)),
+        None => bail!("no workspace {:?} in the trash", name_or_id),
+    }
+}
+
+/// Moves a trashed workspace back into the workspaces dir. Returns the
+/// workspace's new directory.
+pub fn restore(paths: &Paths, entry: &Entry) -> Result<PathBuf> {
+    let name = &entry.manifest.name;
+    workspace::validate_name(name)?;
+
+    let content = entry.content_dir();
+    let meta = workspace::load_metadata(&content)
+        .map_err(|e| anyhow::anyhow!("reading trashed workspace: {}", e))?;
+    if &meta.name != name {
+        bail!(
+            "trashed workspace is named {:?} but its manifest says {:?}",
+            meta.name,
+            name
+        );
+    }
+
+    let ws_dir = workspace::dir(&paths.workspaces_dir, name);
+    if ws_dir.exists() {
+        bail!(
+            "workspace {:?} already exists; remove or rename it first (`wsp rename {} <new>`)",
+            name,
+            name
+        );
+    }
+    fs::create_dir_all(&paths.workspaces_dir)?;
+    move_dir(&content, &ws_dir)?;
+    fs::remove_dir_all(&entry.dir)
+        .map_err(|e| anyhow::anyhow!("removing trash entry {}: {}", entry.id, e))?;
+    Ok(ws_dir)
+}
+
+/// Permanently deletes entries removed more than `older_than` ago, or all of
+/// them. Returns the deleted entries.
+pub fn purge(trash_dir: &Path, older_than: Option<Duration>) -> Result<Vec<Entry>> {
+    let now = Utc::now();
+    let mut purged = Vec::new();
+    for entry in list(trash_dir)? {
+        if let Some(age) = older_than
+            && now - entry.manifest.removed < age
+        {
+            continue;
+        }
+        fs::remove_dir_all(&entry.dir)
+            .map_err(|e| anyhow::anyhow!("deleting trash entry {}: {}", entry.id, e))?;
+        purged.push(entry);
+    }
+    Ok(purged)
+}
+
+/// Applies the configured `trash-retention`, best-effort.
+pub fn expire(paths: &Paths) {
+    let retention = Config::load_from(&paths.config_path).and_then(|cfg| cfg.trash_retention());
+    match retention {
+        Ok(Some(age)) => {
+            if let Err(e) = purge(&paths.trash_dir, Some(age)) {
+                eprintln!("warning: emptying old trash: {}", e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("warning: skipping trash retention: {}", e),
+    }
+}
+
+fn load_manifest(entry_dir: &Path) -> Result<Manifest> {
+    let data = fs::read_to_string(entry_dir.join(MANIFEST_FILE))
+        .map_err(|e| anyhow::anyhow!("reading {}: {}", MANIFEST_FILE, e))?;
+    serde_yaml_ng::from_str(&data).map_err(|e| anyhow::anyhow!("parsing {}: {}", MANIFEST_FILE, e))
+}
+
+fn save_manifest(entry_dir: &Path, manifest: &Manifest) -> Result<()> {
+    let data = serde_yaml_ng::to_string(manifest)?;
+    let mut tmp =
+        tempfile::NamedTempFile::new_in(entry_dir).context("creating temp file for atomic save")?;
+    tmp.write_all(data.as_bytes())
+        .context("writing manifest to temp file")?;
+    tmp.persist(entry_dir.join(MANIFEST_FILE))
+        .context("renaming temp file to manifest")?;
+    Ok(())
+}
+
+/// Renames `from` to `to`, copying and deleting instead when they are on
+/// different filesystems (the trash lives in the data dir, workspaces may not).
+fn move_dir(from: &Path, to: &Path) -> Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            if let Err(e) = copy_dir(from, to) {
+                let _ = fs::remove_dir_all(to);
+                bail!("copying {} to {}: {}", from.display(), to.display(), e);
+            }
+            fs::remove_dir_all(from)
+                .map_err(|e| anyhow::anyhow!("removing {}: {}", from.display(), e))
+        }
+        Err(e) => bail!("moving {} to {}: {}", from.display(), to.display(), e),
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let (src, dst) = (entry.path(), to.join(entry.file_name()));
+        let ft = entry.file_type()?;
+        if ft.is_dir() {
+            copy_dir(&src, &dst)?;
+        } else if ft.is_symlink() {
+            copy_symlink(&src, &dst)?;
+        } else {
+            fs::copy(&src, &dst)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(src: &Path, dst: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(src)?, dst)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(src: &Path, dst: &Path) -> io::Result<()> {
+    fs::copy(src, dst).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn make_workspace(paths: &Paths, name: &str) -> (PathBuf, Metadata) {
+        let ws_dir = workspace::dir(&paths.workspaces_dir, name);
+        fs::create_dir_all(ws_dir.join("api")).unwrap();
+        fs::write(ws_dir.join("api").join("work.txt"), "unpushed").unwrap();
+        let meta = Metadata {
+            name: name.into(),
+            branch: format!("me/{}", name),
+            repos: BTreeMap::new(),
+            created: Utc::now(),
+            base: None,
+            parent: None,
+            dirs: BTreeMap::new(),
+            tasks: BTreeMap::new(),
+        };
+        workspace::save_metadata(&ws_dir, &meta).unwrap();
+        (ws_dir, meta)
+    }
+
+    #[test]
+    fn test_put_list_restore() {
+        let tmp = tempfile::tempdir().unwrap();
+        let paths = Paths::from_dirs(&tmp.path().join("data"), &tmp.path().join("ws"));
+        let (ws_dir, meta) = make_workspace(&paths, "bug-1");
+
+        let id = put(&paths.trash_dir, &ws_dir, &meta, "wsp rm --force").unwrap();
+        assert!(!ws_dir.exists());
+        assert!(id.starts_with("bug-1-"), "{}", id);
+
+        let entries = list(&paths.trash_dir).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, id);
+        assert_eq!(entries[0].manifest.reason, "wsp rm --force");
+        assert_eq!(entries[0].manifest.path, ws_dir);
+
+        // A new workspace took the name: restoring must not clobber it.
+        make_workspace(&paths, "bug-1");
+        let entry = find(&paths.trash_dir, "bug-1").unwrap();
+        let err = restore(&paths, &entry).unwrap_err().to_string();
+        assert!(err.contains("already exists"), "{}", err);
+        fs::remove_dir_all(&ws_dir).unwrap();
+
+        let entry = find(&paths.trash_dir, &id).unwrap();
+        assert_eq!(restore(&paths, &entry).unwrap(), ws_dir);
+        assert_eq!(
+            fs::read_to_string(ws_dir.join("api").join("work.txt")).unwrap(),
+            "unpushed"
+        );
+        assert!(list(&paths.trash_dir).unwrap().is_empty());
+        assert!(find(&paths.trash_dir, "bug-1").is_err());
+    }
+
+    #[test]
+    fn test_restore_rejects_bad_name() {
+        let tmp = tempfile::tempdir().unwrap();
+        let paths = Paths::from_dirs(&tmp.path().join("data"), &tmp.path().join("ws"));
+        let (ws_dir, meta) = make_workspace(&paths, "ok");
+        put(&paths.trash_dir, &ws_dir, &meta, "wsp rm").unwrap();
+
+        let mut entry = find(&paths.trash_dir, "ok").unwrap();
+        entry.manifest.name = "../escape".into();
+        let err = restore(&paths, &entry).unwrap_err().to_string();
+        assert!(err.contains("path separators"), "{}", err);
+    }
+
+    #[test]
+    fn test_purge() {
+        let tmp = tempfile::tempdir().unwrap();
+        let paths = Paths::from_dirs(&tmp.path().join("data"), &tmp.path().join("ws"));
+        for name in ["old", "new"] {
+            let (ws_dir, meta) = make_workspace(&paths, name);
+            put(&paths.trash_dir, &ws_dir, &meta, "wsp rm").unwrap();
+        }
+        // Backdate one entry.
+        let mut old = find(&paths.trash_dir, "old").unwrap();
+        old.manifest.removed = Utc::now() - Duration::days(40);
+        save_manifest(&old.dir, &old.manifest).unwrap();
+
+        let purged = purge(&paths.trash_dir, Some(Duration::days(30))).unwrap();
+        assert_eq!(
+            purged
+                .iter()
+                .map(|e| e.manifest.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["old"]
+        );
+        assert_eq!(purge(&paths.trash_dir, None).unwrap().len(), 1);
+        assert!(list(&paths.trash_dir).unwrap().is_empty());
+    }
+}
@@ -11,11 +11,16 @@ use crate::config::Paths;
 use crate::git;
 use crate::giturl;
 use crate::mirror;
+use crate::trash;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct WorkspaceRepoRef {
     #[serde(skip_serializing_if = "String::is_empty", default)]
     pub r#ref: String,
+    /// Branch the workspace branch starts from and merges into, overriding
+    /// the workspace base. Only meaningful for active repos.
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub base: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,8 +29,34 @@ pub struct Metadata {
     pub branch: String,
     pub repos: BTreeMap<String, Option<WorkspaceRepoRef>>,
     pub created: DateTime<Utc>,
+    /// Branch active repos start from and merge into. None means the
+    /// remote's default branch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Workspace this one is stacked on. Its active repos branch from, and
+    /// merge into, the parent's workspace branch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub dirs: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tasks: BTreeMap<String, Task>,
+}
+
+/// A named command run across repos with `wsp run <task>`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Task {
+    /// Shell command run in each repo (via `sh -c`).
+    pub command: String,
+    /// Per-repo replacement commands, keyed by repo name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, String>,
+    /// Repos the task runs in. Empty means all repos.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repos: Vec<String>,
+    /// Repos that must finish first, in this order, before the rest run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
 }
 
 impl Metadata {
@@ -38,6 +69,184 @@ impl Metadata {
         let parsed = parse_identity(identity)?;
         Ok(parsed.repo)
     }
+
+    /// Returns the base branch for an active repo: its own override, else the
+    /// workspace base. None means the remote's default branch.
+    pub fn base_branch(&self, identity: &str) -> Option<&str> {
+        match self.repos.get(identity) {
+            Some(Some(re)) if !re.base.is_empty() => Some(re.base.as_str()),
+            _ => self.base.as_deref(),
+        }
+    }
+
+    /// Returns true if `identity` is in the workspace and not pinned to a ref.
+    pub fn is_active(&self, identity: &str) -> bool {
+        match self.repos.get(identity) {
+            Some(None) => true,
+            Some(Some(re)) => re.r#ref.is_empty(),
+            None => false,
+        }
+    }
+
+    /// Loads the workspace this one is stacked on. None if it isn't stacked
+    /// or the parent workspace no longer exists.
+    pub fn load_parent(&self, ws_dir: &Path) -> Option<Parent> {
+        let name = self.parent.as_deref()?;
+        load_sibling(ws_dir, name).ok()
+    }
+
+    /// Returns the ref an active repo is compared against: the parent's
+    /// workspace branch for stacked repos, else origin/<base>. None means
+    /// origin/<default>.
+    pub fn base_ref(&self, identity: &str, parent: Option<&Parent>) -> Option<String> {
+        if let Some(p) = parent
+            && self.is_active(identity)
+            && p.meta.is_active(identity)
+        {
+            return Some(format!("{}/{}", PARENT_REMOTE, p.meta.branch));
+        }
+        self.base_branch(identity).map(|b| format!("origin/{}", b))
+    }
+}
+
+/// A stacked workspace's parent, which lives next to it.
+pub struct Parent {
+    pub dir: PathBuf,
+    pub meta: Metadata,
+}
+
+impl Parent {
+    /// Returns the parent's clone of `identity` if it's an active repo there.
+    pub fn clone_dir(&self, identity: &str) -> Option<PathBuf> {
+        if !self.meta.is_active(identity) {
+            return None;
+        }
+        let dn = self.meta.dir_name(identity).ok()?;
+        Some(self.dir.join(dn))
+    }
+}
+
+fn load_sibling(ws_dir: &Path, name: &str) -> Result<Parent> {
+    let dir = ws_dir
+        .parent()
+        .map(|p| p.join(name))
+        .ok_or_else(|| anyhow::anyhow!("workspace {:?} not found", name))?;
+    let meta =
+        load_metadata(&dir).map_err(|e| anyhow::anyhow!("reading workspace {:?}: {}", name, e))?;
+    Ok(Parent { dir, meta })
+}
+
+/// Returns the workspaces `meta` is stacked on, root first. The chain ends at
+/// a parent that no longer exists.
+pub fn ancestors(ws_dir: &Path, meta: &Metadata) -> Result<Vec<Parent>> {
+    let mut chain: Vec<Parent> = Vec::new();
+    let mut next = meta.load_parent(ws_dir);
+    while let Some(p) = next {
+        if p.meta.name == meta.name || chain.iter().any(|c| c.meta.name == p.meta.name) {
+            bail!("workspace {:?} is stacked on itself", meta.name);
+        }
+        next = p.meta.load_parent(&p.dir);
+        chain.push(p);
+    }
+    chain.reverse();
+    Ok(chain)
+}
+
+/// Remote in a stacked workspace's clones that points at the parent
+/// workspace's clone.
+pub const PARENT_REMOTE: &str = "wsp-parent";
+
+/// Points a stacked clone's parent remote at the parent workspace's clone and
+/// fetches it, so `wsp-parent/<branch>` is current.
+pub fn fetch_parent(clone_dir: &Path, parent_clone: &Path) -> Result<()> {
+    git::remote_set_url(clone_dir, PARENT_REMOTE, &parent_clone.to_string_lossy())?;
+    git::fetch_remote_prune(clone_dir, PARENT_REMOTE)
+}
+
+/// Where a new workspace's active repos branch from.
+#[derive(Debug, Clone, Default)]
+pub struct Bases {
+    /// Applies to every active repo without an entry in `repos`.
+    pub default: Option<String>,
+    /// Per-repo overrides, keyed by identity.
+    pub repos: BTreeMap<String, String>,
+    /// Workspace to stack on. Repos active there branch from its branch.
+    pub parent: Option<String>,
+    /// Exact commits to start the workspace branch at instead of the base's
+    /// tip, keyed by identity (`wsp new --from-lock`).
+    pub commits: BTreeMap<String, String>,
+}
+
+/// What a new clone checks out.
+struct Checkout {
+    /// Pinned ref for context repos; empty for active repos.
+    git_ref: String,
+    /// Mirror branch the workspace branch starts from; empty means the
+    /// default branch.
+    base: String,
+    /// Parent workspace clone and branch to start from instead (stacked repos).
+    parent: Option<(PathBuf, String)>,
+    /// Commit to start the workspace branch at; empty means the tip of the
+    /// base (or parent) branch.
+    commit: String,
+}
+
+/// Resolves the metadata entry and checkout for a repo being added.
+fn plan_checkout(
+    identity: &str,
+    git_ref: &str,
+    base: &str,
+    default_base: Option<&str>,
+    parent: Option<&Parent>,
+) -> Result<(Option<WorkspaceRepoRef>, Checkout)> {
+    if !git_ref.is_empty() && !base.is_empty() {
+        bail!(
+            "repo {} is pinned to {}, a base branch only applies to active repos",
+            identity,
+            git_ref
+        );
+    }
+
+    let mut base = base.to_string();
+    let mut stacked_on = None;
+    if let Some(p) = parent
+        && git_ref.is_empty()
+        && let Some(parent_clone) = p.clone_dir(identity)
+    {
+        if !base.is_empty() {
+            bail!(
+                "repo {} is stacked on workspace {:?}, it can't have its own base",
+                identity,
+                p.meta.name
+            );
+        }
+        // Keep the parent's base so the repo falls back to it once the parent
+        // workspace is gone.
+        if let Some(Some(re)) = p.meta.repos.get(identity) {
+            base = re.base.clone();
+        }
+        stacked_on = Some((parent_clone, p.meta.branch.clone()));
+    }
+
+    let entry = if git_ref.is_empty() && base.is_empty() {
+        None
+    } else {
+        Some(WorkspaceRepoRef {
+            r#ref: git_ref.to_string(),
+            base: base.clone(),
+        })
+    };
+    let checkout = Checkout {
+        git_ref: git_ref.to_string(),
+        base: if base.is_empty() {
+            default_base.unwrap_or_default().to_string()
+        } else {
+            base
+        },
+        parent: stacked_on,
+        commit: String::new(),
+    };
+    Ok((entry, checkout))
 }
 
 /// Detects repo-name collisions and returns a dirs map with `owner-repo` entries
@@ -146,6 +355,7 @@ pub fn create(
     paths: &Paths,
     name: &str,
     repo_refs: &BTreeMap<String, String>,
+    bases: &Bases,
     branch_prefix: Option<&str>,
     upstream_urls: &BTreeMap<String, String>,
 ) -> Result<()> {
@@ -169,6 +379,7 @@ pub fn create(
         &ws_dir,
         name,
         repo_refs,
+        bases,
         upstream_urls,
     ) {
         Ok(()) => Ok(()),
@@ -186,18 +397,30 @@ fn create_inner(
     ws_dir: &Path,
     name: &str,
     repo_refs: &BTreeMap<String, String>,
+    bases: &Bases,
     upstream_urls: &BTreeMap<String, String>,
 ) -> Result<()> {
+    let parent = match &bases.parent {
+        Some(p) => Some(load_sibling(ws_dir, p)?),
+        None => None,
+    };
+    let default_base = bases
+        .default
+        .clone()
+        .filter(|b| !b.is_empty())
+        .or_else(|| parent.as_ref().and_then(|p| p.meta.base.clone()));
+
     let mut repos: BTreeMap<String, Option<WorkspaceRepoRef>> = BTreeMap::new();
+    let mut checkouts: BTreeMap<&String, Checkout> = BTreeMap::new();
     for (identity, r) in repo_refs {
-        if r.is_empty() {
-            repos.insert(identity.clone(), None);
-        } else {
-            repos.insert(
-                identity.clone(),
-                Some(WorkspaceRepoRef { r#ref: r.clone() }),
-            );
+        let base = bases.repos.get(identity).map(|s| s.as_str()).unwrap_or("");
+        let (entry, mut checkout) =
+            plan_checkout(identity, r, base, default_base.as_deref(), parent.as_ref())?;
+        if let Some(commit) = bases.commits.get(identity) {
+            checkout.commit = commit.clone();
         }
+        repos.insert(identity.clone(), entry);
+        checkouts.insert(identity, checkout);
     }
 
     let identities: Vec<&str> = repo_refs.keys().map(|s| s.as_str()).collect();
@@ -208,17 +431,28 @@ fn create_inner(
         branch: branch.to_string(),
         repos,
         created: Utc::now(),
+        base: default_base,
+        parent: bases.parent.clone(),
         dirs: dirs.clone(),
+        tasks: BTreeMap::new(),
     };
 
-    for (identity, r) in repo_refs {
+    for (identity, checkout) in &checkouts {
         let dn = meta.dir_name(identity)?;
         let upstream = upstream_urls
-            .get(identity)
+            .get(*identity)
             .map(|s| s.as_str())
             .unwrap_or("");
-        clone_from_mirror(mirrors_dir, ws_dir, identity, &dn, branch, r, upstream)
-            .map_err(|e| anyhow::anyhow!("cloning repo {}: {}", identity, e))?;
+        clone_from_mirror(
+            mirrors_dir,
+            ws_dir,
+            identity,
+            &dn,
+            branch,
+            checkout,
+            upstream,
+        )
+        .map_err(|e| anyhow::anyhow!("cloning repo {}: {}", identity, e))?;
     }
 
     save_metadata(ws_dir, &meta)?;
@@ -229,9 +463,11 @@ pub fn add_repos(
     mirrors_dir: &Path,
     ws_dir: &Path,
     repo_refs: &BTreeMap<String, String>,
+    repo_bases: &BTreeMap<String, String>,
     upstream_urls: &BTreeMap<String, String>,
 ) -> Result<()> {
     let mut meta = load_metadata(ws_dir)?;
+    let parent = meta.load_parent(ws_dir);
 
     for (identity, r) in repo_refs {
         if meta.repos.contains_key(identity) {
@@ -239,6 +475,10 @@ pub fn add_repos(
             continue;
         }
 
+        let base = repo_bases.get(identity).map(|s| s.as_str()).unwrap_or("");
+        let (entry, checkout) =
+            plan_checkout(identity, r, base, meta.base.as_deref(), parent.as_ref())?;
+
         let new_parsed = parse_identity(identity)?;
         let new_default_dir = new_parsed.repo.clone();
 
@@ -278,7 +518,7 @@ pub fn add_repos(
                 identity,
                 &new_dir,
                 &meta.branch,
-                r,
+                &checkout,
                 upstream,
             )
             .map_err(|e| anyhow::anyhow!("cloning repo {}: {}", identity, e))?;
@@ -291,20 +531,13 @@ pub fn add_repos(
                 identity,
                 &dn,
                 &meta.branch,
-                r,
+                &checkout,
                 upstream,
             )
             .map_err(|e| anyhow::anyhow!("cloning repo {}: {}", identity, e))?;
         }
 
-        if r.is_empty() {
-            meta.repos.insert(identity.clone(), None);
-        } else {
-            meta.repos.insert(
-                identity.clone(),
-                Some(WorkspaceRepoRef { r#ref: r.clone() }),
-            );
-        }
+        meta.repos.insert(identity.clone(), entry);
     }
 
     save_metadata(ws_dir, &meta)
@@ -322,78 +555,26 @@ pub fn remove_repos(ws_dir: &Path, identities_to_remove: &[String], force: bool)
 
     // Safety check: for active repos, check pending changes + unmerged branches
     if !force {
-        let mut problems: Vec<String> = Vec::new();
-        for identity in identities_to_remove {
-            let entry = &meta.repos[identity];
-            let is_active = match entry {
-                None => true,
-                Some(re) => re.r#ref.is_empty(),
-            };
-            if !is_active {
-                continue;
-            }
-
-            let dn = meta.dir_name(identity)?;
-            let clone_dir = ws_dir.join(&dn);
-
-            let changed = git::changed_file_count(&clone_dir).unwrap_or(0);
-            let ahead = git::ahead_count(&clone_dir).unwrap_or(0);
-            if changed > 0 || ahead > 0 {
-                problems.push(format!("{} (pending changes)", identity));
-                continue;
-            }
-
-            // Fetch origin with prune for up-to-date merge detection
-            let fetch_failed = git::fetch_remote_prune(&clone_dir, "origin").is_err();
-            if fetch_failed {
-                eprintln!("  warning: fetch failed for {}, using local data", identity);
-            }
-
-            if git::branch_exists(&clone_dir, &meta.branch) {
-                let default_branch = git::default_branch_for_remote(&clone_dir, "origin")
-                    .or_else(|_| git::default_branch(&clone_dir))
-                    .unwrap_or_default();
-                if !default_branch.is_empty() {
-                    let merge_target = format!("origin/{}", default_branch);
-                    let target = if git::ref_exists(&clone_dir, &merge_target) {
-                        merge_target
-                    } else {
-                        default_branch
-                    };
-                    match git::branch_safety(&clone_dir, &meta.branch, &target) {
-                        git::BranchSafety::Merged | git::BranchSafety::SquashMerged => {}
-                        git::BranchSafety::PushedToRemote => {
-                            let mut msg =
-                                format!("{} (unmerged branch, but pushed to remote)", identity);
-                            if fetch_failed {
-                                msg.push_str(" (fetch failed, local data may be stale)");
-                            }
-                            problems.push(msg);
-                        }
-                        git::BranchSafety::Unmerged => {
-                            let mut msg = format!("{} (unmerged branch)", identity);
-                            if fetch_failed {
-                                msg.push_str(" (fetch failed, local data may be stale)");
-                            }
-                            problems.push(msg);
-                        }
-                    }
-                }
-            }
-        }
-
+        let problems = removal_problems(ws_dir, &meta, identities_to_remove)?;
         if !problems.is_empty() {
-            let mut list = String::new();
-            for p in &problems {
-                list.push_str(&format!("\n  - {}", p));
-            }
             bail!(
                 "cannot remove repos:{}\n\nUse --force to remove anyway",
-                list
+                problem_list(&problems)
             );
         }
     }
 
+    // Overrides that aren't generated collision names were picked by hand
+    // (e.g. by `wsp adopt`) and stay as they are
+    let all_ids: Vec<&str> = meta.repos.keys().map(|s| s.as_str()).collect();
+    let generated = compute_dir_names(&all_ids)?;
+    let custom_dirs: BTreeMap<String, String> = meta
+        .dirs
+        .iter()
+        .filter(|(id, d)| generated.get(*id) != Some(*d))
+        .map(|(id, d)| (id.clone(), d.clone()))
+        .collect();
+
     // Remove clone directories
     for identity in identities_to_remove {
         let dn = meta.dir_name(identity)?;
@@ -409,7 +590,8 @@ pub fn remove_repos(ws_dir: &Path, identities_to_remove: &[String], force: bool)
 
     // Recalculate dir names for remaining repos
     let remaining_ids: Vec<&str> = meta.repos.keys().map(|s| s.as_str()).collect();
-    let new_dirs = compute_dir_names(&remaining_ids)?;
+    let mut new_dirs = compute_dir_names(&remaining_ids)?;
+    new_dirs.retain(|id, _| !custom_dirs.contains_key(id));
 
     // Check if any collision disambiguations can be undone
     for (identity, new_dir) in &new_dirs {
@@ -425,6 +607,7 @@ pub fn remove_repos(ws_dir: &Path, identities_to_remove: &[String], force: bool)
     for identity in meta.repos.keys() {
         if let Some(old_dir) = meta.dirs.get(identity).cloned()
             && !new_dirs.contains_key(identity)
+            && !custom_dirs.contains_key(identity)
         {
             let parsed = parse_identity(identity)?;
             let short_name = parsed.repo.clone();
@@ -435,23 +618,518 @@ pub fn remove_repos(ws_dir: &Path, identities_to_remove: &[String], force: bool)
     }
 
     // Update dirs map
+    for (identity, custom) in custom_dirs {
+        if meta.repos.contains_key(&identity) {
+            new_dirs.insert(identity, custom);
+        }
+    }
     meta.dirs = new_dirs;
 
     save_metadata(ws_dir, &meta)
 }
 
+/// Turns a context repo into an active one: creates the workspace branch at
+/// the currently checked out pinned ref (or checks it out if the clone already
+/// has it) and drops the pin from the metadata.
+pub fn activate_repo(ws_dir: &Path, identity: &str) -> Result<()> {
+    let mut meta = load_metadata(ws_dir)?;
+    if !meta.repos.contains_key(identity) {
+        bail!("repo {} is not in this workspace", identity);
+    }
+    if meta.is_active(identity) {
+        bail!("repo {} is already active", identity);
+    }
+
+    let clone_dir = ws_dir.join(meta.dir_name(identity)?);
+    if git::rebase_in_progress(&clone_dir) || git::merge_in_progress(&clone_dir) {
+        bail!("{} has a rebase or merge in progress", identity);
+    }
+
+    if let Some(parent_clone) = meta.load_parent(ws_dir).and_then(|p| p.clone_dir(identity)) {
+        fetch_parent(&clone_dir, &parent_clone)?;
+    }
+
+    if git::branch_exists(&clone_dir, &meta.branch) {
+        git::checkout(&clone_dir, &meta.branch)?;
+    } else {
+        git::checkout_new_branch(&clone_dir, &meta.branch, "HEAD")?;
+    }
+
+    meta.repos.insert(identity.to_string(), None);
+    save_metadata(ws_dir, &meta)
+}
+
+/// Turns an active repo into a context repo pinned to `git_ref`. Refuses,
+/// unless forced, when the workspace branch has work that isn't merged yet —
+/// the same checks `remove_repos` makes. The workspace branch is kept.
+pub fn pin_repo(ws_dir: &Path, identity: &str, git_ref: &str, force: bool) -> Result<()> {
+    let mut meta = load_metadata(ws_dir)?;
+    if !meta.repos.contains_key(identity) {
+        bail!("repo {} is not in this workspace", identity);
+    }
+    if let Some(Some(re)) = meta.repos.get(identity)
+        && !re.r#ref.is_empty()
+    {
+        bail!("repo {} is already pinned to {}", identity, re.r#ref);
+    }
+
+    if !force {
+        let problems = removal_problems(ws_dir, &meta, &[identity.to_string()])?;
+        if !problems.is_empty() {
+            bail!(
+                "cannot pin repo:{}\n\nUse --force to pin anyway",
+                problem_list(&problems)
+            );
+        }
+    }
+
+    let clone_dir = ws_dir.join(meta.dir_name(identity)?);
+    if git::fetch_remote(&clone_dir, "wsp-mirror").is_err() {
+        eprintln!("  warning: fetch failed for {}, using local data", identity);
+    }
+    checkout_pinned_ref(&clone_dir, git_ref)
+        .map_err(|e| anyhow::anyhow!("checking out {} in {}: {}", git_ref, identity, e))?;
+
+    meta.repos.insert(
+        identity.to_string(),
+        Some(WorkspaceRepoRef {
+            r#ref: git_ref.to_string(),
+            base: String::new(),
+        }),
+    );
+    save_metadata(ws_dir, &meta)
+}
+
+/// A git clone found in a directory being adopted as a workspace.
+#[derive(Debug, Clone)]
+pub struct FoundClone {
+    pub dir_name: String,
+    pub identity: String,
+    pub url: String,
+}
+
+/// Finds the git clones directly under `dir` and parses their `origin` URLs.
+pub fn scan_clones(dir: &Path) -> Result<Vec<FoundClone>> {
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(dir)
+        .map_err(|e| anyhow::anyhow!("reading {}: {}", dir.display(), e))?
+        .collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(|e| e.file_name());
+
+    let mut found: Vec<FoundClone> = Vec::new();
+    for entry in entries {
+        let dir_name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        if dir_name.starts_with('.') || !path.is_dir() {
+            continue;
+        }
+        if !path.join(".git").exists() {
+            eprintln!("  skipping {}: not a git repo", dir_name);
+            continue;
+        }
+        let url = git::remote_url(&path, "origin")
+            .map_err(|_| anyhow::anyhow!("{} has no origin remote", dir_name))?;
+        let identity = giturl::parse(&url)
+            .map_err(|e| anyhow::anyhow!("{}: parsing origin URL: {}", dir_name, e))?
+            .identity();
+        if let Some(other) = found.iter().find(|c| c.identity == identity) {
+            bail!(
+                "{} and {} are both clones of {}",
+                other.dir_name,
+                dir_name,
+                identity
+            );
+        }
+        found.push(FoundClone {
+            dir_name,
+            identity,
+            url,
+        });
+    }
+    if found.is_empty() {
+        bail!("no git repos found in {}", dir.display());
+    }
+    Ok(found)
+}
+
+/// Turns `src`, a directory of existing clones, into workspace `name` without
+/// re-cloning anything. Each clone gets a `wsp-mirror` remote pointing at its
+/// (already registered) mirror. Repos on a feature branch become active and
+/// share the most common such branch as the workspace branch; repos on their
+/// default branch, a detached HEAD or another branch become context repos.
+/// `src` (canonical) is moved into the workspaces dir. Returns the workspace
+/// dir. On failure, the `wsp-mirror` remotes added so far are removed again.
+pub fn adopt(
+    paths: &Paths,
+    src: &Path,
+    name: &str,
+    branch_prefix: Option<&str>,
+    clones: &[FoundClone],
+) -> Result<PathBuf> {
+    let mut added: Vec<PathBuf> = Vec::new();
+    let result = adopt_inner(paths, src, name, branch_prefix, clones, &mut added);
+    if result.is_err() {
+        for clone_dir in &added {
+            let _ = git::remote_remove(clone_dir, "wsp-mirror");
+        }
+    }
+    result
+}
+
+fn adopt_inner(
+    paths: &Paths,
+    src: &Path,
+    name: &str,
+    branch_prefix: Option<&str>,
+    clones: &[FoundClone],
+    added: &mut Vec<PathBuf>,
+) -> Result<PathBuf> {
+    validate_name(name)?;
+    if src.join(METADATA_FILE).exists() {
+        bail!("{} is already a workspace", src.display());
+    }
+    let ws_dir = dir(&paths.workspaces_dir, name);
+    let moving = fs::canonicalize(&ws_dir).ok().as_deref() != Some(src);
+    if moving && ws_dir.exists() {
+        bail!("workspace {:?} already exists", name);
+    }
+
+    // Where each clone is: Ok(branch) for a feature branch, Err(ref) to pin
+    let mut checked_out: Vec<(&FoundClone, std::result::Result<String, String>)> = Vec::new();
+    for clone in clones {
+        let clone_dir = src.join(&clone.dir_name);
+        validate_dir_name(&clone.dir_name)?;
+        if git::rebase_in_progress(&clone_dir) || git::merge_in_progress(&clone_dir) {
+            bail!("{} has a rebase or merge in progress", clone.dir_name);
+        }
+
+        let parsed = parse_identity(&clone.identity)?;
+        let mirror_dir = mirror::dir(&paths.mirrors_dir, &parsed);
+        if git::remote_url(&clone_dir, "wsp-mirror").is_err() {
+            added.push(clone_dir.clone());
+        }
+        git::remote_set_url(&clone_dir, "wsp-mirror", &mirror_dir.to_string_lossy())?;
+        git::configure_wsp_mirror_refspec(&clone_dir)?;
+        git::fetch_remote(&clone_dir, "wsp-mirror")?;
+        let default_branch = match git::default_branch_for_remote(&clone_dir, "origin") {
+            Ok(b) => Some(b),
+            Err(_) => {
+                let b = git::default_branch_for_remote(&clone_dir, "wsp-mirror").ok();
+                if let Some(b) = &b {
+                    let _ = git::remote_set_head(&clone_dir, "origin", b);
+                }
+                b
+            }
+        };
+
+        let state = match git::branch_current(&clone_dir)? {
+            b if b == "HEAD" => match git::tag_at_head(&clone_dir) {
+                Some(tag) => Err(tag),
+                None => Err(git::head_sha(&clone_dir)?),
+            },
+            b if Some(&b) == default_branch.as_ref() => Err(b),
+            b => Ok(b),
+        };
+        checked_out.push((clone, state));
+    }
+
+    let conventional = match branch_prefix.filter(|p| !p.is_empty()) {
+        Some(prefix) => format!("{}/{}", prefix, name),
+        None => name.to_string(),
+    };
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for (_, state) in &checked_out {
+        if let Ok(b) = state {
+            *counts.entry(b.as_str()).or_default() += 1;
+        }
+    }
+    let branch = if counts.contains_key(conventional.as_str()) {
+        conventional
+    } else {
+        // Most common wins; BTreeMap order breaks ties alphabetically
+        counts
+            .iter()
+            .rev()
+            .max_by_key(|(_, n)| **n)
+            .map(|(b, _)| b.to_string())
+            .unwrap_or(conventional)
+    };
+
+    let mut repos = BTreeMap::new();
+    let mut dirs = BTreeMap::new();
+    for (clone, state) in checked_out {
+        let entry = match state {
+            Ok(b) if b == branch => None,
+            Ok(b) => {
+                eprintln!(
+                    "  warning: {} is on {}, not {}; adopting it as a context repo",
+                    clone.dir_name, b, branch
+                );
+                Some(b)
+            }
+            Err(r) => Some(r),
+        };
+        repos.insert(
+            clone.identity.clone(),
+            entry.map(|r| WorkspaceRepoRef {
+                r#ref: r,
+                base: String::new(),
+            }),
+        );
+        if parse_identity(&clone.identity)?.repo != clone.dir_name {
+            dirs.insert(clone.identity.clone(), clone.dir_name.clone());
+        }
+    }
+
+    let meta = Metadata {
+        name: name.to_string(),
+        branch,
+        repos,
+        created: Utc::now(),
+        base: None,
+        parent: None,
+        dirs,
+        tasks: BTreeMap::new(),
+    };
+    save_metadata(src, &meta)?;
+
+    if moving
+        && let Err(e) =
+            fs::create_dir_all(&paths.workspaces_dir).and_then(|()| fs::rename(src, &ws_dir))
+    {
+        let _ = fs::remove_file(src.join(METADATA_FILE));
+        bail!("moving {} to {}: {}", src.display(), ws_dir.display(), e);
+    }
+    Ok(ws_dir)
+}
+
+/// Where a context repo's pin could move: tags newer than the pinned tag
+/// (newest first) and, when pinned to a branch, how many commits the mirror
+/// has that the clone hasn't checked out.
+#[derive(Debug, Default)]
+pub struct PinCandidates {
+    pub newer_tags: Vec<String>,
+    pub behind: Option<u32>,
+}
+
+pub fn pin_candidates(clone_dir: &Path, git_ref: &str) -> Result<PinCandidates> {
+    let newer_tags = if git::tag_exists(clone_dir, git_ref) {
+        git::tags_by_version(clone_dir)?
+            .into_iter()
+            .take_while(|t| t != git_ref)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let mirror_ref = format!("wsp-mirror/{}", git_ref);
+    let behind = if git::ref_exists(clone_dir, &format!("refs/remotes/{}", mirror_ref)) {
+        Some(git::commit_count(clone_dir, "HEAD", &mirror_ref)?)
+    } else {
+        None
+    };
+    Ok(PinCandidates { newer_tags, behind })
+}
+
+/// Moves a context repo's pin to `new_ref`, checking the clone out the same
+/// way `create` does and rewriting its ref in `.wsp.yaml`. Branch pins are
+/// fast-forwarded to the mirror's tip.
+pub fn update_pin(ws_dir: &Path, identity: &str, new_ref: &str) -> Result<()> {
+    let mut meta = load_metadata(ws_dir)?;
+    if !meta.repos.contains_key(identity) {
+        bail!("repo {} is not in this workspace", identity);
+    }
+    if meta.is_active(identity) {
+        bail!("repo {} is not pinned; use `wsp repo pin`", identity);
+    }
+
+    let clone_dir = ws_dir.join(meta.dir_name(identity)?);
+    if git::changed_file_count(&clone_dir)? > 0 {
+        bail!("{} has pending changes", identity);
+    }
+
+    checkout_pinned_ref(&clone_dir, new_ref)?;
+    let mirror_ref = format!("wsp-mirror/{}", new_ref);
+    if git::branch_exists(&clone_dir, new_ref)
+        && git::ref_exists(&clone_dir, &format!("refs/remotes/{}", mirror_ref))
+    {
+        git::merge_ff_only(&clone_dir, &mirror_ref)
+            .map_err(|e| anyhow::anyhow!("fast-forwarding {}: {}", new_ref, e))?;
+    }
+
+    meta.repos.insert(
+        identity.to_string(),
+        Some(WorkspaceRepoRef {
+            r#ref: new_ref.to_string(),
+            base: String::new(),
+        }),
+    );
+    save_metadata(ws_dir, &meta)
+}
+
+/// How safe it is to delete an active repo's clone.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// Uncommitted changes, or commits on neither the upstream nor the base.
+    PendingChanges,
+    Merged,
+    SquashMerged,
+    PushedToRemote,
+    Unmerged,
+    /// The clone has no workspace branch, so there is nothing to merge.
+    NoBranch,
+    /// The workspace branch is on its base but nothing was ever committed
+    /// on it: it was never worked on rather than merged.
+    NoCommits,
+    /// The base branch couldn't be determined, so merges weren't checked.
+    UnknownBase,
+}
+
+pub struct RepoSafety {
+    pub verdict: Verdict,
+    /// Fetching origin (or the parent workspace) failed, so the verdict is
+    /// based on local data.
+    pub fetch_failed: bool,
+}
+
+/// Checks whether an active repo's work would survive deleting its clone:
+/// no pending changes, and the workspace branch merged into its base. Fetches
+/// origin (and the parent workspace, for stacked repos) first, best-effort.
+pub fn repo_safety(
+    ws_dir: &Path,
+    meta: &Metadata,
+    parent: Option<&Parent>,
+    identity: &str,
+) -> Result<RepoSafety> {
+    let clone_dir = ws_dir.join(meta.dir_name(identity)?);
+
+    let base_ref = meta.base_ref(identity, parent);
+    if git::changed_file_count(&clone_dir).unwrap_or(0) > 0 {
+        return Ok(RepoSafety {
+            verdict: Verdict::PendingChanges,
+            fetch_failed: false,
+        });
+    }
+
+    // Fetch origin with prune for up-to-date merge detection
+    let mut fetch_failed = git::fetch_remote_prune(&clone_dir, "origin").is_err();
+    if let Some(parent_clone) = parent.and_then(|p| p.clone_dir(identity)) {
+        fetch_failed |= fetch_parent(&clone_dir, &parent_clone).is_err();
+    }
+
+    // A merged (or squash-merged) branch is safe even if its commits aren't
+    // on the base; otherwise local-only commits make it pending.
+    let merge_verdict = if !git::branch_exists(&clone_dir, &meta.branch) {
+        Verdict::NoBranch
+    } else {
+        let target = base_ref.clone().or_else(|| {
+            let default_branch = git::default_branch_for_remote(&clone_dir, "origin")
+                .or_else(|_| git::default_branch(&clone_dir))
+                .ok()?;
+            let merge_target = format!("origin/{}", default_branch);
+            if git::ref_exists(&clone_dir, &merge_target) {
+                Some(merge_target)
+            } else {
+                Some(default_branch)
+            }
+        });
+        // Without origin/HEAD the default-branch lookup falls back to the
+        // checked-out branch, which would compare the branch with itself.
+        let target = target.filter(|t| {
+            t != &meta.branch && t.strip_prefix("origin/") != Some(meta.branch.as_str())
+        });
+        match target {
+            Some(target) => match git::branch_safety(&clone_dir, &meta.branch, &target) {
+                git::BranchSafety::Merged => Verdict::Merged,
+                git::BranchSafety::SquashMerged => Verdict::SquashMerged,
+                git::BranchSafety::PushedToRemote => Verdict::PushedToRemote,
+                git::BranchSafety::Unmerged => Verdict::Unmerged,
+            },
+            None => Verdict::UnknownBase,
+        }
+    };
+    let verdict = match merge_verdict {
+        Verdict::Merged if !git::branch_has_commits(&clone_dir, &meta.branch) => Verdict::NoCommits,
+        Verdict::Merged | Verdict::SquashMerged => merge_verdict,
+        _ if git::ahead_count(&clone_dir, base_ref.as_deref()).unwrap_or(0) > 0 => {
+            Verdict::PendingChanges
+        }
+        _ => merge_verdict,
+    };
+    Ok(RepoSafety {
+        verdict,
+        fetch_failed,
+    })
+}
+
+/// Active repos among `identities` that would lose work if their clone went
+/// away: pending changes, or a workspace branch not merged into its base.
+/// Context repos are never reported.
+fn removal_problems(ws_dir: &Path, meta: &Metadata, identities: &[String]) -> Result<Vec<String>> {
+    let parent = meta.load_parent(ws_dir);
+    let mut problems: Vec<String> = Vec::new();
+    for identity in identities {
+        if !meta.is_active(identity) {
+            continue;
+        }
+
+        let safety = repo_safety(ws_dir, meta, parent.as_ref(), identity)?;
+        if safety.fetch_failed {
+            eprintln!("  warning: fetch failed for {}, using local data", identity);
+        }
+        let mut msg = match safety.verdict {
+            Verdict::PendingChanges => {
+                problems.push(format!("{} (pending changes)", identity));
+                continue;
+            }
+            Verdict::PushedToRemote => {
+                format!("{} (unmerged branch, but pushed to remote)", identity)
+            }
+            Verdict::Unmerged => format!("{} (unmerged branch)", identity),
+            Verdict::UnknownBase => {
+                eprintln!(
+                    "  warning: cannot detect default branch for {}, skipping merge check",
+                    identity
+                );
+                continue;
+            }
+            Verdict::Merged | Verdict::SquashMerged | Verdict::NoBranch | Verdict::NoCommits => {
+                continue;
+            }
+        };
+        if safety.fetch_failed {
+            msg.push_str(" (fetch failed, local data may be stale)");
+        }
+        problems.push(msg);
+    }
+    Ok(problems)
+}
+
+fn problem_list(problems: &[String]) -> String {
+    let mut list = String::new();
+    for p in problems {
+        list.push_str(&format!("\n  - {}", p));
+    }
+    list
+}
+
 /// Resolved per-repo info for workspace-scoped commands.
+#[derive(Debug, Clone)]
 pub struct RepoInfo {
+    pub identity: String,
     pub dir_name: String,
     pub clone_dir: PathBuf,
     pub is_context: bool,
     pub pinned_ref: Option<String>,
+    /// Ref active repos are compared against (`origin/<base>`, or the parent
+    /// workspace's branch when stacked). None means `origin/<default>`.
+    pub base_ref: Option<String>,
     pub error: Option<String>,
 }
 
 impl Metadata {
     /// Build a RepoInfo for each repo in the workspace.
     pub fn repo_infos(&self, ws_dir: &Path) -> Vec<RepoInfo> {
+        let parent = self.load_parent(ws_dir);
         let mut infos = Vec::new();
         for (identity, entry) in &self.repos {
             let is_context = match entry {
@@ -462,14 +1140,21 @@ impl Metadata {
                 Some(re) if !re.r#ref.is_empty() => Some(re.r#ref.clone()),
                 _ => None,
             };
+            let base_ref = if is_context {
+                None
+            } else {
+                self.base_ref(identity, parent.as_ref())
+            };
             let dir_name = match self.dir_name(identity) {
                 Ok(d) => d,
                 Err(e) => {
                     infos.push(RepoInfo {
+                        identity: identity.clone(),
                         dir_name: identity.clone(),
                         clone_dir: PathBuf::new(),
                         is_context,
                         pinned_ref,
+                        base_ref,
                         error: Some(e.to_string()),
                     });
                     continue;
@@ -477,10 +1162,12 @@ impl Metadata {
             };
             let clone_dir = ws_dir.join(&dir_name);
             infos.push(RepoInfo {
+                identity: identity.clone(),
                 dir_name,
                 clone_dir,
                 is_context,
                 pinned_ref,
+                base_ref,
                 error: None,
             });
         }
@@ -522,99 +1209,135 @@ pub fn propagate_mirror_to_clones(ws_dir: &Path, meta: &Metadata) {
     });
 }
 
-pub fn remove(paths: &Paths, name: &str, force: bool) -> Result<()> {
+/// Removes a workspace by moving it into the trash, recording `reason` (the
+/// command that removed it). Returns the trash entry id.
+pub fn remove(paths: &Paths, name: &str, force: bool, reason: &str) -> Result<String> {
     let ws_dir = dir(&paths.workspaces_dir, name);
     let meta =
         load_metadata(&ws_dir).map_err(|e| anyhow::anyhow!("reading workspace metadata: {}", e))?;
 
     if !force {
-        let mut problems: Vec<String> = Vec::new();
+        let identities: Vec<String> = meta.repos.keys().cloned().collect();
+        let mut problems = removal_problems(&ws_dir, &meta, &identities)?;
+        if !problems.is_empty() {
+            problems.sort();
+            bail!(
+                "workspace {:?} has pending changes or unmerged branches ({}):{}\n\nUse --force to remove anyway",
+                name,
+                meta.branch,
+                problem_list(&problems)
+            );
+        }
+    }
 
-        for (identity, entry) in &meta.repos {
-            let is_active = match entry {
-                None => true,
-                Some(re) => re.r#ref.is_empty(),
-            };
-            if !is_active {
-                continue;
-            }
+    let id = trash::put(&paths.trash_dir, &ws_dir, &meta, reason)?;
+    trash::expire(paths);
+    Ok(id)
+}
 
-            let dn = meta.dir_name(identity)?;
-            let clone_dir = ws_dir.join(&dn);
+/// Renames a workspace: moves its directory, renames the workspace branch in
+/// every active clone (unless `keep_branch`), and updates `.wsp.yaml` along
+/// with any workspaces stacked on it. Returns warnings for branches that
+/// were already pushed under the old name.
+pub fn rename(paths: &Paths, old: &str, new: &str, keep_branch: bool) -> Result<Vec<String>> {
+    validate_name(new)?;
+    let old_dir = dir(&paths.workspaces_dir, old);
+    let new_dir = dir(&paths.workspaces_dir, new);
+    let mut meta = load_metadata(&old_dir)
+        .map_err(|e| anyhow::anyhow!("reading workspace {:?}: {}", old, e))?;
+    if new_dir.exists() {
+        bail!("workspace {:?} already exists", new);
+    }
+
+    // Only the trailing workspace name is replaced, so a branch prefix is kept.
+    let old_branch = meta.branch.clone();
+    let new_branch = if keep_branch {
+        old_branch.clone()
+    } else if old_branch == old {
+        new.to_string()
+    } else if let Some(prefix) = old_branch.strip_suffix(&format!("/{}", old)) {
+        format!("{}/{}", prefix, new)
+    } else {
+        bail!(
+            "branch {:?} is not named after the workspace; use --keep-branch",
+            old_branch
+        );
+    };
 
-            // Check for pending local changes first
-            let changed = git::changed_file_count(&clone_dir).unwrap_or(0);
-            let ahead = git::ahead_count(&clone_dir).unwrap_or(0);
-            if changed > 0 || ahead > 0 {
-                problems.push(format!("{} (pending changes)", identity));
-                continue;
-            }
+    // Check every clone before touching anything
+    let mut clones: Vec<(String, String)> = Vec::new();
+    for identity in meta.repos.keys() {
+        if !meta.is_active(identity) {
+            continue;
+        }
+        let dn = meta.dir_name(identity)?;
+        let clone_dir = old_dir.join(&dn);
+        if git::rebase_in_progress(&clone_dir) || git::merge_in_progress(&clone_dir) {
+            bail!("{} has a rebase or merge in progress", dn);
+        }
+        if new_branch != old_branch && git::branch_exists(&clone_dir, &new_branch) {
+            bail!("branch {:?} already exists in {}", new_branch, dn);
+        }
+        clones.push((identity.clone(), dn));
+    }
 
-            // Best-effort fetch origin with prune to detect remote merges
-            let fetch_failed = git::fetch_remote_prune(&clone_dir, "origin").is_err();
-            if fetch_failed {
-                eprintln!("  warning: fetch failed for {}, using local data", identity);
-            }
+    fs::rename(&old_dir, &new_dir)
+        .map_err(|e| anyhow::anyhow!("moving workspace directory: {}", e))?;
 
-            if !git::branch_exists(&clone_dir, &meta.branch) {
+    let mut warnings = Vec::new();
+    if new_branch != old_branch {
+        let mut renamed: Vec<&str> = Vec::new();
+        for (identity, dn) in &clones {
+            let clone_dir = new_dir.join(dn);
+            if !git::branch_exists(&clone_dir, &old_branch) {
                 continue;
             }
-            let default_branch = match git::default_branch_for_remote(&clone_dir, "origin") {
-                Ok(b) => b,
-                Err(_) => match git::default_branch(&clone_dir) {
-                    Ok(b) => b,
-                    Err(e) => {
-                        eprintln!(
-                            "  warning: cannot detect default branch for {}: {}",
-                            identity, e
-                        );
-                        continue;
-                    }
-                },
-            };
-            let merge_target = format!("origin/{}", default_branch);
-            let target = if git::ref_exists(&clone_dir, &merge_target) {
-                merge_target
-            } else {
-                default_branch
-            };
-            match git::branch_safety(&clone_dir, &meta.branch, &target) {
-                git::BranchSafety::Merged | git::BranchSafety::SquashMerged => {}
-                git::BranchSafety::PushedToRemote => {
-                    let mut msg = format!("{} (unmerged branch, but pushed to remote)", identity);
-                    if fetch_failed {
-                        msg.push_str(" (fetch failed, local data may be stale)");
-                    }
-                    problems.push(msg);
-                }
-                git::BranchSafety::Unmerged => {
-                    let mut msg = format!("{} (unmerged branch)", identity);
-                    if fetch_failed {
-                        msg.push_str(" (fetch failed, local data may be stale)");
-                    }
-                    problems.push(msg);
+            if let Err(e) = git::branch_rename(&clone_dir, &old_branch, &new_branch) {
+                // Roll back so the workspace stays consistent (best-effort)
+                for dn in renamed {
+                    let _ = git::branch_rename(&new_dir.join(dn), &new_branch, &old_branch);
                 }
+                let _ = fs::rename(&new_dir, &old_dir);
+                bail!("renaming branch in {}: {}", identity, e);
+            }
+            renamed.push(dn);
+            if git::remote_branch_exists(&clone_dir, &old_branch) {
+                warnings.push(format!(
+                    "{}: {:?} was pushed to origin and keeps its old name there; \
+                     run `git push -u origin {}` and `git push origin --delete {}`",
+                    dn, old_branch, new_branch, old_branch
+                ));
             }
         }
+    }
 
-        if !problems.is_empty() {
-            let mut sorted = problems;
-            sorted.sort();
-            let mut list = String::new();
-            for p in &sorted {
-                list.push_str(&format!("\n  - {}", p));
+    meta.name = new.to_string();
+    meta.branch = new_branch;
+    save_metadata(&new_dir, &meta)?;
+
+    // Re-point workspaces stacked on this one and refresh their parent refs
+    let parent = Parent { dir: new_dir, meta };
+    for name in list_all(&paths.workspaces_dir)? {
+        let child_dir = dir(&paths.workspaces_dir, &name);
+        let Ok(mut child) = load_metadata(&child_dir) else {
+            continue;
+        };
+        if child.parent.as_deref() != Some(old) {
+            continue;
+        }
+        child.parent = Some(new.to_string());
+        save_metadata(&child_dir, &child)?;
+        for identity in child.repos.keys() {
+            if let Some(parent_clone) = parent.clone_dir(identity)
+                && child.is_active(identity)
+                && let Ok(dn) = child.dir_name(identity)
+            {
+                let _ = fetch_parent(&child_dir.join(dn), &parent_clone);
             }
-            bail!(
-                "workspace {:?} has pending changes or unmerged branches ({}):{}\n\nUse --force to remove anyway",
-                name,
-                meta.branch,
-                list
-            );
         }
     }
 
-    fs::remove_dir_all(&ws_dir)?;
-    Ok(())
+    Ok(warnings)
 }
 
 pub fn list_all(workspaces_dir: &Path) -> Result<Vec<String>> {
@@ -645,9 +1368,10 @@ fn clone_from_mirror(
     identity: &str,
     dir_name: &str,
     branch: &str,
-    git_ref: &str,
+    checkout: &Checkout,
     upstream_url: &str,
 ) -> Result<()> {
+    let git_ref = checkout.git_ref.as_str();
     let parsed = parse_identity(identity)?;
     let mirror_dir = mirror::dir(mirrors_dir, &parsed);
     let dest = ws_dir.join(dir_name);
@@ -664,33 +1388,26 @@ fn clone_from_mirror(
         git::remote_set_origin(&dest, upstream_url)?;
     }
 
-    // 4. Copy default branch info from wsp-mirror to origin
+    // 4. Fetch origin so remote tracking branches (origin/main etc.) exist
+    if !upstream_url.is_empty() {
+        git::fetch_remote(&dest, "origin")?;
+    }
+
+    // 4b. Copy default branch info from wsp-mirror to origin. set-head needs
+    // origin/<default> to exist, so this must follow the fetch.
     if let Ok(default_br) = git::default_branch_for_remote(&dest, "wsp-mirror") {
         let _ = git::remote_set_head(&dest, "origin", &default_br);
     }
 
-    // 4b. Fetch origin so remote tracking branches (origin/main etc.) exist
-    if !upstream_url.is_empty() {
-        git::fetch_remote(&dest, "origin")?;
+    // 4c. Stacked repo: add the parent workspace's clone as a remote
+    if let Some((parent_clone, _)) = &checkout.parent {
+        fetch_parent(&dest, parent_clone)?;
     }
 
     // 5. Checkout the right ref/branch
     // Context repo: check out at the specified ref
     if !git_ref.is_empty() {
-        let ws_mirror_ref = format!("wsp-mirror/{}", git_ref);
-        if git::branch_exists(&dest, git_ref) {
-            // Local branch already exists
-            git::checkout(&dest, git_ref)?;
-        } else if git::ref_exists(&dest, &format!("refs/remotes/wsp-mirror/{}", git_ref)) {
-            // Create branch from wsp-mirror/<ref>, no tracking — devs must
-            // explicitly `git push -u` to avoid accidentally pushing to the
-            // wrong branch.
-            git::checkout_new_branch(&dest, git_ref, &ws_mirror_ref)?;
-        } else {
-            // Tag or SHA: detached HEAD
-            git::checkout_detached(&dest, git_ref)?;
-        }
-        return Ok(());
+        return checkout_pinned_ref(&dest, git_ref);
     }
 
     // Active repo: create/checkout workspace branch
@@ -699,16 +1416,54 @@ fn clone_from_mirror(
         return Ok(());
     }
 
-    // No upstream tracking — the workspace branch differs from the default
-    // branch, so tracking origin/<default> would cause a bare `git push` to
+    // No upstream tracking — the workspace branch differs from the base
+    // branch, so tracking origin/<base> would cause a bare `git push` to
     // target the wrong branch. Devs set tracking explicitly via `git push -u`.
-    let default_branch = git::default_branch_for_remote(&dest, "wsp-mirror")?;
-    let start_point = format!("wsp-mirror/{}", default_branch);
+    let start_point = if !checkout.commit.is_empty() {
+        if !git::commit_exists(&dest, &checkout.commit) {
+            bail!("commit {} not found in mirror", checkout.commit);
+        }
+        checkout.commit.clone()
+    } else if let Some((_, parent_branch)) = &checkout.parent {
+        let start_point = format!("{}/{}", PARENT_REMOTE, parent_branch);
+        if !git::ref_exists(&dest, &format!("refs/remotes/{}", start_point)) {
+            bail!("parent branch {:?} not found", parent_branch);
+        }
+        start_point
+    } else {
+        let base = if checkout.base.is_empty() {
+            git::default_branch_for_remote(&dest, "wsp-mirror")?
+        } else {
+            checkout.base.clone()
+        };
+        let start_point = format!("wsp-mirror/{}", base);
+        if !git::ref_exists(&dest, &format!("refs/remotes/{}", start_point)) {
+            bail!("base branch {:?} not found", base);
+        }
+        start_point
+    };
     git::checkout_new_branch(&dest, branch, &start_point)?;
 
     Ok(())
 }
 
+/// Checks out a context repo's pinned ref: a local branch if one exists,
+/// a new branch from `wsp-mirror/<ref>`, or a detached HEAD for tags and SHAs.
+fn checkout_pinned_ref(dest: &Path, git_ref: &str) -> Result<()> {
+    if git::branch_exists(dest, git_ref) {
+        // Local branch already exists
+        git::checkout(dest, git_ref)
+    } else if git::ref_exists(dest, &format!("refs/remotes/wsp-mirror/{}", git_ref)) {
+        // Create branch from wsp-mirror/<ref>, no tracking — devs must
+        // explicitly `git push -u` to avoid accidentally pushing to the
+        // wrong branch.
+        git::checkout_new_branch(dest, git_ref, &format!("wsp-mirror/{}", git_ref))
+    } else {
+        // Tag or SHA: detached HEAD
+        git::checkout_detached(dest, git_ref)
+    }
+}
+
 fn parse_identity(identity: &str) -> Result<giturl::Parsed> {
     giturl::Parsed::from_identity(identity)
 }
@@ -803,7 +1558,15 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "test-ws", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "test-ws",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "test-ws");
         let meta = load_metadata(&ws_dir).unwrap();
@@ -826,7 +1589,15 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity, String::new())]);
-        create(&paths, "no-track", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "no-track",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "no-track");
         let clone_dir = ws_dir.join("test-repo");
@@ -844,7 +1615,15 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity, "main".into())]);
-        create(&paths, "ctx-no-track", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "ctx-no-track",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "ctx-no-track");
         let clone_dir = ws_dir.join("test-repo");
@@ -861,7 +1640,15 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "my-feature", &refs, Some("jganoff"), &upstream_urls).unwrap();
+        create(
+            &paths,
+            "my-feature",
+            &refs,
+            &Bases::default(),
+            Some("jganoff"),
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "my-feature");
         let meta = load_metadata(&ws_dir).unwrap();
@@ -877,7 +1664,15 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "empty-prefix", &refs, Some(""), &upstream_urls).unwrap();
+        create(
+            &paths,
+            "empty-prefix",
+            &refs,
+            &Bases::default(),
+            Some(""),
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "empty-prefix");
         let meta = load_metadata(&ws_dir).unwrap();
@@ -890,8 +1685,26 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "test-ws-dup", &refs, None, &upstream_urls).unwrap();
-        assert!(create(&paths, "test-ws-dup", &refs, None, &upstream_urls).is_err());
+        create(
+            &paths,
+            "test-ws-dup",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
+        assert!(
+            create(
+                &paths,
+                "test-ws-dup",
+                &refs,
+                &Bases::default(),
+                None,
+                &upstream_urls
+            )
+            .is_err()
+        );
     }
 
     #[test]
@@ -899,7 +1712,15 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity, String::new())]);
-        create(&paths, "test-ws-detect", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "test-ws-detect",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "test-ws-detect");
 
@@ -924,13 +1745,21 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "rm-merged", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-merged",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rm-merged");
         assert!(ws_dir.exists());
 
         // Branch was created from main with no extra commits, so it's merged
-        remove(&paths, "rm-merged", false).unwrap();
+        remove(&paths, "rm-merged", false, "wsp rm").unwrap();
         assert!(!ws_dir.exists());
     }
 
@@ -963,19 +1792,286 @@ mod tests {
             );
         }
 
-        // Fetch to update mirror
-        git::fetch(&mirror_dir, true).unwrap();
+        // Fetch to update mirror
+        git::fetch(&mirror_dir, true).unwrap();
+
+        // Create workspace
+        let refs = BTreeMap::from([(identity.clone(), String::new())]);
+        create(
+            &paths,
+            "rm-origin-ahead",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
+
+        let ws_dir = dir(&paths.workspaces_dir, "rm-origin-ahead");
+        assert!(ws_dir.exists());
+
+        // Remove should succeed — the workspace branch has no extra commits
+        remove(&paths, "rm-origin-ahead", false, "wsp rm").unwrap();
+        assert!(!ws_dir.exists());
+    }
+
+    #[test]
+    fn test_create_with_base_branch() {
+        let (paths, _d, source_repo, identity, upstream_urls) = setup_test_env();
+
+        // Add a release branch that is ahead of main
+        for args in [
+            vec!["checkout", "-b", "release"],
+            vec!["commit", "--allow-empty", "-m", "release fix"],
+            vec!["checkout", "main"],
+        ] {
+            git::run(Some(source_repo.path()), &args).unwrap();
+        }
+        let mirror_dir = mirror::dir(&paths.mirrors_dir, &parse_identity(&identity).unwrap());
+        git::fetch(&mirror_dir, true).unwrap();
+        let release_sha = git::run(Some(source_repo.path()), &["rev-parse", "release"]).unwrap();
+
+        let refs = BTreeMap::from([(identity.clone(), String::new())]);
+        let bases = Bases {
+            default: Some("release".into()),
+            ..Default::default()
+        };
+        create(&paths, "on-release", &refs, &bases, None, &upstream_urls).unwrap();
+
+        let ws_dir = dir(&paths.workspaces_dir, "on-release");
+        let meta = load_metadata(&ws_dir).unwrap();
+        assert_eq!(meta.base.as_deref(), Some("release"));
+        assert_eq!(meta.base_branch(&identity), Some("release"));
+
+        let infos = meta.repo_infos(&ws_dir);
+        assert_eq!(infos[0].base_ref.as_deref(), Some("origin/release"));
+
+        // The workspace branch starts from the base, not main
+        let clone_dir = ws_dir.join("test-repo");
+        assert_eq!(git::head_sha(&clone_dir).unwrap(), release_sha);
+        assert_eq!(
+            git::ahead_count(&clone_dir, Some("origin/release")).unwrap(),
+            0
+        );
+
+        // Merged into the base counts as safe, even though main lacks the commit
+        remove(&paths, "on-release", false, "wsp rm").unwrap();
+        assert!(!ws_dir.exists());
+    }
+
+    #[test]
+    fn test_create_stacked_workspace() {
+        let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
+
+        let refs = BTreeMap::from([(identity.clone(), String::new())]);
+        create(
+            &paths,
+            "part-1",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
+        let parent_clone = dir(&paths.workspaces_dir, "part-1").join("test-repo");
+        for args in [
+            vec!["config", "user.email", "test@test.com"],
+            vec!["config", "user.name", "Test"],
+            vec!["config", "commit.gpgsign", "false"],
+            vec!["commit", "--allow-empty", "-m", "part 1"],
+        ] {
+            git::run(Some(&parent_clone), &args).unwrap();
+        }
+
+        let bases = Bases {
+            parent: Some("part-1".into()),
+            ..Default::default()
+        };
+        create(&paths, "part-2", &refs, &bases, None, &upstream_urls).unwrap();
+
+        let ws_dir = dir(&paths.workspaces_dir, "part-2");
+        let meta = load_metadata(&ws_dir).unwrap();
+        assert_eq!(meta.parent.as_deref(), Some("part-1"));
+
+        // Branches from the parent's workspace branch and compares against it
+        let clone_dir = ws_dir.join("test-repo");
+        assert_eq!(
+            git::head_sha(&clone_dir).unwrap(),
+            git::head_sha(&parent_clone).unwrap()
+        );
+        let infos = meta.repo_infos(&ws_dir);
+        assert_eq!(infos[0].base_ref.as_deref(), Some("wsp-parent/part-1"));
+        assert_eq!(
+            git::ahead_count(&clone_dir, infos[0].base_ref.as_deref()).unwrap(),
+            0
+        );
+
+        let chain = ancestors(&ws_dir, &meta).unwrap();
+        let names: Vec<&str> = chain.iter().map(|p| p.meta.name.as_str()).collect();
+        assert_eq!(names, vec!["part-1"]);
+
+        // Merged into the parent is safe, although main lacks "part 1"
+        remove(&paths, "part-2", false, "wsp rm").unwrap();
+        assert!(!ws_dir.exists());
+
+        // Once the parent is gone, a stacked workspace falls back to origin
+        create(&paths, "part-3", &refs, &bases, None, &upstream_urls).unwrap();
+        remove(&paths, "part-1", true, "wsp rm").unwrap();
+        let ws_dir = dir(&paths.workspaces_dir, "part-3");
+        let meta = load_metadata(&ws_dir).unwrap();
+        assert!(meta.load_parent(&ws_dir).is_none());
+        assert_eq!(meta.repo_infos(&ws_dir)[0].base_ref, None);
+    }
+
+    #[test]
+    fn test_rename() {
+        let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
+
+        let refs = BTreeMap::from([(identity.clone(), String::new())]);
+        create(
+            &paths,
+            "old-name",
+            &refs,
+            &Bases::default(),
+            Some("me"),
+            &upstream_urls,
+        )
+        .unwrap();
+        let stacked = Bases {
+            parent: Some("old-name".into()),
+            ..Default::default()
+        };
+        create(&paths, "child", &refs, &stacked, None, &upstream_urls).unwrap();
+
+        let warnings = rename(&paths, "old-name", "new-name", false).unwrap();
+        assert!(warnings.is_empty());
+
+        assert!(!dir(&paths.workspaces_dir, "old-name").exists());
+        let ws_dir = dir(&paths.workspaces_dir, "new-name");
+        let meta = load_metadata(&ws_dir).unwrap();
+        assert_eq!(meta.name, "new-name");
+        assert_eq!(meta.branch, "me/new-name");
+
+        // The prefix is kept and the clone is on the renamed branch
+        let clone_dir = ws_dir.join("test-repo");
+        assert_eq!(git::branch_current(&clone_dir).unwrap(), "me/new-name");
+        assert!(!git::branch_exists(&clone_dir, "me/old-name"));
+
+        // Stacked workspaces follow the rename
+        let child_dir = dir(&paths.workspaces_dir, "child");
+        let child = load_metadata(&child_dir).unwrap();
+        assert_eq!(child.parent.as_deref(), Some("new-name"));
+        assert!(git::ref_exists(
+            &child_dir.join("test-repo"),
+            "refs/remotes/wsp-parent/me/new-name"
+        ));
+
+        // --keep-branch leaves the branch alone
+        rename(&paths, "new-name", "kept", true).unwrap();
+        let meta = load_metadata(&dir(&paths.workspaces_dir, "kept")).unwrap();
+        assert_eq!(meta.branch, "me/new-name");
+    }
+
+    #[test]
+    fn test_rename_errors() {
+        let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
+
+        let refs = BTreeMap::from([(identity.clone(), String::new())]);
+        for name in ["ws-a", "ws-b"] {
+            create(&paths, name, &refs, &Bases::default(), None, &upstream_urls).unwrap();
+        }
+
+        let cases = vec![
+            ("invalid name", "ws-a", "../x", "path separators"),
+            ("target exists", "ws-a", "ws-b", "already exists"),
+            ("missing source", "nope", "ws-c", "reading workspace"),
+        ];
+        for (name, old, new, want) in cases {
+            let err = rename(&paths, old, new, false).unwrap_err();
+            assert!(err.to_string().contains(want), "{}: {}", name, err);
+        }
+
+        // Branch already taken in a clone: nothing is changed
+        let clone_dir = dir(&paths.workspaces_dir, "ws-a").join("test-repo");
+        git::run(Some(&clone_dir), &["branch", "ws-c"]).unwrap();
+        let err = rename(&paths, "ws-a", "ws-c", false).unwrap_err();
+        assert!(err.to_string().contains("already exists in test-repo"));
+        assert!(dir(&paths.workspaces_dir, "ws-a").exists());
+        assert_eq!(git::branch_current(&clone_dir).unwrap(), "ws-a");
+    }
+
+    #[test]
+    fn test_create_with_repo_base_errors() {
+        let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
+
+        let bases = Bases {
+            repos: BTreeMap::from([(identity.clone(), "no-such-branch".into())]),
+            ..Default::default()
+        };
+
+        // Unknown base branch fails and cleans up
+        let refs = BTreeMap::from([(identity.clone(), String::new())]);
+        let err = create(&paths, "bad-base", &refs, &bases, None, &upstream_urls).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("base branch \"no-such-branch\" not found")
+        );
+        assert!(!dir(&paths.workspaces_dir, "bad-base").exists());
+
+        // A base on a pinned repo is rejected
+        let refs = BTreeMap::from([(identity.clone(), "main".to_string())]);
+        let err = create(&paths, "pinned-base", &refs, &bases, None, &upstream_urls).unwrap_err();
+        assert!(err.to_string().contains("only applies to active repos"));
+    }
+
+    #[test]
+    fn test_repo_safety() {
+        let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
-        // Create workspace
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "rm-origin-ahead", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "safety",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
-        let ws_dir = dir(&paths.workspaces_dir, "rm-origin-ahead");
-        assert!(ws_dir.exists());
+        let ws_dir = dir(&paths.workspaces_dir, "safety");
+        let repo_dir = ws_dir.join("test-repo");
+        let meta = load_metadata(&ws_dir).unwrap();
+        let verdict = || {
+            repo_safety(&ws_dir, &meta, None, &identity)
+                .unwrap()
+                .verdict
+        };
 
-        // Remove should succeed — the workspace branch has no extra commits
-        remove(&paths, "rm-origin-ahead", false).unwrap();
-        assert!(!ws_dir.exists());
+        assert!(
+            git::ref_exists(&repo_dir, "refs/remotes/origin/HEAD"),
+            "clones should know origin's default branch"
+        );
+        // A fresh branch sits on its base but was never worked on.
+        assert_eq!(verdict(), Verdict::NoCommits);
+
+        fs::write(repo_dir.join("dirty.txt"), "dirty").unwrap();
+        assert_eq!(verdict(), Verdict::PendingChanges);
+        fs::remove_file(repo_dir.join("dirty.txt")).unwrap();
+
+        for kv in [["user.email", "test@test.com"], ["user.name", "Test"]] {
+            git::run(Some(&repo_dir), &["config", kv[0], kv[1]]).unwrap();
+        }
+        git::run(Some(&repo_dir), &["config", "commit.gpgsign", "false"]).unwrap();
+        crate::testutil::local_commit(&repo_dir, "work.txt", "work");
+        assert_eq!(verdict(), Verdict::PendingChanges);
+
+        // Without origin/HEAD the base can't be found; the branch must not be
+        // judged merged into itself.
+        git::run(Some(&repo_dir), &["reset", "--hard", "HEAD~1"]).unwrap();
+        git::run(Some(&repo_dir), &["remote", "set-head", "origin", "-d"]).unwrap();
+        git::run(Some(&repo_dir), &["branch", "-D", "main"]).ok();
+        assert_eq!(verdict(), Verdict::UnknownBase);
     }
 
     #[test]
@@ -983,7 +2079,15 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "rm-unmerged", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-unmerged",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rm-unmerged");
         let repo_dir = ws_dir.join("test-repo");
@@ -1009,7 +2113,7 @@ mod tests {
             );
         }
 
-        let result = remove(&paths, "rm-unmerged", false);
+        let result = remove(&paths, "rm-unmerged", false, "wsp rm");
         assert!(result.is_err());
         let err = result.unwrap_err().to_string();
         assert!(
@@ -1027,7 +2131,15 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "rm-force", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-force",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rm-force");
         let repo_dir = ws_dir.join("test-repo");
@@ -1054,7 +2166,7 @@ mod tests {
         }
 
         // Force remove should succeed despite unmerged branch
-        remove(&paths, "rm-force", true).unwrap();
+        remove(&paths, "rm-force", true, "wsp rm").unwrap();
         assert!(!ws_dir.exists());
     }
 
@@ -1063,13 +2175,21 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity, String::new())]);
-        create(&paths, "rm-dirty", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-dirty",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rm-dirty");
         let repo_dir = ws_dir.join("test-repo");
         fs::write(repo_dir.join("dirty.txt"), "x").unwrap();
 
-        let result = remove(&paths, "rm-dirty", false);
+        let result = remove(&paths, "rm-dirty", false, "wsp rm");
         assert!(result.is_err());
         let err = result.unwrap_err().to_string();
         assert!(
@@ -1090,7 +2210,15 @@ mod tests {
 
         // Create a workspace
         let refs = BTreeMap::from([(identity, String::new())]);
-        create(&paths, "ws-1-list", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "ws-1-list",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let names = list_all(&paths.workspaces_dir).unwrap();
         assert_eq!(names, vec!["ws-1-list"]);
@@ -1107,7 +2235,10 @@ mod tests {
                 ("github.com/user/repo-b".into(), None),
             ]),
             created: Utc::now(),
+            base: None,
+            parent: None,
             dirs: BTreeMap::new(),
+            tasks: BTreeMap::new(),
         };
 
         save_metadata(tmp.path(), &meta).unwrap();
@@ -1133,17 +2264,22 @@ mod tests {
                     "github.com/acme/user-service".into(),
                     Some(WorkspaceRepoRef {
                         r#ref: "main".into(),
+                        base: String::new(),
                     }),
                 ),
                 (
                     "github.com/acme/proto".into(),
                     Some(WorkspaceRepoRef {
                         r#ref: "v1.0".into(),
+                        base: String::new(),
                     }),
                 ),
             ]),
             created: Utc::now(),
+            base: None,
+            parent: None,
             dirs: BTreeMap::new(),
+            tasks: BTreeMap::new(),
         };
 
         save_metadata(tmp.path(), &meta).unwrap();
@@ -1168,6 +2304,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_load_metadata_with_tasks() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(
+            tmp.path().join(METADATA_FILE),
+            r#"name: my-ws
+branch: my-ws
+repos:
+  github.com/acme/api-gateway:
+  github.com/acme/proto:
+created: 2024-01-01T00:00:00Z
+tasks:
+  build:
+    command: make build
+    order: [proto]
+    overrides:
+      proto: buf generate
+  lint:
+    command: make lint
+    repos: [api-gateway]
+"#,
+        )
+        .unwrap();
+
+        let loaded = load_metadata(tmp.path()).unwrap();
+        assert_eq!(
+            loaded.tasks["build"],
+            Task {
+                command: "make build".into(),
+                overrides: BTreeMap::from([("proto".into(), "buf generate".into())]),
+                repos: vec![],
+                order: vec!["proto".into()],
+            }
+        );
+        assert_eq!(loaded.tasks["lint"].repos, vec!["api-gateway"]);
+
+        // Round-trips without emitting empty fields.
+        save_metadata(tmp.path(), &loaded).unwrap();
+        let raw = fs::read_to_string(tmp.path().join(METADATA_FILE)).unwrap();
+        assert!(!raw.contains("overrides: {}"), "{}", raw);
+        assert_eq!(load_metadata(tmp.path()).unwrap().tasks, loaded.tasks);
+    }
+
     #[test]
     fn test_validate_name() {
         let cases = vec![
@@ -1260,7 +2439,14 @@ mod tests {
         // Try to create with a nonexistent repo identity — will fail
         let refs = BTreeMap::from([("nonexistent.local/user/nope".into(), String::new())]);
         let upstream_urls = BTreeMap::new();
-        let result = create(&paths, "fail-ws", &refs, None, &upstream_urls);
+        let result = create(
+            &paths,
+            "fail-ws",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        );
         assert!(result.is_err());
 
         // Workspace dir should have been cleaned up
@@ -1277,7 +2463,15 @@ mod tests {
 
         // Create workspace with the repo as context (ref = "main")
         let refs = BTreeMap::from([(identity.clone(), "main".into())]);
-        create(&paths, "ctx-ws", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "ctx-ws",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "ctx-ws");
         let meta = load_metadata(&ws_dir).unwrap();
@@ -1287,18 +2481,346 @@ mod tests {
         assert!(ws_dir.join("test-repo").exists());
     }
 
+    #[test]
+    fn test_activate_and_pin_repo() {
+        let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
+
+        let refs = BTreeMap::from([(identity.clone(), "main".into())]);
+        create(
+            &paths,
+            "toggle-ws",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
+
+        let ws_dir = dir(&paths.workspaces_dir, "toggle-ws");
+        let clone_dir = ws_dir.join("test-repo");
+
+        activate_repo(&ws_dir, &identity).unwrap();
+        let meta = load_metadata(&ws_dir).unwrap();
+        assert!(meta.is_active(&identity));
+        assert_eq!(git::branch_current(&clone_dir).unwrap(), "toggle-ws");
+        assert!(
+            activate_repo(&ws_dir, &identity)
+                .unwrap_err()
+                .to_string()
+                .contains("already active")
+        );
+
+        // Pending changes block pinning unless forced
+        fs::write(clone_dir.join("dirty.txt"), "x").unwrap();
+        let err = pin_repo(&ws_dir, &identity, "main", false)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("pending changes"), "got: {}", err);
+        fs::remove_file(clone_dir.join("dirty.txt")).unwrap();
+
+        pin_repo(&ws_dir, &identity, "main", false).unwrap();
+        let meta = load_metadata(&ws_dir).unwrap();
+        assert!(!meta.is_active(&identity));
+        assert_eq!(meta.repos[&identity].as_ref().unwrap().r#ref, "main");
+        assert_eq!(git::branch_current(&clone_dir).unwrap(), "main");
+        assert!(
+            pin_repo(&ws_dir, &identity, "main", false)
+                .unwrap_err()
+                .to_string()
+                .contains("already pinned to main")
+        );
+
+        // Re-activating picks the kept workspace branch back up
+        activate_repo(&ws_dir, &identity).unwrap();
+        assert_eq!(git::branch_current(&clone_dir).unwrap(), "toggle-ws");
+    }
+
+    #[test]
+    fn test_update_pin() {
+        let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
+        let source = PathBuf::from(&upstream_urls[&identity]);
+        let parsed = parse_identity(&identity).unwrap();
+        let refresh = |clone_dir: &Path| {
+            mirror::fetch(&paths.mirrors_dir, &parsed).unwrap();
+            git::fetch_tags(clone_dir, "wsp-mirror").unwrap();
+        };
+
+        git::run(Some(&source), &["tag", "v1.0"]).unwrap();
+        mirror::fetch(&paths.mirrors_dir, &parsed).unwrap();
+
+        let refs = BTreeMap::from([(identity.clone(), "v1.0".into())]);
+        create(
+            &paths,
+            "bump-ws",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
+        let ws_dir = dir(&paths.workspaces_dir, "bump-ws");
+        let clone_dir = ws_dir.join("test-repo");
+
+        // Newer tags are listed newest first, by version number
+        for tag in ["v1.1", "v1.10", "v1.9"] {
+            git::run(Some(&source), &["commit", "--allow-empty", "-m", tag]).unwrap();
+            git::run(Some(&source), &["tag", tag]).unwrap();
+        }
+        refresh(&clone_dir);
+        let candidates = pin_candidates(&clone_dir, "v1.0").unwrap();
+        assert_eq!(candidates.newer_tags, vec!["v1.10", "v1.9", "v1.1"]);
+        assert_eq!(candidates.behind, None);
+
+        update_pin(&ws_dir, &identity, "v1.10").unwrap();
+        let meta = load_metadata(&ws_dir).unwrap();
+        assert_eq!(meta.repos[&identity].as_ref().unwrap().r#ref, "v1.10");
+        assert_eq!(
+            git::head_sha(&clone_dir).unwrap(),
+            git::run(Some(&source), &["rev-parse", "v1.10"]).unwrap()
+        );
+        assert!(
+            pin_candidates(&clone_dir, "v1.10")
+                .unwrap()
+                .newer_tags
+                .is_empty()
+        );
+
+        // Branch pins report and fast-forward to the mirror's tip
+        update_pin(&ws_dir, &identity, "main").unwrap();
+        git::run(Some(&source), &["commit", "--allow-empty", "-m", "tip"]).unwrap();
+        refresh(&clone_dir);
+        assert_eq!(pin_candidates(&clone_dir, "main").unwrap().behind, Some(1));
+        update_pin(&ws_dir, &identity, "main").unwrap();
+        assert_eq!(pin_candidates(&clone_dir, "main").unwrap().behind, Some(0));
+        assert_eq!(git::branch_current(&clone_dir).unwrap(), "main");
+    }
+
+    #[test]
+    fn test_update_pin_errors() {
+        let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
+
+        let refs = BTreeMap::from([(identity.clone(), String::new())]);
+        create(
+            &paths,
+            "bump-active",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
+        let ws_dir = dir(&paths.workspaces_dir, "bump-active");
+
+        let err = update_pin(&ws_dir, &identity, "main")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("is not pinned"), "got: {}", err);
+
+        pin_repo(&ws_dir, &identity, "main", true).unwrap();
+        fs::write(ws_dir.join("test-repo").join("dirty.txt"), "x").unwrap();
+        let err = update_pin(&ws_dir, &identity, "main")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("pending changes"), "got: {}", err);
+    }
+
+    #[test]
+    fn test_create_at_locked_commits() {
+        let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
+        let source = PathBuf::from(&upstream_urls[&identity]);
+        let parsed = parse_identity(&identity).unwrap();
+        let old_sha = git::run(Some(&source), &["rev-parse", "HEAD"]).unwrap();
+
+        // The mirror moves on past the locked commit
+        git::run(Some(&source), &["commit", "--allow-empty", "-m", "later"]).unwrap();
+        mirror::fetch(&paths.mirrors_dir, &parsed).unwrap();
+
+        let refs = BTreeMap::from([(identity.clone(), String::new())]);
+        let bases = Bases {
+            commits: BTreeMap::from([(identity.clone(), old_sha.clone())]),
+            ..Bases::default()
+        };
+        create(&paths, "thawed", &refs, &bases, None, &upstream_urls).unwrap();
+        let ws_dir = dir(&paths.workspaces_dir, "thawed");
+        let clone_dir = ws_dir.join("test-repo");
+        assert_eq!(git::head_sha(&clone_dir).unwrap(), old_sha);
+        assert_eq!(git::branch_current(&clone_dir).unwrap(), "thawed");
+
+        let lock = crate::lockfile::capture(&ws_dir, &load_metadata(&ws_dir).unwrap()).unwrap();
+        let locked = &lock.repos[&identity];
+        assert_eq!(locked.sha, old_sha);
+        assert_eq!(locked.branch, "thawed");
+        assert_eq!(locked.role, crate::lockfile::Role::Active);
+
+        let bases = Bases {
+            commits: BTreeMap::from([(identity.clone(), "0".repeat(40))]),
+            ..Bases::default()
+        };
+        let err = create(&paths, "thawed-bad", &refs, &bases, None, &upstream_urls)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("not found in mirror"), "got: {}", err);
+        assert!(!dir(&paths.workspaces_dir, "thawed-bad").exists());
+    }
+
+    #[test]
+    fn test_adopt() {
+        let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
+        let source = &upstream_urls[&identity];
+        let hand_made = tempfile::tempdir().unwrap();
+        let src = fs::canonicalize(hand_made.path())
+            .unwrap()
+            .join("feature-x");
+        fs::create_dir_all(src.join("notes")).unwrap();
+
+        // A hand-made clone under a custom dir name, on a feature branch
+        let clone_dir = src.join("api-checkout");
+        git::run(None, &["clone", "-q", source, clone_dir.to_str().unwrap()]).unwrap();
+        git::run(
+            Some(&clone_dir),
+            &[
+                "remote",
+                "set-url",
+                "origin",
+                "https://test.local/user/test-repo.git",
+            ],
+        )
+        .unwrap();
+        git::run(Some(&clone_dir), &["checkout", "-q", "-b", "feature-x"]).unwrap();
+
+        mirror::fetch(&paths.mirrors_dir, &parse_identity(&identity).unwrap()).unwrap();
+        let clones = scan_clones(&src).unwrap();
+        assert_eq!(clones.len(), 1, "non-repo dirs are skipped");
+        assert_eq!(clones[0].identity, identity);
+        assert_eq!(clones[0].dir_name, "api-checkout");
+
+        let ws_dir = adopt(&paths, &src, "feature-x", None, &clones).unwrap();
+        assert_eq!(ws_dir, dir(&paths.workspaces_dir, "feature-x"));
+        assert!(!src.exists(), "adopted dir is moved, not copied");
+
+        let meta = load_metadata(&ws_dir).unwrap();
+        assert_eq!(meta.branch, "feature-x");
+        assert!(meta.is_active(&identity));
+        assert_eq!(meta.dir_name(&identity).unwrap(), "api-checkout");
+        let moved = ws_dir.join("api-checkout");
+        let mirror_url = git::remote_url(&moved, "wsp-mirror").unwrap();
+        assert_eq!(
+            PathBuf::from(mirror_url),
+            mirror::dir(&paths.mirrors_dir, &parse_identity(&identity).unwrap())
+        );
+        assert!(git::ref_exists(&moved, "refs/remotes/wsp-mirror/main"));
+
+        let err = adopt(&paths, &ws_dir, "feature-x", None, &clones)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("already a workspace"), "got: {}", err);
+    }
+
+    #[test]
+    fn test_adopt_default_branch_is_context() {
+        let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
+        let hand_made = tempfile::tempdir().unwrap();
+        let src = fs::canonicalize(hand_made.path()).unwrap();
+        let clone_dir = src.join("test-repo");
+        git::run(
+            None,
+            &[
+                "clone",
+                "-q",
+                &upstream_urls[&identity],
+                clone_dir.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+        git::run(
+            Some(&clone_dir),
+            &[
+                "remote",
+                "set-url",
+                "origin",
+                "git@test.local:user/test-repo.git",
+            ],
+        )
+        .unwrap();
+
+        let clones = scan_clones(&src).unwrap();
+        let ws_dir = adopt(&paths, &src, "reading", Some("me"), &clones).unwrap();
+        let meta = load_metadata(&ws_dir).unwrap();
+        assert_eq!(meta.branch, "me/reading");
+        assert_eq!(meta.repos[&identity].as_ref().unwrap().r#ref, "main");
+        assert!(meta.dirs.is_empty());
+    }
+
+    #[test]
+    fn test_adopt_failure_removes_mirror_remotes() {
+        let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
+        let hand_made = tempfile::tempdir().unwrap();
+        let src = fs::canonicalize(hand_made.path()).unwrap();
+        for name in ["a", "b"] {
+            let clone_dir = src.join(name);
+            git::run(
+                None,
+                &[
+                    "clone",
+                    "-q",
+                    &upstream_urls[&identity],
+                    clone_dir.to_str().unwrap(),
+                ],
+            )
+            .unwrap();
+        }
+        // b fails the checks after a already got its wsp-mirror remote
+        let git_dir = git::run(
+            Some(&src.join("b")),
+            &["rev-parse", "--git-path", "rebase-merge"],
+        )
+        .unwrap();
+        fs::create_dir_all(src.join("b").join(git_dir)).unwrap();
+        let clones: Vec<FoundClone> = ["a", "b"]
+            .iter()
+            .map(|n| FoundClone {
+                dir_name: n.to_string(),
+                identity: identity.clone(),
+                url: upstream_urls[&identity].clone(),
+            })
+            .collect();
+
+        let err = adopt(&paths, &src, "half", None, &clones)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("rebase or merge in progress"), "got: {}", err);
+        assert!(git::remote_url(&src.join("a"), "wsp-mirror").is_err());
+        assert!(!src.join(METADATA_FILE).exists());
+        assert!(!dir(&paths.workspaces_dir, "half").exists());
+    }
+
     #[test]
     fn test_add_repos_to_existing_workspace() {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         // Create workspace with active repo
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "add-ws", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "add-ws",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "add-ws");
 
         // Try adding the same repo again — should skip
-        add_repos(&paths.mirrors_dir, &ws_dir, &refs, &upstream_urls).unwrap();
+        add_repos(
+            &paths.mirrors_dir,
+            &ws_dir,
+            &refs,
+            &BTreeMap::new(),
+            &upstream_urls,
+        )
+        .unwrap();
 
         let meta = load_metadata(&ws_dir).unwrap();
         assert_eq!(meta.repos.len(), 1);
@@ -1309,7 +2831,15 @@ mod tests {
         let (paths, _d, source_repo, identity1, mut upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity1, String::new())]);
-        create(&paths, "add-no-track", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "add-no-track",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "add-no-track");
 
@@ -1324,7 +2854,14 @@ mod tests {
         upstream_urls.extend(urls2);
 
         let add_refs = BTreeMap::from([(identity2, String::new())]);
-        add_repos(&paths.mirrors_dir, &ws_dir, &add_refs, &upstream_urls).unwrap();
+        add_repos(
+            &paths.mirrors_dir,
+            &ws_dir,
+            &add_refs,
+            &BTreeMap::new(),
+            &upstream_urls,
+        )
+        .unwrap();
 
         let clone_dir = ws_dir.join("added-repo");
         let result = git::run(Some(&clone_dir), &["rev-parse", "--verify", "@{upstream}"]);
@@ -1340,10 +2877,18 @@ mod tests {
 
         // Create workspace with context repo (pinned to "main")
         let refs = BTreeMap::from([(identity, "main".into())]);
-        create(&paths, "rm-ws-ctx", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-ws-ctx",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         // Remove should succeed without touching context repo branches
-        remove(&paths, "rm-ws-ctx", false).unwrap();
+        remove(&paths, "rm-ws-ctx", false, "wsp rm").unwrap();
     }
 
     /// Creates a second mirror with a different owner but same repo name.
@@ -1415,7 +2960,10 @@ mod tests {
             branch: "test".into(),
             repos: BTreeMap::from([("github.com/acme/utils".into(), None)]),
             created: Utc::now(),
+            base: None,
+            parent: None,
             dirs: BTreeMap::from([("github.com/acme/utils".into(), "acme-utils".into())]),
+            tasks: BTreeMap::new(),
         };
         assert_eq!(
             meta.dir_name("github.com/acme/utils").unwrap(),
@@ -1430,7 +2978,10 @@ mod tests {
             branch: "test".into(),
             repos: BTreeMap::from([("github.com/acme/utils".into(), None)]),
             created: Utc::now(),
+            base: None,
+            parent: None,
             dirs: BTreeMap::new(),
+            tasks: BTreeMap::new(),
         };
         assert_eq!(meta.dir_name("github.com/acme/utils").unwrap(), "utils");
     }
@@ -1464,7 +3015,15 @@ mod tests {
             (identity1.clone(), String::new()),
             (identity2.clone(), String::new()),
         ]);
-        create(&paths, "collide-ws", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "collide-ws",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "collide-ws");
         let meta = load_metadata(&ws_dir).unwrap();
@@ -1480,7 +3039,15 @@ mod tests {
         let (paths, _d, source_repo, identity1, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity1.clone(), String::new())]);
-        create(&paths, "add-collide", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "add-collide",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "add-collide");
         assert!(ws_dir.join("test-repo").exists());
@@ -1493,7 +3060,14 @@ mod tests {
             "test-repo",
         );
         let new_refs = BTreeMap::from([(identity2.clone(), String::new())]);
-        add_repos(&paths.mirrors_dir, &ws_dir, &new_refs, &urls2).unwrap();
+        add_repos(
+            &paths.mirrors_dir,
+            &ws_dir,
+            &new_refs,
+            &BTreeMap::new(),
+            &urls2,
+        )
+        .unwrap();
 
         let meta = load_metadata(&ws_dir).unwrap();
         assert_eq!(meta.dir_name(&identity1).unwrap(), "user-test-repo");
@@ -1520,7 +3094,15 @@ mod tests {
             (identity1.clone(), String::new()),
             (identity2.clone(), String::new()),
         ]);
-        create(&paths, "rm-repo-ws", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-repo-ws",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rm-repo-ws");
         assert!(ws_dir.join("test-repo").exists());
@@ -1541,7 +3123,15 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity, String::new())]);
-        create(&paths, "rm-repo-nf", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-repo-nf",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rm-repo-nf");
         let result = remove_repos(&ws_dir, &["test.local/nobody/fake".to_string()], false);
@@ -1559,7 +3149,15 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "rm-repo-dirty", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-repo-dirty",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rm-repo-dirty");
         let repo_dir = ws_dir.join("test-repo");
@@ -1575,7 +3173,15 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "rm-repo-force", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-repo-force",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rm-repo-force");
         let repo_dir = ws_dir.join("test-repo");
@@ -1605,7 +3211,15 @@ mod tests {
             (identity1.clone(), String::new()),
             (identity2.clone(), String::new()),
         ]);
-        create(&paths, "rm-repo-col", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-repo-col",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rm-repo-col");
         assert!(ws_dir.join("user-test-repo").exists());
@@ -1622,12 +3236,60 @@ mod tests {
         assert!(!ws_dir.join("other-test-repo").exists());
     }
 
+    #[test]
+    fn test_remove_repos_keeps_custom_dir_names() {
+        let (paths, _d, source_repo, identity1, mut upstream_urls) = setup_test_env();
+        let (identity2, urls2) = add_mirror_with_owner(
+            &paths,
+            source_repo.path(),
+            "test.local",
+            "other",
+            "another-repo",
+        );
+        upstream_urls.extend(urls2);
+
+        let refs = BTreeMap::from([
+            (identity1.clone(), String::new()),
+            (identity2.clone(), String::new()),
+        ]);
+        create(
+            &paths,
+            "rm-repo-custom",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
+        let ws_dir = dir(&paths.workspaces_dir, "rm-repo-custom");
+
+        // A hand-picked dir name, as `wsp adopt` records for existing clones
+        fs::rename(ws_dir.join("test-repo"), ws_dir.join("my-checkout")).unwrap();
+        let mut meta = load_metadata(&ws_dir).unwrap();
+        meta.dirs.insert(identity1.clone(), "my-checkout".into());
+        save_metadata(&ws_dir, &meta).unwrap();
+
+        remove_repos(&ws_dir, std::slice::from_ref(&identity2), false).unwrap();
+
+        let meta = load_metadata(&ws_dir).unwrap();
+        assert_eq!(meta.dir_name(&identity1).unwrap(), "my-checkout");
+        assert!(ws_dir.join("my-checkout").exists());
+    }
+
     #[test]
     fn test_remove_repos_context_repo() {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), "main".into())]);
-        create(&paths, "rm-repo-ctx", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-repo-ctx",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rm-repo-ctx");
         remove_repos(&ws_dir, &[identity.clone()], false).unwrap();
@@ -1724,7 +3386,15 @@ mod tests {
         let (paths, _d, source_repo, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "rm-squash", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-squash",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rm-squash");
         let repo_dir = ws_dir.join("test-repo");
@@ -1733,7 +3403,7 @@ mod tests {
         squash_merge_branch(source_repo.path(), "rm-squash", "main");
 
         // Remove should succeed without --force since branch is squash-merged
-        remove(&paths, "rm-squash", false).unwrap();
+        remove(&paths, "rm-squash", false, "wsp rm").unwrap();
         assert!(!ws_dir.exists());
     }
 
@@ -1742,14 +3412,22 @@ mod tests {
         let (paths, _d, _source_repo, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "rm-pushed", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-pushed",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rm-pushed");
         let repo_dir = ws_dir.join("test-repo");
 
         commit_push_and_track(&repo_dir, "rm-pushed", "wip.txt", "wip");
 
-        let result = remove(&paths, "rm-pushed", false);
+        let result = remove(&paths, "rm-pushed", false, "wsp rm");
         assert!(result.is_err());
         let err = result.unwrap_err().to_string();
         assert!(
@@ -1765,7 +3443,15 @@ mod tests {
         let (paths, _d, source_repo, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "rmr-squash", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rmr-squash",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rmr-squash");
         let repo_dir = ws_dir.join("test-repo");
@@ -1783,7 +3469,15 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "rmr-pushed", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rmr-pushed",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rmr-pushed");
         let repo_dir = ws_dir.join("test-repo");
@@ -1805,7 +3499,15 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "two-remotes", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "two-remotes",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "two-remotes");
         let clone_dir = ws_dir.join("test-repo");
@@ -1838,13 +3540,21 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "rm-no-mirror", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-no-mirror",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         // The workspace branch should NOT exist in the mirror (clones are independent)
         let parsed = parse_identity(&identity).unwrap();
         let mirror_dir = mirror::dir(&paths.mirrors_dir, &parsed);
 
-        remove(&paths, "rm-no-mirror", false).unwrap();
+        remove(&paths, "rm-no-mirror", false, "wsp rm").unwrap();
 
         // Mirror should still exist and be intact
         assert!(mirror_dir.exists());
@@ -1855,7 +3565,15 @@ mod tests {
         let (paths, _d, source_repo, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "prop-ws", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "prop-ws",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "prop-ws");
         let clone_dir = ws_dir.join("test-repo");
@@ -1908,7 +3626,15 @@ mod tests {
         let (paths, _d, _r, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "origin-refs", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "origin-refs",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "origin-refs");
         let clone_dir = ws_dir.join("test-repo");
@@ -1925,7 +3651,15 @@ mod tests {
         let (paths, _d, source_repo, identity, upstream_urls) = setup_test_env();
 
         let refs = BTreeMap::from([(identity.clone(), String::new())]);
-        create(&paths, "rm-div-squash", &refs, None, &upstream_urls).unwrap();
+        create(
+            &paths,
+            "rm-div-squash",
+            &refs,
+            &Bases::default(),
+            None,
+            &upstream_urls,
+        )
+        .unwrap();
 
         let ws_dir = dir(&paths.workspaces_dir, "rm-div-squash");
         let repo_dir = ws_dir.join("test-repo");
@@ -1977,7 +3711,7 @@ mod tests {
         assert!(out.status.success());
 
         // Remove should succeed without --force
-        remove(&paths, "rm-div-squash", false).unwrap();
+        remove(&paths, "rm-div-squash", false, "wsp rm").unwrap();
         assert!(!ws_dir.exists());
     }
 }